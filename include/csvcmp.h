@@ -0,0 +1,41 @@
+/* C interface of the csv-compare library (libcsv_compare). */
+#ifndef CSVCMP_H
+#define CSVCMP_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * Compares a column of the orig and diff CSV texts. options is a JSON
+ * object such as {"orig_index": 2, "with_headers": true}, or NULL.
+ * Returns a JSON document: the comparison, or on failure an object with a
+ * stable "code", a "message" and its "causes". Release it with csvcmp_free.
+ */
+char *csvcmp_compare(const char *orig, const char *diff, const char *options);
+
+/*
+ * Like csvcmp_compare, for CSV data in byte buffers that need not be
+ * NUL-terminated or UTF-8.
+ */
+char *csvcmp_compare_bytes(const unsigned char *orig, size_t orig_len,
+                           const unsigned char *diff, size_t diff_len,
+                           const char *options);
+
+/* Releases a document returned by csvcmp_compare. NULL is ignored. */
+void csvcmp_free(char *document);
+
+/*
+ * Allocate and release input buffers, for hosts such as WebAssembly
+ * runtimes that have no allocator of their own in the module's memory.
+ */
+unsigned char *csvcmp_alloc(size_t len);
+void csvcmp_dealloc(unsigned char *data, size_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
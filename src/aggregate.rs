@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+use csv::{StringRecord, Writer};
+use tempfile::TempDir;
+
+use crate::cli::{CompareArgs, ReaderArgs};
+use crate::errors::Category;
+use crate::expr::ColumnRef;
+use crate::parse::{Table, field, read_table, same_header};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Sum,
+    Count,
+    Min,
+    Max,
+    Avg,
+}
+
+/// A pre-aggregation such as `sum(amount), count(id) by customer_id`,
+/// computing each function over the rows of every group.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregate {
+    functions: Vec<(Function, ColumnRef, String)>,
+    by: Vec<(ColumnRef, String)>,
+}
+
+fn column(source: &str) -> Result<ColumnRef, String> {
+    match source.trim() {
+        "" => Err("missing column".to_string()),
+        name => Ok(match name.parse::<usize>() {
+            Ok(index) if index > 0 => ColumnRef::Index(index),
+            _ => ColumnRef::Name(name.to_string()),
+        }),
+    }
+}
+
+/// Parses `FUNC(COLUMN), … by COLUMN, …`, with functions `sum`, `count`,
+/// `min`, `max` and `avg`, and columns by header name or 1-based index.
+pub fn parse_aggregate(source: &str) -> Result<Aggregate, String> {
+    let (functions, by) = source
+        .rsplit_once(" by ")
+        .ok_or("expected `FUNC(COLUMN), … by COLUMN, …`")?;
+    let functions = functions
+        .split(',')
+        .map(|function| {
+            let function = function.trim();
+            let (name, rest) = function
+                .split_once('(')
+                .ok_or_else(|| format!("expected `FUNC(COLUMN)`, found `{function}`"))?;
+            let argument = rest
+                .strip_suffix(')')
+                .ok_or_else(|| format!("missing `)` in `{function}`"))?;
+            let name = name.trim().to_lowercase();
+            let func = match name.as_str() {
+                "sum" => Function::Sum,
+                "count" => Function::Count,
+                "min" => Function::Min,
+                "max" => Function::Max,
+                "avg" => Function::Avg,
+                _ => return Err(format!("unknown function `{name}`")),
+            };
+            Ok((
+                func,
+                column(argument)?,
+                format!("{name}({})", argument.trim()),
+            ))
+        })
+        .collect::<Result<_, String>>()?;
+    let by = by
+        .split(',')
+        .map(|name| Ok((column(name)?, name.trim().to_string())))
+        .collect::<Result<_, String>>()?;
+    Ok(Aggregate { functions, by })
+}
+
+/// 1-based index of `column` in `table`.
+fn resolve(column: &ColumnRef, table: &Table, path: &Path, strict: bool) -> Result<usize> {
+    match column {
+        ColumnRef::Index(index) => Ok(*index),
+        ColumnRef::Name(name) => table
+            .headers
+            .as_ref()
+            .and_then(|headers| {
+                headers
+                    .iter()
+                    .position(|header| same_header(header, name, strict))
+            })
+            .map(|position| position + 1)
+            .ok_or_else(|| {
+                Category::BadIndex.error(format!(
+                    "--aggregate: no column `{name}` in {} (names need --with-headers)",
+                    path.display()
+                ))
+            }),
+    }
+}
+
+fn decimals(value: &str) -> usize {
+    value.split_once('.').map_or(0, |(_, frac)| frac.len())
+}
+
+/// State of one function over the rows of a group.
+#[derive(Default)]
+struct Accumulator {
+    count: usize,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+    precision: usize,
+}
+
+impl Accumulator {
+    fn add(&mut self, function: Function, value: &str) -> Result<(), ()> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(());
+        }
+        self.count += 1;
+        if function == Function::Count {
+            return Ok(());
+        }
+        let number: f64 = value.parse().map_err(|_| ())?;
+        self.sum += number;
+        self.min = Some(self.min.map_or(number, |min| min.min(number)));
+        self.max = Some(self.max.map_or(number, |max| max.max(number)));
+        self.precision = self.precision.max(decimals(value));
+        Ok(())
+    }
+
+    fn result(&self, function: Function) -> String {
+        let precision = self.precision;
+        let number = |number: Option<f64>| {
+            number.map_or(String::new(), |number| format!("{number:.precision$}"))
+        };
+        match function {
+            Function::Sum => format!("{:.precision$}", self.sum),
+            Function::Count => self.count.to_string(),
+            Function::Min => number(self.min),
+            Function::Max => number(self.max),
+            Function::Avg => number((self.count > 0).then(|| self.sum / self.count as f64)),
+        }
+    }
+}
+
+impl Aggregate {
+    /// Header and one row per group of `table`, groups in order of their
+    /// first row.
+    fn apply(&self, table: &Table, path: &Path, strict: bool) -> Result<Vec<Vec<String>>> {
+        let resolve = |column| resolve(column, table, path, strict);
+        let by = self
+            .by
+            .iter()
+            .map(|(column, _)| resolve(column))
+            .collect::<Result<Vec<_>>>()?;
+        let functions = self
+            .functions
+            .iter()
+            .map(|(function, column, _)| Ok((*function, resolve(column)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut groups: Vec<(Vec<String>, Vec<Accumulator>)> = Vec::new();
+        let mut positions: HashMap<Vec<String>, usize> = HashMap::new();
+        for record in &table.records {
+            let key = by
+                .iter()
+                .map(|&index| field(record, index).map(str::to_string))
+                .collect::<Result<Vec<_>>>()?;
+            let position = *positions.entry(key.clone()).or_insert_with(|| {
+                groups.push((
+                    key,
+                    functions.iter().map(|_| Accumulator::default()).collect(),
+                ));
+                groups.len() - 1
+            });
+            for ((function, index), accumulator) in functions.iter().zip(&mut groups[position].1) {
+                let value = field(record, *index)?;
+                accumulator
+                    .add(*function, value)
+                    .map_err(|()| not_a_number(value, record, path))?;
+            }
+        }
+
+        let header = self
+            .by
+            .iter()
+            .map(|(_, name)| name)
+            .chain(self.functions.iter().map(|(_, _, name)| name))
+            .cloned()
+            .collect();
+        let rows = groups.into_iter().map(|(key, accumulators)| {
+            let values = functions
+                .iter()
+                .zip(&accumulators)
+                .map(|((function, _), accumulator)| accumulator.result(*function));
+            key.into_iter().chain(values).collect()
+        });
+        Ok(std::iter::once(header).chain(rows).collect())
+    }
+}
+
+fn not_a_number(value: &str, record: &StringRecord, path: &Path) -> anyhow::Error {
+    Category::Parse.error(format!(
+        "--aggregate: `{value}` at line {} of {} is not a number",
+        record.position().map_or(0, |pos| pos.line()),
+        path.display()
+    ))
+}
+
+/// Replaces the inputs of `--aggregate` by temporary files of their groups,
+/// joined on the group columns. The returned directory must be kept alive
+/// while the files are read.
+pub fn write(args: &CompareArgs) -> Result<Option<(CompareArgs, TempDir)>> {
+    let Some(aggregate) = &args.aggregate else {
+        return Ok(None);
+    };
+    if args.orig.is_dir() || args.diff.is_dir() {
+        return Err(anyhow!("--aggregate compares single files"));
+    }
+    let dir = tempfile::tempdir()?;
+    let mut resolved = args.clone();
+    for (path, name) in [(&args.orig, "orig.csv"), (&args.diff, "diff.csv")] {
+        let table = read_table(path, &args.parse.reader)?;
+        let target = dir.path().join(name);
+        let mut writer = Writer::from_path(&target)?;
+        for row in aggregate.apply(&table, path, args.strict_headers)? {
+            writer.write_record(row)?;
+        }
+        writer.flush()?;
+        if name == "orig.csv" {
+            resolved.orig = target;
+        } else {
+            resolved.diff = target;
+        }
+    }
+    resolved.aggregate = None;
+    resolved.join = (1..=aggregate.by.len()).collect();
+    resolved.parse.reader = ReaderArgs {
+        with_headers: true,
+        ..Default::default()
+    };
+    Ok(Some((resolved, dir)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggregate() {
+        let aggregate = parse_aggregate("sum(amount), count(2), avg(amount) by customer").unwrap();
+        let table = Table {
+            headers: Some(StringRecord::from(vec!["customer", "amount"])),
+            records: [["a", "1.5"], ["b", "2"], ["a", "3.25"], ["a", ""]]
+                .into_iter()
+                .map(|record| StringRecord::from(record.to_vec()))
+                .collect(),
+        };
+        assert_eq!(
+            aggregate.apply(&table, Path::new("t.csv"), false).unwrap(),
+            [
+                vec!["customer", "sum(amount)", "count(2)", "avg(amount)"],
+                vec!["a", "4.75", "2", "2.38"],
+                vec!["b", "2", "1", "2"],
+            ]
+        );
+
+        assert!(parse_aggregate("sum(amount)").is_err());
+        assert!(parse_aggregate("median(amount) by id").is_err());
+        let table = Table {
+            headers: None,
+            records: vec![StringRecord::from(vec!["a", "x"])],
+        };
+        let aggregate = parse_aggregate("sum(2) by 1").unwrap();
+        assert!(aggregate.apply(&table, Path::new("t.csv"), false).is_err());
+    }
+}
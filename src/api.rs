@@ -0,0 +1,95 @@
+use anyhow::Result;
+
+use crate::cli::{Cli, Command};
+use crate::errors::Category;
+use crate::json::Value;
+use crate::parse::parse_bytes;
+use crate::provenance::Provenance;
+use crate::redact::Masking;
+use crate::report;
+use crate::sort::sort_values;
+
+/// Options `compare` accepts, named like the command line flags with `_`
+/// for `-`. Anything that reads or writes other files or runs commands is
+/// left out, since callers may pass options from untrusted requests.
+const OPTIONS: &[&str] = &[
+    "orig_index",
+    "diff_index",
+    "with_headers",
+    "raw",
+    "strip_quotes",
+    "lossy_utf8",
+    "bytes",
+    "with_prefix",
+    "normalize_bools",
+    "round",
+    "where",
+    "sort",
+    "sort_order",
+    "redact",
+    "hash_values",
+    "salt",
+    "chunk_rows",
+];
+
+/// Command line flag of an option, as a single `--name=value` argument so
+/// that values can never be taken for other flags.
+fn option_arg(name: &str, value: &Value) -> Result<Option<String>> {
+    if !OPTIONS.contains(&name) {
+        return Err(Category::Parse.error(format!("unknown option `{name}`")));
+    }
+    let flag = format!("--{}", name.replace('_', "-"));
+    Ok(match value {
+        Value::Bool(true) => Some(flag),
+        Value::Bool(false) | Value::Null => None,
+        Value::Number(number) => Some(format!("{flag}={number}")),
+        Value::String(text) => Some(format!("{flag}={text}")),
+        _ => return Err(Category::Parse.error(format!("option `{name}` must be a scalar"))),
+    })
+}
+
+/// Compares a column of the `orig` and `diff` CSV texts with the same
+/// semantics as the command line. `options` is a JSON object such as
+/// `{"orig_index": 2, "with_headers": true}`, or null. Returns the document
+/// `--template` renders: counts, hunks, inputs and provenance.
+pub fn compare(orig: &str, diff: &str, options: &Value) -> Result<Value> {
+    compare_bytes(orig.as_bytes(), diff.as_bytes(), options)
+}
+
+/// Like `compare`, for CSV data that need not be UTF-8 (see the
+/// `lossy_utf8` option). Nothing touches the filesystem.
+pub fn compare_bytes(orig: &[u8], diff: &[u8], options: &Value) -> Result<Value> {
+    let mut argv = ["csv-compare", "compare", "orig.csv", "diff.csv"]
+        .map(String::from)
+        .to_vec();
+    match options {
+        Value::Object(options) => {
+            for (name, value) in options {
+                argv.extend(option_arg(name, value)?);
+            }
+        }
+        Value::Null => {}
+        _ => return Err(Category::Parse.error("options must be an object")),
+    }
+
+    let cli = Cli::parse_given(argv).map_err(|err| Category::Parse.error(err.to_string()))?;
+    let Command::Compare(args) = cli.command else {
+        unreachable!("the options are parsed as a compare command");
+    };
+    let orig_index = args
+        .orig_index
+        .ok_or_else(|| Category::BadIndex.error("`orig_index` is required"))?;
+    let mut orig_lines = parse_bytes(orig, orig_index, &args.parse)?;
+    let mut diff_lines = parse_bytes(diff, args.diff_index.unwrap_or(orig_index), &args.parse)?;
+    if args.sort {
+        sort_values(&mut orig_lines, args.sort_order);
+        sort_values(&mut diff_lines, args.sort_order);
+    }
+    Ok(report::comparison(
+        ("orig", "diff"),
+        (&orig_lines, &diff_lines),
+        &Masking::new(&args),
+        args.chunk_rows,
+        &Provenance::in_memory(&args, (orig, diff)),
+    ))
+}
@@ -0,0 +1,113 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+
+use anyhow::Result;
+
+use crate::cli::ApplyArgs;
+use crate::errors::Category;
+use crate::parse::load_column;
+
+/// Start line and length of one side of a hunk header range, `-3,2` or `+5`.
+fn range(range: &str) -> Option<(usize, usize)> {
+    let range = &range[1..];
+    match range.split_once(',') {
+        Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+/// Applies the unified diff `patch`, as written by `--patch-output`, to the
+/// column `values`. Context and removed lines must match the values.
+pub fn apply_patch(values: &[String], patch: &str) -> Result<Vec<String>> {
+    let mismatch = |line: usize, expected: &str| {
+        Category::Parse.error(format!(
+            "patch does not apply: value {} is {:?}, the patch expects {expected:?}",
+            line + 1,
+            values.get(line).map_or("", String::as_str)
+        ))
+    };
+    let mut res = Vec::with_capacity(values.len());
+    let mut pos = 0;
+    let mut in_hunk = false;
+    for line in patch.lines() {
+        if let Some(header) = line.strip_prefix("@@ ") {
+            let old = header
+                .split_whitespace()
+                .next()
+                .filter(|old| old.starts_with('-'))
+                .and_then(range)
+                .ok_or_else(|| Category::Parse.error(format!("invalid hunk header `{line}`")))?;
+            // An empty range starts after its line, a non-empty one at it.
+            let start = if old.1 == 0 { old.0 } else { old.0 - 1 };
+            if start < pos || start > values.len() {
+                return Err(Category::Parse.error(format!(
+                    "patch does not apply: hunk `{line}` is out of order or range"
+                )));
+            }
+            res.extend_from_slice(&values[pos..start]);
+            pos = start;
+            in_hunk = true;
+            continue;
+        }
+        if !in_hunk {
+            continue;
+        }
+        let (tag, value) = line.split_at(line.len().min(1));
+        match tag {
+            " " | "-" => {
+                if values.get(pos).map(String::as_str) != Some(value) {
+                    return Err(mismatch(pos, value));
+                }
+                if tag == " " {
+                    res.push(value.to_string());
+                }
+                pos += 1;
+            }
+            "+" => res.push(value.to_string()),
+            _ => {
+                return Err(Category::Parse.error(format!("invalid patch line `{line}`")));
+            }
+        }
+    }
+    res.extend_from_slice(&values[pos..]);
+    Ok(res)
+}
+
+pub fn run(args: &ApplyArgs) -> Result<()> {
+    let values = load_column(&args.parse, &args.file, args.index)?;
+    let patch = fs::read_to_string(&args.patch)?;
+    let patched = apply_patch(&values, &patch)?;
+    let mut output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+    for value in patched {
+        writeln!(output, "{value}")?;
+    }
+    output.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_patch() {
+        let values: Vec<String> = ["a", "b", "c", "d", "e"].map(String::from).to_vec();
+        let patch = "--- a/orig.csv\n+++ b/diff.csv\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n@@ -5 +5,2 @@\n e\n+f\n";
+        assert_eq!(
+            apply_patch(&values, patch).unwrap(),
+            ["a", "B", "c", "d", "e", "f"]
+        );
+        assert_eq!(
+            apply_patch(&values, "@@ -0,0 +1 @@\n+z\n").unwrap(),
+            ["z", "a", "b", "c", "d", "e"]
+        );
+        let err = apply_patch(&values, "@@ -2 +2 @@\n-x\n+y\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "patch does not apply: value 2 is \"b\", the patch expects \"x\""
+        );
+    }
+}
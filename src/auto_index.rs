@@ -0,0 +1,127 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+
+use crate::cli::CompareArgs;
+use crate::errors::Category;
+use crate::i18n::tr;
+use crate::parse::{Table, sample_table};
+
+/// Records sampled from each file to pick the columns.
+const SAMPLE_RECORDS: usize = 1000;
+
+fn column_sets(table: &Table) -> Vec<HashSet<&str>> {
+    let width = table
+        .records
+        .iter()
+        .map(|record| record.len())
+        .max()
+        .unwrap_or(0);
+    (0..width)
+        .map(|index| {
+            table
+                .records
+                .iter()
+                .filter_map(|record| record.get(index))
+                .filter(|value| !value.is_empty())
+                .collect()
+        })
+        .collect()
+}
+
+/// 1-based orig and diff indices of the column pair whose sampled values
+/// overlap most, by Jaccard similarity. Ties go to pairs at the same index,
+/// then to the leftmost pair.
+fn best_pair(orig: &Table, diff: &Table) -> Option<(usize, usize, f64)> {
+    let (orig_sets, diff_sets) = (column_sets(orig), column_sets(diff));
+    let mut best: Option<(usize, usize, f64)> = None;
+    for (orig_index, orig_values) in orig_sets.iter().enumerate() {
+        for (diff_index, diff_values) in diff_sets.iter().enumerate() {
+            let union = orig_values.union(diff_values).count();
+            if union == 0 {
+                continue;
+            }
+            let similarity = orig_values.intersection(diff_values).count() as f64 / union as f64;
+            let better = match best {
+                None => true,
+                Some((_, _, best_similarity)) if similarity != best_similarity => {
+                    similarity > best_similarity
+                }
+                Some((best_orig, best_diff, _)) => {
+                    orig_index == diff_index && best_orig != best_diff
+                }
+            };
+            if better {
+                best = Some((orig_index, diff_index, similarity));
+            }
+        }
+    }
+    best.map(|(orig_index, diff_index, similarity)| (orig_index + 1, diff_index + 1, similarity))
+}
+
+/// Fills in `--orig-index` and `--diff-index` for `--auto-index` from a
+/// sample of both files, announcing the choice so the prompt can confirm it.
+pub fn resolve(args: &CompareArgs) -> Result<Option<CompareArgs>> {
+    if !args.auto_index || args.orig_index.is_some() {
+        return Ok(None);
+    }
+    let orig = sample_table(&args.orig, &args.parse.reader, SAMPLE_RECORDS)?;
+    let diff = sample_table(&args.diff, &args.parse.reader, SAMPLE_RECORDS)?;
+    let (orig_index, diff_index, similarity) = best_pair(&orig, &diff)
+        .ok_or_else(|| Category::BadIndex.error("--auto-index found no values to compare"))?;
+    let name = |table: &Table, index: usize| match table
+        .headers
+        .as_ref()
+        .and_then(|headers| headers.get(index - 1))
+    {
+        Some(header) => format!("{index} ({header})"),
+        None => index.to_string(),
+    };
+    let shared = format!("{:.0}", similarity * 100.0);
+    eprintln!(
+        "{}",
+        tr(
+            "auto-index",
+            &[
+                ("orig", &name(&orig, orig_index)),
+                ("diff", &name(&diff, diff_index)),
+                ("shared", &shared),
+            ]
+        )
+    );
+    let mut resolved = args.clone();
+    resolved.orig_index = Some(orig_index);
+    resolved.diff_index = Some(diff_index);
+    Ok(Some(resolved))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use csv::StringRecord;
+
+    fn table(rows: &[&[&str]]) -> Table {
+        Table {
+            headers: None,
+            records: rows
+                .iter()
+                .map(|row| StringRecord::from(row.to_vec()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_best_pair() {
+        let orig = table(&[&["1", "eu", "x"], &["2", "us", "y"]]);
+        let diff = table(&[&["us", "1"], &["eu", "3"], &["apac", "4"]]);
+        let (orig_index, diff_index, similarity) = best_pair(&orig, &diff).unwrap();
+        assert_eq!((orig_index, diff_index), (2, 1));
+        assert!((similarity - 2.0 / 3.0).abs() < 1e-9);
+
+        let same = table(&[&["a", "a"]]);
+        assert_eq!(
+            best_pair(&same, &same).map(|(o, d, _)| (o, d)),
+            Some((1, 1))
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::logging;
+use crate::sha256::{self, Sha256};
+
+/// Bumped whenever the on-disk layout of cached columns changes.
+const CACHE_VERSION: &str = "1";
+
+/// Cached column data of a single input file, keyed by its content hash and
+/// the parse options that produced it.
+pub struct ColumnCache {
+    path: PathBuf,
+}
+
+impl ColumnCache {
+    pub fn new(dir: &Path, input: &Path, options: &[String]) -> Result<Self> {
+        let mut hasher = Sha256::new();
+        hasher.update(CACHE_VERSION.as_bytes());
+        hasher.update(&sha256::hash_file(input)?);
+        for option in options {
+            hasher.update(&(option.len() as u64).to_le_bytes());
+            hasher.update(option.as_bytes());
+        }
+        let key = sha256::to_hex(&hasher.finalize());
+        Ok(Self {
+            path: dir.join(format!("{key}.col")),
+        })
+    }
+
+    /// The cached values, or `None` if there are none or the entry is
+    /// corrupted, in which case the caller parses the input again.
+    pub fn load(&self) -> Result<Option<Vec<String>>> {
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let values = decode(&data);
+        if values.is_none() {
+            logging::warn(
+                "cache-corrupted",
+                &[("entry", self.path.display().to_string())],
+            );
+        }
+        Ok(values)
+    }
+
+    pub fn store(&self, values: &[String]) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Unique per writer, as parallel comparisons may store the same key.
+        let tmp = self
+            .path
+            .with_extension(format!("{:016x}.tmp", fastrand::u64(..)));
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        writer.write_all(&(values.len() as u64).to_le_bytes())?;
+        for value in values {
+            writer.write_all(&(value.len() as u64).to_le_bytes())?;
+            writer.write_all(value.as_bytes())?;
+        }
+        writer.flush()?;
+        drop(writer);
+        fs::rename(tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Splits a length-prefixed field off `data`. Lengths are checked against
+/// the bytes left, so a corrupted length cannot allocate past the entry.
+fn take_field(data: &mut &[u8]) -> Option<u64> {
+    let (len, rest) = data.split_first_chunk::<8>()?;
+    *data = rest;
+    Some(u64::from_le_bytes(*len))
+}
+
+fn decode(mut data: &[u8]) -> Option<Vec<String>> {
+    let count = take_field(&mut data)?;
+    // Every value takes at least its 8-byte length.
+    if count > (data.len() / 8) as u64 {
+        return None;
+    }
+    let mut values = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let len = take_field(&mut data)?;
+        if len > data.len() as u64 {
+            return None;
+        }
+        let (value, rest) = data.split_at(len as usize);
+        values.push(String::from_utf8(value.to_vec()).ok()?);
+        data = rest;
+    }
+    data.is_empty().then_some(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::{FileWriteStr, PathChild};
+
+    #[test]
+    fn test_column_cache() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let input = dir.child("input.csv");
+        input.write_str("a,b\nc,d").unwrap();
+        let options = vec!["1".to_string()];
+
+        let cache = ColumnCache::new(&dir.path().join("cache"), input.path(), &options).unwrap();
+        assert!(cache.load().unwrap().is_none());
+        let values = vec!["a".to_string(), "".to_string(), "ü".to_string()];
+        cache.store(&values).unwrap();
+        assert_eq!(cache.load().unwrap(), Some(values));
+
+        fs::write(&cache.path, [&u64::MAX.to_le_bytes()[..], b"x"].concat()).unwrap();
+        assert!(cache.load().unwrap().is_none());
+        fs::write(&cache.path, [0u8; 3]).unwrap();
+        assert!(cache.load().unwrap().is_none());
+        let truncated = [&1u64.to_le_bytes()[..], &u64::MAX.to_le_bytes()].concat();
+        fs::write(&cache.path, truncated).unwrap();
+        assert!(cache.load().unwrap().is_none());
+
+        let other = vec!["2".to_string()];
+        let cache = ColumnCache::new(&dir.path().join("cache"), input.path(), &other).unwrap();
+        assert!(cache.load().unwrap().is_none());
+    }
+}
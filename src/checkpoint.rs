@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+#[cfg(unix)]
+use std::ffi::OsStr;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Result, anyhow};
+use tempfile::NamedTempFile;
+
+use crate::cli::CompareArgs;
+use crate::sha256::Sha256;
+
+const MAGIC: &[u8; 8] = b"CSVCKPT1";
+
+/// Outcomes of compared directory pairs, appended as each pair finishes so
+/// that `--resume` can skip them after an interruption. A pair interrupted
+/// midway has no record and is compared again from its start. The file
+/// starts with `MAGIC` and the SHA-256 of the compare options; each record is
+/// a status byte (`0` identical, `1` differ), the length of the path as a
+/// little-endian `u64` and the raw bytes of the path.
+pub struct Checkpoint {
+    file: Mutex<File>,
+    done: HashMap<PathBuf, bool>,
+}
+
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> &[u8] {
+    path.as_os_str().as_bytes()
+}
+
+#[cfg(unix)]
+fn bytes_path(bytes: &[u8]) -> Option<PathBuf> {
+    Some(OsStr::from_bytes(bytes).into())
+}
+
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> &[u8] {
+    path.as_os_str().as_encoded_bytes()
+}
+
+// Encoded bytes only convert back safely as UTF-8; other paths are compared
+// again on resume.
+#[cfg(not(unix))]
+fn bytes_path(bytes: &[u8]) -> Option<PathBuf> {
+    std::str::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+fn write_record(file: &mut File, path: &Path, differ: bool) -> Result<()> {
+    let bytes = path_bytes(path);
+    let mut record = Vec::with_capacity(9 + bytes.len());
+    record.push(u8::from(differ));
+    record.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+    record.extend_from_slice(bytes);
+    file.write_all(&record)?;
+    Ok(())
+}
+
+/// Hash of the options the recorded outcomes depend on: all given ones but
+/// the checkpoint's own and `--jobs`.
+fn options_hash(args: &CompareArgs) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for (name, value) in &args.given {
+        if ["checkpoint", "resume", "jobs"].contains(&name.as_str()) {
+            continue;
+        }
+        let option = format!("{name}={value}");
+        hasher.update(&(option.len() as u64).to_le_bytes());
+        hasher.update(option.as_bytes());
+    }
+    hasher.finalize()
+}
+
+fn parse(mut contents: &[u8]) -> HashMap<PathBuf, bool> {
+    let mut done = HashMap::new();
+    // A run killed mid-write leaves a partial last record, which is dropped,
+    // as is anything after a record that does not parse.
+    while let [status @ (0 | 1), rest @ ..] = contents
+        && let Some((len, rest)) = rest.split_first_chunk::<8>()
+        && let Some(len) = usize::try_from(u64::from_le_bytes(*len))
+            .ok()
+            .filter(|&len| len <= rest.len())
+    {
+        let (path, rest) = rest.split_at(len);
+        if let Some(path) = bytes_path(path) {
+            done.insert(path, *status == 1);
+        }
+        contents = rest;
+    }
+    done
+}
+
+impl Checkpoint {
+    /// Opens the checkpoint at `path`, keeping its recorded pairs with
+    /// `--resume` and starting over otherwise. Resuming a checkpoint written
+    /// with other options is refused, as its outcomes may not hold.
+    pub fn open(path: &Path, args: &CompareArgs) -> Result<Self> {
+        let hash = options_hash(args);
+        let done = match fs::read(path) {
+            Ok(contents) if args.resume => {
+                let records = contents
+                    .strip_prefix(MAGIC)
+                    .and_then(|rest| rest.split_first_chunk::<32>())
+                    .filter(|(recorded, _)| **recorded == hash)
+                    .map(|(_, records)| records)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "{} was written with other options; compare again without --resume",
+                            path.display()
+                        )
+                    })?;
+                parse(records)
+            }
+            Err(err) if err.kind() != ErrorKind::NotFound => return Err(err.into()),
+            _ => HashMap::new(),
+        };
+        // The kept records are written to a new file that replaces the old
+        // one, so an interruption meanwhile leaves the old one intact.
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut temp = NamedTempFile::new_in(dir)?;
+        temp.write_all(MAGIC)?;
+        temp.write_all(&hash)?;
+        for (pair, differ) in &done {
+            write_record(temp.as_file_mut(), pair, *differ)?;
+        }
+        temp.as_file().sync_all()?;
+        temp.persist(path)?;
+        Ok(Self {
+            file: Mutex::new(OpenOptions::new().append(true).open(path)?),
+            done,
+        })
+    }
+
+    /// Whether the pair at `path` differed, if a previous run compared it.
+    pub fn get(&self, path: &Path) -> Option<bool> {
+        self.done.get(path).copied()
+    }
+
+    pub fn record(&self, path: &Path, differ: bool) -> Result<()> {
+        let mut file = self.file.lock().unwrap();
+        write_record(&mut file, path, differ)?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::Value;
+    use assert_fs::prelude::{FileWriteBin, PathChild};
+
+    #[test]
+    fn test_resume() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.child("checkpoint");
+        let mut args =
+            crate::cli::tests::compare_args(&["-o", "1", "--checkpoint", "c", "--resume"]);
+        args.given = vec![("orig-index".to_string(), Value::String("1".to_string()))];
+        let mut contents = MAGIC.to_vec();
+        contents.extend_from_slice(&options_hash(&args));
+        for (status, name) in [(0, "a.csv"), (1, "sub/b\tx\n.csv")] {
+            contents.push(status);
+            contents.extend_from_slice(&(name.len() as u64).to_le_bytes());
+            contents.extend_from_slice(name.as_bytes());
+        }
+        // Partial record of an interrupted write.
+        contents.extend_from_slice(&[1, 5, 0, 0, 0, 0, 0, 0, 0, b'c']);
+        path.write_binary(&contents).unwrap();
+
+        let checkpoint = Checkpoint::open(path.path(), &args).unwrap();
+        assert_eq!(checkpoint.get(Path::new("a.csv")), Some(false));
+        assert_eq!(checkpoint.get(Path::new("sub/b\tx\n.csv")), Some(true));
+        assert_eq!(checkpoint.get(Path::new("c")), None);
+        checkpoint.record(Path::new("c.csv"), true).unwrap();
+        drop(checkpoint);
+
+        let checkpoint = Checkpoint::open(path.path(), &args).unwrap();
+        assert_eq!(checkpoint.get(Path::new("c.csv")), Some(true));
+        drop(checkpoint);
+
+        // Other options than those recorded, but --jobs, refuse to resume.
+        args.given
+            .push(("jobs".to_string(), Value::String("4".to_string())));
+        assert!(Checkpoint::open(path.path(), &args).is_ok());
+        args.given[0].1 = Value::String("2".to_string());
+        let err = Checkpoint::open(path.path(), &args).err().unwrap();
+        assert!(err.to_string().contains("other options"), "{err}");
+        args.given[0].1 = Value::String("1".to_string());
+        let checkpoint = Checkpoint::open(path.path(), &args).unwrap();
+        assert_eq!(checkpoint.get(Path::new("c.csv")), Some(true));
+        drop(checkpoint);
+
+        args.resume = false;
+        let checkpoint = Checkpoint::open(path.path(), &args).unwrap();
+        assert_eq!(checkpoint.get(Path::new("a.csv")), None);
+        drop(checkpoint);
+        args.resume = true;
+        let checkpoint = Checkpoint::open(path.path(), &args).unwrap();
+        assert_eq!(checkpoint.get(Path::new("c.csv")), None);
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use similar::{Algorithm, DiffOp, capture_diff_slices, group_diff_ops};
+
+/// Positions of values occurring exactly once on each side, at least
+/// `chunk_rows` apart and in the same order on both sides.
+fn anchors(orig: &[&str], diff: &[&str], chunk_rows: usize) -> Vec<(usize, usize)> {
+    let mut counts: HashMap<&str, (usize, usize, usize)> = HashMap::new();
+    for value in orig {
+        counts.entry(value).or_default().0 += 1;
+    }
+    for (idx, value) in diff.iter().enumerate() {
+        if let Some(count) = counts.get_mut(value) {
+            count.1 += 1;
+            count.2 = idx;
+        }
+    }
+
+    let mut res = Vec::new();
+    let (mut next_orig, mut next_diff) = (chunk_rows, 0);
+    for (idx, value) in orig.iter().enumerate().skip(chunk_rows) {
+        if idx < next_orig {
+            continue;
+        }
+        if let Some(&(1, 1, diff_idx)) = counts.get(value)
+            && diff_idx >= next_diff
+        {
+            res.push((idx, diff_idx));
+            (next_orig, next_diff) = (idx + chunk_rows, diff_idx + 1);
+        }
+    }
+    res
+}
+
+fn shifted(op: DiffOp, old_offset: usize, new_offset: usize) -> DiffOp {
+    match op {
+        DiffOp::Equal {
+            old_index,
+            new_index,
+            len,
+        } => DiffOp::Equal {
+            old_index: old_index + old_offset,
+            new_index: new_index + new_offset,
+            len,
+        },
+        DiffOp::Delete {
+            old_index,
+            old_len,
+            new_index,
+        } => DiffOp::Delete {
+            old_index: old_index + old_offset,
+            old_len,
+            new_index: new_index + new_offset,
+        },
+        DiffOp::Insert {
+            old_index,
+            new_index,
+            new_len,
+        } => DiffOp::Insert {
+            old_index: old_index + old_offset,
+            new_index: new_index + new_offset,
+            new_len,
+        },
+        DiffOp::Replace {
+            old_index,
+            old_len,
+            new_index,
+            new_len,
+        } => DiffOp::Replace {
+            old_index: old_index + old_offset,
+            old_len,
+            new_index: new_index + new_offset,
+            new_len,
+        },
+    }
+}
+
+fn push_op(ops: &mut Vec<DiffOp>, op: DiffOp) {
+    if let (Some(DiffOp::Equal { len, .. }), DiffOp::Equal { len: next_len, .. }) =
+        (ops.last_mut(), op)
+    {
+        *len += next_len;
+    } else {
+        ops.push(op);
+    }
+}
+
+/// Diff ops of both columns. Columns longer than `chunk_rows` are split at
+/// anchors, values occurring once on each side, and the chunks between them
+/// are diffed on their own, bounding the quadratic worst case of Myers.
+pub fn diff_ops(orig: &[&str], diff: &[&str], chunk_rows: usize) -> Vec<DiffOp> {
+    if orig.len().max(diff.len()) <= chunk_rows {
+        return capture_diff_slices(Algorithm::Myers, orig, diff);
+    }
+    let mut ops = Vec::new();
+    let (mut old_start, mut new_start) = (0, 0);
+    let anchors = anchors(orig, diff, chunk_rows);
+    for &(old_end, new_end) in anchors.iter().chain([&(orig.len(), diff.len())]) {
+        let chunk = capture_diff_slices(
+            Algorithm::Myers,
+            &orig[old_start..old_end],
+            &diff[new_start..new_end],
+        );
+        for op in chunk {
+            push_op(&mut ops, shifted(op, old_start, new_start));
+        }
+        if old_end < orig.len() {
+            push_op(
+                &mut ops,
+                DiffOp::Equal {
+                    old_index: old_end,
+                    new_index: new_end,
+                    len: 1,
+                },
+            );
+        }
+        (old_start, new_start) = (old_end + 1, new_end + 1);
+    }
+    ops
+}
+
+/// `diff_ops` grouped into hunks with `context` unchanged values around the
+/// changes.
+pub fn grouped_ops(
+    orig: &[&str],
+    diff: &[&str],
+    chunk_rows: usize,
+    context: usize,
+) -> Vec<Vec<DiffOp>> {
+    group_diff_ops(diff_ops(orig, diff, chunk_rows), context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_ops() {
+        let orig: Vec<String> = (0..100).map(|i| i.to_string()).collect();
+        let mut diff = orig.clone();
+        diff[5] = "x".to_string();
+        diff.remove(50);
+        diff.insert(80, "y".to_string());
+        let orig: Vec<&str> = orig.iter().map(String::as_str).collect();
+        let diff: Vec<&str> = diff.iter().map(String::as_str).collect();
+
+        let whole = diff_ops(&orig, &diff, usize::MAX);
+        let chunked = diff_ops(&orig, &diff, 10);
+        let changes = |ops: &[DiffOp]| -> Vec<String> {
+            ops.iter()
+                .flat_map(|op| op.iter_changes(&orig[..], &diff[..]))
+                .map(|change| format!("{}{}", change.tag(), change.value()))
+                .collect()
+        };
+        assert_eq!(changes(&chunked), changes(&whole));
+        assert_eq!(
+            chunked.iter().map(|op| op.old_range().len()).sum::<usize>(),
+            orig.len()
+        );
+
+        let orig = ["a", "u", "b", "v", "c"];
+        let diff = ["a", "u", "B", "v", "c"];
+        assert_eq!(anchors(&orig, &diff, 1), [(1, 1), (3, 3), (4, 4)]);
+        assert_eq!(
+            diff_ops(&orig, &diff, 1),
+            capture_diff_slices(Algorithm::Myers, &orig, &diff)
+        );
+    }
+}
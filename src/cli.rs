@@ -0,0 +1,848 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use clap::builder::RangedU64ValueParser;
+use clap::parser::ValueSource;
+use clap::{
+    ArgAction, ArgGroup, ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand,
+    ValueEnum,
+};
+use csv::Terminator;
+use regex_automata::meta::Regex;
+
+use crate::aggregate::{Aggregate, parse_aggregate};
+use crate::dates::parse_duration;
+use crate::errors::ErrorFormat;
+use crate::expr::Expr;
+use crate::i18n::Locale;
+use crate::json::Value;
+use crate::spill::parse_size;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = None, long_about = None)]
+#[command(subcommand_required = true, arg_required_else_help = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+    /// Language of prompts and messages (default: `CSV_COMPARE_LANG` or `LANG`)
+    #[arg(long, value_enum, global = true, required = false)]
+    pub lang: Option<Locale>,
+    /// Write timings, decisions, skipped rows and warnings to this file as logfmt lines
+    #[arg(long, global = true, required = false, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+    /// Report errors on stderr as text or as JSON with a stable `code`
+    #[arg(long, value_enum, global = true, required = false, default_value_t)]
+    pub errors: ErrorFormat,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Compare a column of two CSV files (default)
+    Compare(Box<CompareArgs>),
+    /// Print statistics of a column of a CSV file
+    Stats(StatsArgs),
+    /// Merge two CSV files on a key column
+    Merge(MergeArgs),
+    /// Generate a pair of CSV fixtures with injected differences
+    Generate(GenerateArgs),
+    /// Validate a CSV file against a JSON schema
+    Validate(ValidateArgs),
+    /// Serve comparisons over HTTP (`POST /compare`, `GET /health`)
+    Serve(ServeArgs),
+    /// Apply a `--patch-output` patch to a column of a CSV file
+    Apply(ApplyArgs),
+    /// Render a saved JSON comparison result, as returned by `serve`
+    Report(ReportArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+#[command(group(ArgGroup::new("keys").args(["join", "key_expr", "hash_key", "aggregate"]).multiple(true)))]
+pub struct CompareArgs {
+    /// Orig CSV file, or directory of CSV files (`file.csv@REV` reads it from a git revision)
+    #[arg(index = 1)]
+    pub orig: PathBuf,
+    /// Diff CSV file, or directory of CSV files (`file.csv@REV` reads it from a git revision)
+    // The default only stands in until `--single-file` or `--diff-values`
+    // replace it.
+    #[arg(
+        index = 2,
+        required_unless_present_any = ["single_file", "diff_values"],
+        default_value = "-",
+        hide_default_value = true
+    )]
+    pub diff: PathBuf,
+    /// Format of the diff file
+    #[arg(long, value_enum, default_value_t = DiffFormat::Csv, conflicts_with_all = ["keys", "single_file", "line_numbers", "show_columns"])]
+    pub diff_format: DiffFormat,
+    /// Compare the orig column against these values instead of a diff file
+    #[arg(long, value_delimiter = ',', value_name = "VALUES", conflicts_with_all = ["diff", "single_file", "diff_format", "keys", "line_numbers", "show_columns"])]
+    pub diff_values: Vec<String>,
+    /// Without `--orig-index`, compare the column pair whose sampled values overlap most
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with_all = ["keys", "diff_index", "single_file", "diff_format", "diff_values"])]
+    pub auto_index: bool,
+    /// Compare two columns of the orig file, `--orig-index` against `--diff-index`
+    #[arg(long, action = ArgAction::SetTrue, requires = "diff_index", conflicts_with_all = ["diff", "keys"])]
+    pub single_file: bool,
+    /// Read the orig file from this git revision instead of the working tree
+    #[arg(long, value_name = "REV", required = false)]
+    pub orig_rev: Option<String>,
+    /// Orig index of column to compare (optional with `--join`, which then compares full rows)
+    #[arg(long, short, value_parser = one_based(), required_unless_present_any = ["keys", "auto_index"])]
+    pub orig_index: Option<usize>,
+    /// Diff index of column to compare (optional, defaults to `orig_index`)
+    #[arg(long, short, value_parser = one_based(), required = false)]
+    pub diff_index: Option<usize>,
+    /// Write the selected set of values (of full rows with `--join`, `--key-expr` or `--hash-key`) instead of a diff
+    #[arg(long, value_enum, required = false)]
+    pub emit: Option<SetOperation>,
+    /// Only print set metrics (Jaccard similarity, overlap, exclusive set sizes)
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with_all = ["emit", "base"])]
+    pub metrics_only: bool,
+    /// Only print the numbers of removed, added and changed values (per column with `--join`), no values
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with_all = ["emit", "metrics_only", "base", "interactive_review", "patch_output", "tool", "print0", "brief", "top", "summary"])]
+    pub counts_only: bool,
+    /// Only print the numbers of added, removed and unchanged values, on one line
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with_all = ["keys", "counts_only", "emit", "metrics_only", "base", "interactive_review", "patch_output", "tool", "print0", "list", "brief", "top", "summary", "side_by_side", "format", "template", "report_identical"])]
+    pub count: bool,
+    /// Render the comparison through this template instead of printing a diff (`{{ counts.added }}`, `{% for hunk in hunks %}`, …)
+    #[arg(long, value_name = "PATH", required = false, conflicts_with_all = ["keys", "counts_only", "emit", "metrics_only", "base", "interactive_review", "patch_output", "tool", "print0", "brief", "top", "summary", "side_by_side"])]
+    pub template: Option<PathBuf>,
+    /// Output format: a unified diff, or a summary for another tool
+    #[arg(long, value_enum, default_value_t = OutputFormat::Diff, conflicts_with_all = ["keys", "template", "counts_only", "emit", "metrics_only", "base", "interactive_review", "tool", "print0", "brief", "side_by_side"])]
+    pub format: OutputFormat,
+    /// Link to the full report in `--format slack` output
+    #[arg(long, value_name = "URL")]
+    pub report_url: Option<String>,
+    /// Join rows on these key columns and compare the selected column per key
+    #[arg(long, value_delimiter = ',', value_parser = one_based(), conflicts_with_all = ["metrics_only"])]
+    pub join: Vec<usize>,
+    /// Join rows on the value of this expression, e.g. `concat(col('order_id'), '-', col('line'))`, after any `--join` columns
+    #[arg(long, value_name = "EXPR", value_parser = Expr::parse, required = false, conflicts_with_all = ["metrics_only"])]
+    pub key_expr: Option<Expr>,
+    /// Join rows on a hash of these columns, keeping wide composite keys out of memory
+    #[arg(long, value_delimiter = ',', value_parser = one_based(), conflicts_with_all = ["metrics_only"])]
+    pub hash_key: Vec<usize>,
+    /// Aggregate both files before comparing and join them on the groups, e.g. `sum(amount), count(id) by customer_id`
+    #[arg(long, value_name = "SPEC", value_parser = parse_aggregate, required = false, conflicts_with_all = ["join", "key_expr", "hash_key", "orig_index", "diff_index", "metrics_only"])]
+    pub aggregate: Option<Aggregate>,
+    /// Match header names exactly, instead of ignoring case, spaces and underscores
+    #[arg(long, action = ArgAction::SetTrue, requires = "keys")]
+    pub strict_headers: bool,
+    /// CSV file of `orig,diff` column pairs (names or 1-based indices) matching columns across schemas, used with `--join`
+    #[arg(long, value_name = "PATH", required = false, requires = "keys")]
+    pub column_map: Option<PathBuf>,
+    /// TOML file with per-column comparison rules, used with `--join`
+    #[arg(long, required = false, requires = "keys")]
+    pub rules: Option<PathBuf>,
+    /// Treat timestamps of `date` rules within this window (e.g. `5s`, `2m`) as equal
+    #[arg(long, value_parser = parse_duration, required = false, requires = "rules")]
+    pub time_tolerance: Option<i64>,
+    /// With `--join`, print changed rows in full, highlighting the changed fields and dimming the rest
+    #[arg(long, action = ArgAction::SetTrue, requires = "keys")]
+    pub row_diff: bool,
+    /// With `--join`, print the sums of numeric compared columns in both files, with their absolute and percent change
+    #[arg(long, action = ArgAction::SetTrue, requires = "keys", conflicts_with_all = ["counts_only", "redact", "hash_values"])]
+    pub totals: bool,
+    /// Common ancestor CSV file for a three-way comparison, read with `--orig-index`
+    #[arg(long, required = false, conflicts_with_all = ["keys", "emit"])]
+    pub base: Option<PathBuf>,
+    /// Write the three-way merged column to this file, with conflict markers
+    #[arg(long, required = false, requires = "base")]
+    pub merge_output: Option<PathBuf>,
+    /// Only report whether the compared columns differ, like `diff -q`
+    #[arg(long, short = 'q', action = ArgAction::SetTrue, conflicts_with_all = ["keys", "emit", "base", "metrics_only", "interactive_review"])]
+    pub brief: bool,
+    /// Print an explicit message when the compared columns are identical, like `diff -s`
+    #[arg(long, short = 's', action = ArgAction::SetTrue, conflicts_with_all = ["keys", "emit", "base", "metrics_only", "interactive_review"])]
+    pub report_identical: bool,
+    /// Show values removed in one place and added in another as moved (`~`), with both positions
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with_all = ["keys", "side_by_side"])]
+    pub detect_moves: bool,
+    /// Leave moved values out of the diff
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with_all = ["keys", "side_by_side"])]
+    pub ignore_moves: bool,
+    /// Only print changed values matching this regular expression; counts and statistics still cover all changes
+    #[arg(long, value_name = "REGEX", value_parser = parse_regex, required = false, conflicts_with = "side_by_side")]
+    pub show_matching: Option<Regex>,
+    /// Print at most N hunks of the diff
+    #[arg(
+        long,
+        value_name = "N",
+        required = false,
+        conflicts_with = "side_by_side"
+    )]
+    pub max_hunks: Option<usize>,
+    /// Print at most N lines of diff hunks
+    #[arg(
+        long,
+        value_name = "N",
+        required = false,
+        conflicts_with = "side_by_side"
+    )]
+    pub max_lines: Option<usize>,
+    /// Leave out unchanged context lines, like `diff --suppress-common-lines`
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub changes_only: bool,
+    /// Skip the confirmation prompt
+    #[arg(long, short = 'y', action = ArgAction::SetTrue)]
+    pub yes: bool,
+    /// Write the compared values of each side, one per line, to `orig.txt` and `diff.txt` in this directory
+    #[arg(long, value_name = "DIR", required = false)]
+    pub emit_normalized: Option<PathBuf>,
+    /// Write an HTML report to this file, or for directories, one report per pair plus an `index.html` into this directory
+    #[arg(long, value_name = "PATH", required = false)]
+    pub html: Option<PathBuf>,
+    /// Open the --html report (or directory index) in the default browser once written
+    #[arg(long, action = ArgAction::SetTrue, requires = "html")]
+    pub open: bool,
+    /// POST a JSON summary of the comparison to this webhook when values differ
+    #[arg(long, value_name = "URL", required = false)]
+    pub notify_url: Option<String>,
+    /// Only notify when more than this many values are removed, added or changed
+    #[arg(long, value_name = "N", default_value_t = 0, requires = "notify_url")]
+    pub notify_threshold: usize,
+    /// Send the full result, with every hunk, instead of the summary
+    #[arg(long, action = ArgAction::SetTrue, requires = "notify_url")]
+    pub notify_full: bool,
+    /// When comparing directories, compare files in subdirectories too
+    #[arg(long, short = 'r', action = ArgAction::SetTrue)]
+    pub recursive: bool,
+    /// When comparing directories, compare this many file pairs at once, reporting one line per pair
+    #[arg(long, short = 'j', default_value_t = 1)]
+    pub jobs: usize,
+    /// When comparing directories, only compare files matching this glob (repeatable)
+    #[arg(long, action = ArgAction::Append, required = false)]
+    pub include: Vec<String>,
+    /// When comparing directories, skip files matching this glob (repeatable)
+    #[arg(long, action = ArgAction::Append, required = false)]
+    pub exclude: Vec<String>,
+    /// When comparing directories, record each finished pair in this file (an interrupted pair is compared again from its start on --resume; single-file comparisons cannot be checkpointed)
+    #[arg(long, value_name = "PATH")]
+    pub checkpoint: Option<PathBuf>,
+    /// Skip the pairs recorded in the --checkpoint file by an interrupted run with the same options (but --jobs)
+    #[arg(long, action = ArgAction::SetTrue, requires = "checkpoint")]
+    pub resume: bool,
+    /// Swap orig and diff (including their indices), like `diff -R`
+    #[arg(long, short = 'R', action = ArgAction::SetTrue)]
+    pub reverse: bool,
+    /// Review each hunk interactively (keep orig/diff/both, skip) and write the resolved column to this file
+    #[arg(long, value_name = "PATH", required = false, conflicts_with_all = ["keys", "emit", "base", "metrics_only"])]
+    pub interactive_review: Option<PathBuf>,
+    /// Only print these hunks of the diff (1-based, in output order)
+    #[arg(long, value_delimiter = ',', value_parser = one_based(), conflicts_with = "interactive_review")]
+    pub select_hunks: Vec<usize>,
+    /// Write the selected hunks (or those kept from diff during review) as a patch file
+    #[arg(long, value_name = "PATH", required = false, conflicts_with_all = ["keys", "emit", "base", "metrics_only"])]
+    pub patch_output: Option<PathBuf>,
+    /// Use this label instead of `a/{file}` and `b/{file}` in diff headers (once for orig, or twice, orig first)
+    #[arg(long, action = ArgAction::Append, required = false)]
+    pub label: Vec<String>,
+    /// Open the extracted, normalized columns in this external diff tool (e.g. `meld`, `vimdiff`)
+    #[arg(long, value_name = "CMD", required = false, conflicts_with_all = ["keys", "emit", "base", "metrics_only", "interactive_review", "brief"])]
+    pub tool: Option<String>,
+    /// Print only the changed (or `--emit`ted) values, each terminated by NUL instead of newline
+    #[arg(long, short = '0', action = ArgAction::SetTrue, conflicts_with_all = ["keys", "base", "metrics_only", "interactive_review", "brief"])]
+    pub print0: bool,
+    /// Print only the changes, as `-value` and `+value` lines without headers or context
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with_all = ["keys", "base", "metrics_only", "interactive_review", "brief", "emit", "print0", "tool", "side_by_side", "format", "template"])]
+    pub list: bool,
+    /// Also place the printed output on the clipboard, as plain text
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with_all = ["interactive_review", "tool", "print0"])]
+    pub copy: bool,
+    /// Show every compared value as `***` in the output
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with_all = ["hash_values", "emit", "print0", "tool", "base", "interactive_review", "patch_output", "top", "summary"])]
+    pub redact: bool,
+    /// Show compared values as salted hashes, so equal values remain recognizable
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with_all = ["emit", "print0", "tool", "base", "interactive_review", "patch_output", "top", "summary"])]
+    pub hash_values: bool,
+    /// Salt of `--hash-values`
+    #[arg(long, required = false, requires = "hash_values")]
+    pub salt: Option<String>,
+    /// Annotate changes with their 1-based line numbers in the source files
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "sort")]
+    pub line_numbers: bool,
+    /// Show these columns of the source record next to each changed value
+    #[arg(long, value_delimiter = ',', value_parser = one_based(), conflicts_with = "sort")]
+    pub show_columns: Vec<usize>,
+    /// List the N most frequent removed and added values after the diff
+    #[arg(long, value_name = "N", required = false)]
+    pub top: Option<usize>,
+    /// Show orig and diff values next to each other instead of a unified diff
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with_all = ["line_numbers", "show_columns", "wrap"])]
+    pub side_by_side: bool,
+    /// Truncate displayed values to N terminal columns, ending them with an ellipsis
+    #[arg(long, value_name = "N", required = false)]
+    pub max_width: Option<usize>,
+    /// Wrap diff lines at `--max-width` instead of truncating them
+    #[arg(long, action = ArgAction::SetTrue, requires = "max_width")]
+    pub wrap: bool,
+    /// Diff columns longer than N rows in chunks, split at values occurring once on each side
+    #[arg(long, value_name = "N", default_value_t = 1_000_000)]
+    pub chunk_rows: usize,
+    /// Print a summary of duplicated values per file after the comparison
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub summary: bool,
+    /// Print the size and SHA-256 checksum of both inputs after the comparison
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with_all = ["emit", "print0", "metrics_only", "brief", "tool", "base", "interactive_review", "template"])]
+    pub checksums: bool,
+    /// Sort both columns before comparing them
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub sort: bool,
+    /// Sort rows by these columns before comparing, each ascending or descending (e.g. `1:asc,3:desc`)
+    #[arg(long, value_name = "KEYS", value_delimiter = ',', value_parser = parse_sort_key, conflicts_with_all = ["sort", "keys", "diff_format", "diff_values"])]
+    pub sort_by: Vec<SortKey>,
+    /// Ordering used by `--sort` and `--sort-by`
+    #[arg(long, value_enum, default_value_t = SortOrder::Lexical)]
+    pub sort_order: SortOrder,
+    /// Order `--emit` values and joined keys canonically and leave the time and host out of reports, so reruns are byte-identical
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub deterministic: bool,
+    /// Write `--emit` output to this file instead of stdout
+    #[arg(long, short = 'O', required = false, requires = "emit")]
+    pub output: Option<PathBuf>,
+    #[command(flatten)]
+    pub parse: ParseArgs,
+    /// Options given on the command line by their long names (or the names
+    /// of positional arguments), with their values as written.
+    #[arg(skip)]
+    pub given: Vec<(String, Value)>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SetOperation {
+    /// Values present in both files
+    Intersection,
+    /// Values present in orig only
+    OnlyOrig,
+    /// Values present in diff only
+    OnlyDiff,
+    /// Values present in either file
+    Union,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffFormat {
+    /// CSV file, compared at `--diff-index`
+    Csv,
+    /// Plain text file with one value per line
+    Lines,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Unified diff of the column
+    Diff,
+    /// Slack Block Kit JSON payload with counts and the top changed values
+    Slack,
+    /// Prometheus text exposition gauges, for scraping and alerting
+    Prometheus,
+    /// GitLab Code Quality report with a finding per differing row
+    GitlabCodequality,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Inputs {
+    /// Both files
+    Both,
+    /// The orig file only
+    Orig,
+    /// The diff file only
+    Diff,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Byte-wise string order
+    Lexical,
+    /// Digit runs compare by their numeric value, so `file9` < `file10`
+    Natural,
+    /// Numeric values first, in numeric order, followed by other values
+    Numeric,
+}
+
+/// A column rows are sorted by, with its direction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SortKey {
+    pub column: usize,
+    pub descending: bool,
+}
+
+/// Parser of 1-based indices and other positive numbers, rejecting 0.
+fn one_based() -> RangedU64ValueParser<usize> {
+    RangedU64ValueParser::new().range(1..)
+}
+
+fn parse_sort_key(value: &str) -> Result<SortKey, String> {
+    let (column, direction) = value.split_once(':').unwrap_or((value, "asc"));
+    let column = match column.parse::<usize>() {
+        Ok(column) if column > 0 => column,
+        _ => {
+            return Err(format!(
+                "invalid column `{column}`, expected a 1-based index"
+            ));
+        }
+    };
+    let descending = match direction {
+        "asc" => false,
+        "desc" => true,
+        _ => {
+            return Err(format!(
+                "invalid direction `{direction}`, expected asc or desc"
+            ));
+        }
+    };
+    Ok(SortKey { column, descending })
+}
+
+#[derive(Args, Debug)]
+pub struct StatsArgs {
+    /// CSV file
+    #[arg(index = 1)]
+    pub file: PathBuf,
+    /// Index of column to inspect
+    #[arg(long, short, value_parser = one_based())]
+    pub index: usize,
+    #[command(flatten)]
+    pub parse: ParseArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct ApplyArgs {
+    /// Patch file written by `--patch-output`
+    #[arg(index = 1)]
+    pub patch: PathBuf,
+    /// CSV file
+    #[arg(index = 2)]
+    pub file: PathBuf,
+    /// Index of column the patch applies to
+    #[arg(long, short, value_parser = one_based())]
+    pub index: usize,
+    /// Write the patched column to this file instead of stdout
+    #[arg(long, short = 'O', required = false)]
+    pub output: Option<PathBuf>,
+    #[command(flatten)]
+    pub parse: ParseArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct ReportArgs {
+    /// JSON comparison result
+    #[arg(index = 1)]
+    pub result: PathBuf,
+    /// Render the result through this template instead of printing its diff
+    #[arg(long, value_name = "PATH", required = false)]
+    pub template: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct MergeArgs {
+    /// Orig CSV file
+    #[arg(index = 1)]
+    pub orig: PathBuf,
+    /// Diff CSV file
+    #[arg(index = 2)]
+    pub diff: PathBuf,
+    /// Index of key column rows are matched on
+    #[arg(long, value_parser = one_based())]
+    pub on: usize,
+    /// How rows with the same key but different contents are resolved
+    #[arg(long, value_enum, default_value_t = MergeStrategy::Union)]
+    pub strategy: MergeStrategy,
+    /// Write the merged CSV to this file instead of stdout
+    #[arg(long, short = 'O', required = false)]
+    pub output: Option<PathBuf>,
+    #[command(flatten)]
+    pub reader: ReaderArgs,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Keep both rows when they differ
+    Union,
+    /// Keep the orig row when rows differ
+    PreferOrig,
+    /// Keep the diff row when rows differ
+    PreferDiff,
+}
+
+#[derive(Args, Debug)]
+pub struct GenerateArgs {
+    /// Path of the generated orig CSV file
+    #[arg(index = 1)]
+    pub orig: PathBuf,
+    /// Path of the generated diff CSV file
+    #[arg(index = 2)]
+    pub diff: PathBuf,
+    /// Number of rows in the orig file
+    #[arg(long, default_value_t = 1000)]
+    pub rows: usize,
+    /// Number of columns, including the leading id column
+    #[arg(long, default_value_t = 5)]
+    pub columns: usize,
+    /// Number of rows added to the diff file
+    #[arg(long, default_value_t = 0)]
+    pub adds: usize,
+    /// Number of rows removed from the diff file
+    #[arg(long, default_value_t = 0)]
+    pub removes: usize,
+    /// Number of rows with an edited field in the diff file
+    #[arg(long, default_value_t = 0)]
+    pub edits: usize,
+    /// Number of rows moved to another position in the diff file
+    #[arg(long, default_value_t = 0)]
+    pub reorders: usize,
+    /// Seed of the random generator, for reproducible fixtures
+    #[arg(long, required = false)]
+    pub seed: Option<u64>,
+    /// Write a header row to both files
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub with_headers: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ValidateArgs {
+    /// CSV file
+    #[arg(index = 1)]
+    pub file: PathBuf,
+    /// JSON schema describing the expected columns
+    #[arg(long, short)]
+    pub schema: PathBuf,
+    #[command(flatten)]
+    pub reader: ReaderArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct ServeArgs {
+    /// Address to listen on; `:PORT` listens on all interfaces
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    pub listen: String,
+    /// Handle at most N connections at once; further ones wait to be accepted
+    #[arg(long, value_name = "N", value_parser = one_based(), default_value_t = 16)]
+    pub max_connections: usize,
+}
+
+#[derive(Args, Debug, Clone, Default)]
+#[command(group(ArgGroup::new("widths_spec").args(["widths", "widths_file"])))]
+pub struct ReaderArgs {
+    /// Whether CSV's have headers
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub with_headers: bool,
+    /// Read fields verbatim, without interpreting quotes or escape sequences
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub raw: bool,
+    /// Remove quotes surrounding field values
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub strip_quotes: bool,
+    /// Read records of any length, taking the column as empty in records too short to hold it
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub missing_as_empty: bool,
+    /// Field delimiter, such as `;`, `\t` or a sequence like `||`, which splits each line verbatim, without quoting
+    #[arg(long, value_name = "DELIM", value_parser = parse_delimiter, required = false)]
+    pub delimiter: Option<String>,
+    /// Ignore the first N lines of each file before parsing, such as a free-text banner above the header
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub skip_rows: usize,
+    /// Ignore the last N records of each file, such as a `TOTAL` footer
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub skip_last: usize,
+    /// Read both inputs, or only `orig` or `diff`, as fixed-width text: each line a record split at `--widths`
+    #[arg(long, value_enum, value_name = "INPUTS", num_args = 0..=1, default_missing_value = "both", requires = "widths_spec", conflicts_with_all = ["raw", "terminator"])]
+    pub fixed_width: Option<Inputs>,
+    /// Byte widths of the fields of `--fixed-width` records
+    #[arg(
+        long,
+        value_name = "N,...",
+        value_delimiter = ',',
+        requires = "fixed_width"
+    )]
+    pub widths: Vec<usize>,
+    /// File listing the field widths of `--fixed-width` records, separated by commas or newlines
+    #[arg(long, value_name = "PATH", required = false, requires = "fixed_width")]
+    pub widths_file: Option<PathBuf>,
+    /// Record terminator: `crlf` (any of CR, LF, CRLF), `lf`, `cr` or a single byte
+    #[arg(long, value_parser = parse_terminator, required = false)]
+    pub terminator: Option<Terminator>,
+    /// Convert CRLF and CR line breaks inside fields to LF before comparison
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub normalize_newlines: bool,
+    /// Replace invalid UTF-8 sequences with U+FFFD instead of failing
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "bytes")]
+    pub lossy_utf8: bool,
+    /// Compare raw bytes, showing bytes that are not valid UTF-8 as `\xNN`
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub bytes: bool,
+    /// Only read records matching this expression, e.g. `amount > 100 AND region = 'EU'` (`$N` is the N-th column)
+    #[arg(long = "where", value_name = "EXPR", value_parser = Expr::parse, required = false)]
+    pub filter: Option<Expr>,
+}
+
+fn parse_regex(value: &str) -> Result<Regex, String> {
+    Regex::new(value).map_err(|err| err.to_string())
+}
+
+fn parse_delimiter(value: &str) -> Result<String, String> {
+    match value {
+        "" => Err("expected at least one character".to_string()),
+        "\\t" => Ok("\t".to_string()),
+        _ => Ok(value.to_string()),
+    }
+}
+
+fn parse_terminator(value: &str) -> Result<Terminator, String> {
+    match value {
+        "crlf" => Ok(Terminator::CRLF),
+        "lf" => Ok(Terminator::Any(b'\n')),
+        "cr" => Ok(Terminator::Any(b'\r')),
+        _ if value.len() == 1 => Ok(Terminator::Any(value.as_bytes()[0])),
+        _ => Err("expected `crlf`, `lf`, `cr` or a single ASCII character".to_string()),
+    }
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct ParseArgs {
+    /// Search prefix of selected rows
+    #[arg(long, short, required = false)]
+    pub with_prefix: Option<String>,
+    #[command(flatten)]
+    pub reader: ReaderArgs,
+    /// Map boolean-like values (true/1/yes/y, false/0/no/n) to `true`/`false`
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub normalize_bools: bool,
+    /// Fail on records too short to hold the column instead of skipping them
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "missing_as_empty")]
+    pub strict: bool,
+    /// Round numeric values to N decimal places before comparing them
+    #[arg(long, value_name = "N", required = false)]
+    pub round: Option<usize>,
+    /// Spill intermediate values to temporary files beyond this much memory (e.g. `512M`, `2G`)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, required = false)]
+    pub max_memory: Option<usize>,
+    /// Pipe extracted values through an external command (one value per line) before diffing
+    #[arg(long, required = false)]
+    pub transform_cmd: Option<String>,
+    /// Cache parsed columns in this directory, keyed by input content and options
+    #[arg(long, required = false)]
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl CompareArgs {
+    /// Applies `--single-file` by reading the diff column from the orig file.
+    fn apply_single_file(&mut self) {
+        if self.single_file {
+            self.diff = self.orig.clone();
+        }
+    }
+
+    /// Applies `--reverse` by swapping the orig and diff sides.
+    fn apply_reverse(&mut self) {
+        if !self.reverse {
+            return;
+        }
+        std::mem::swap(&mut self.orig, &mut self.diff);
+        if let Some(orig_index) = self.orig_index {
+            self.orig_index = Some(self.diff_index.unwrap_or(orig_index));
+            self.diff_index = Some(orig_index);
+        }
+        self.parse.reader.fixed_width = match self.parse.reader.fixed_width {
+            Some(Inputs::Orig) => Some(Inputs::Diff),
+            Some(Inputs::Diff) => Some(Inputs::Orig),
+            inputs => inputs,
+        };
+        self.reverse = false;
+    }
+}
+
+impl Cli {
+    /// Parses the command line, treating an invocation without a subcommand
+    /// as `compare` so that `csv-compare a.csv b.csv -o 1` keeps working.
+    pub fn parse_args() -> Self {
+        Self::from_args(std::env::args_os().collect())
+    }
+
+    fn from_args(args: Vec<OsString>) -> Self {
+        let mut cli =
+            Self::parse_given(with_default_command(args)).unwrap_or_else(|err| err.exit());
+        if let Command::Compare(args) = &mut cli.command {
+            args.apply_single_file();
+            args.apply_reverse();
+        }
+        cli
+    }
+
+    /// Parses `args` like `try_parse_from`, also recording the options given
+    /// to `compare`.
+    pub fn parse_given<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let command = Self::command();
+        let matches = command.clone().try_get_matches_from(args)?;
+        let mut cli = Self::from_arg_matches(&matches)?;
+        if let (Command::Compare(args), Some(("compare", matches))) =
+            (&mut cli.command, matches.subcommand())
+            && let Some(compare) = command.find_subcommand("compare")
+        {
+            args.given = given_options(compare, matches);
+        }
+        Ok(cli)
+    }
+}
+
+/// Arguments of `command` set on the command line, in definition order:
+/// flags as `true`, repeatable and delimited options as arrays of their
+/// values, others as their value.
+fn given_options(command: &clap::Command, matches: &ArgMatches) -> Vec<(String, Value)> {
+    command
+        .get_arguments()
+        .filter(|arg| matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine))
+        .map(|arg| {
+            let id = arg.get_id().as_str();
+            let name = arg.get_long().unwrap_or(id).to_string();
+            if !arg.get_action().takes_values() {
+                return (name, Value::Bool(true));
+            }
+            let mut values = matches
+                .get_raw(id)
+                .into_iter()
+                .flatten()
+                .map(|value| Value::String(value.to_string_lossy().into_owned()));
+            let repeated = matches!(arg.get_action(), ArgAction::Append)
+                || arg.get_value_delimiter().is_some();
+            let value = if repeated {
+                Value::Array(values.collect())
+            } else {
+                values.next().unwrap_or(Value::Null)
+            };
+            (name, value)
+        })
+        .collect()
+}
+
+fn with_default_command(mut args: Vec<OsString>) -> Vec<OsString> {
+    let Some(first) = args.get(1) else {
+        return args;
+    };
+    // Subcommands and flags are ASCII, so anything else is a file name.
+    let Some(first) = first.to_str() else {
+        args.insert(1, "compare".into());
+        return args;
+    };
+    let command = Cli::command();
+    let is_command = first == "help"
+        || command.get_subcommands().any(|sub| {
+            sub.get_name() == first || sub.get_all_aliases().any(|alias| alias == first)
+        });
+    let is_top_level_flag = matches!(first, "-h" | "--help" | "-V" | "--version");
+    if !is_command && !is_top_level_flag {
+        args.insert(1, "compare".into());
+    }
+    args
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// Compare arguments for `a.csv b.csv` with the given options.
+    pub fn compare_args(args: &[&str]) -> CompareArgs {
+        let mut argv = vec!["csv-compare", "compare", "a.csv", "b.csv"];
+        argv.extend(args);
+        match Cli::parse_from(argv).command {
+            Command::Compare(args) => *args,
+            _ => unreachable!(),
+        }
+    }
+
+    fn parse(args: &[&str]) -> Cli {
+        Cli::from_args(args.iter().map(OsString::from).collect())
+    }
+
+    #[test]
+    fn test_default_command() {
+        let cli = parse(&["csv-compare", "a.csv", "b.csv", "-o", "2"]);
+        let Command::Compare(args) = cli.command else {
+            panic!("expected compare command");
+        };
+        assert_eq!(args.orig_index, Some(2));
+
+        let cli = parse(&["csv-compare", "--orig-index", "1", "a.csv", "b.csv"]);
+        assert!(matches!(cli.command, Command::Compare(_)));
+
+        let cli = parse(&["csv-compare", "stats", "a.csv", "-i", "1"]);
+        assert!(matches!(cli.command, Command::Stats(_)));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let name = std::ffi::OsStr::from_bytes(b"caf\xe9.csv");
+            let args = with_default_command(vec!["csv-compare".into(), name.into()]);
+            assert_eq!(args[1], "compare");
+            assert_eq!(args[2], name);
+        }
+    }
+
+    #[test]
+    fn test_reverse() {
+        let cli = parse(&["csv-compare", "a.csv", "b.csv", "-o", "2", "-d", "3", "-R"]);
+        let Command::Compare(args) = cli.command else {
+            panic!("expected compare command");
+        };
+        assert_eq!(args.orig, PathBuf::from("b.csv"));
+        assert_eq!(args.diff, PathBuf::from("a.csv"));
+        assert_eq!(args.orig_index, Some(3));
+        assert_eq!(args.diff_index, Some(2));
+    }
+
+    #[test]
+    fn test_single_file() {
+        let cli = parse(&[
+            "csv-compare",
+            "qa.csv",
+            "--single-file",
+            "-o",
+            "2",
+            "-d",
+            "3",
+        ]);
+        let Command::Compare(args) = cli.command else {
+            panic!("expected compare command");
+        };
+        assert_eq!(args.diff, PathBuf::from("qa.csv"));
+        assert!(Cli::try_parse_from(["csv-compare", "compare", "qa.csv", "-o", "2"]).is_err());
+
+        let cli = parse(&["csv-compare", "qa.csv", "-o", "2", "--diff-values", "eu,us"]);
+        let Command::Compare(args) = cli.command else {
+            panic!("expected compare command");
+        };
+        assert_eq!(args.diff_values, ["eu", "us"]);
+    }
+
+    #[test]
+    fn test_one_based_indices() {
+        let compare = |args: &[&str]| {
+            let mut argv = vec!["csv-compare", "compare", "a.csv", "b.csv"];
+            argv.extend(args);
+            Cli::try_parse_from(argv)
+        };
+        assert!(compare(&["-o", "1"]).is_ok());
+        for args in [
+            &["-o", "0"][..],
+            &["-o", "1", "-d", "0"],
+            &["--join", "1,0"],
+            &["--hash-key", "0"],
+            &["-o", "1", "--show-columns", "0"],
+            &["-o", "1", "--select-hunks", "0"],
+        ] {
+            assert!(compare(args).is_err(), "{args:?}");
+        }
+        assert!(Cli::try_parse_from(["csv-compare", "stats", "a.csv", "-i", "0"]).is_err());
+        assert!(Cli::try_parse_from(["csv-compare", "apply", "p", "a.csv", "-i", "0"]).is_err());
+        assert!(
+            Cli::try_parse_from(["csv-compare", "merge", "a.csv", "b.csv", "--on", "0"]).is_err()
+        );
+        let merge = ["csv-compare", "merge", "a.csv", "b.csv", "--on", "1"];
+        assert!(Cli::try_parse_from([&merge[..], &["--missing-as-empty"]].concat()).is_ok());
+        assert!(compare(&["-o", "1", "--missing-as-empty", "--strict"]).is_err());
+    }
+}
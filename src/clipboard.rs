@@ -0,0 +1,126 @@
+use std::env;
+use std::ffi::OsString;
+use std::io::{self, ErrorKind, IsTerminal, Read, Write};
+use std::process::{Command, ExitCode, Stdio};
+
+use anyhow::{Result, anyhow};
+
+/// Clipboard programs to try, in order, as `[program, args...]`.
+fn candidates() -> Vec<&'static [&'static str]> {
+    if cfg!(target_os = "macos") {
+        return vec![&["pbcopy"]];
+    }
+    if cfg!(windows) {
+        return vec![&["clip"]];
+    }
+    let mut candidates: Vec<&[&str]> = Vec::new();
+    if env::var_os("WAYLAND_DISPLAY").is_some() {
+        candidates.push(&["wl-copy"]);
+    }
+    candidates.push(&["xclip", "-selection", "clipboard"]);
+    candidates.push(&["xsel", "--clipboard", "--input"]);
+    candidates
+}
+
+/// Removes the ANSI color sequences of `text`.
+fn strip_ansi(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            res.push(c);
+        }
+    }
+    res
+}
+
+/// Pipes `text` into the first of `candidates` that is installed.
+fn copy_with(text: &str, candidates: &[&[&str]]) -> Result<()> {
+    for &candidate in candidates {
+        let Some((program, args)) = candidate.split_first() else {
+            continue;
+        };
+        let mut child = match Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(err) if err.kind() == ErrorKind::NotFound => continue,
+            Err(err) => return Err(anyhow!("Failed to run `{program}`: {err}")),
+        };
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(text.as_bytes())?;
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(anyhow!("`{program}` exited with {status}"));
+        }
+        return Ok(());
+    }
+    Err(anyhow!(
+        "No clipboard program found; install one of wl-copy, xclip or xsel"
+    ))
+}
+
+/// Runs this command line again without `--copy`, passing its output
+/// through and placing it on the clipboard as plain text. Returns the exit
+/// code of the run.
+pub fn run_copying() -> Result<ExitCode> {
+    let args: Vec<OsString> = env::args_os()
+        .skip(1)
+        .filter(|arg| arg != "--copy")
+        .collect();
+    let mut command = Command::new(env::current_exe()?);
+    command.args(args).stdout(Stdio::piped());
+    if io::stdout().is_terminal() {
+        command.env("CLICOLOR_FORCE", "1");
+    }
+    let mut child = command.spawn()?;
+    let mut pipe = child.stdout.take().expect("stdout is piped");
+    let mut output = Vec::new();
+    let mut buffer = [0; 8192];
+    let mut stdout = io::stdout().lock();
+    loop {
+        let len = pipe.read(&mut buffer)?;
+        if len == 0 {
+            break;
+        }
+        stdout.write_all(&buffer[..len])?;
+        stdout.flush()?;
+        output.extend_from_slice(&buffer[..len]);
+    }
+    let status = child.wait()?;
+    copy_with(
+        &strip_ansi(&String::from_utf8_lossy(&output)),
+        &candidates(),
+    )?;
+    Ok(ExitCode::from(
+        status.code().map_or(1, |code| code.clamp(0, 255) as u8),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_with() {
+        assert_eq!(strip_ansi("\x1b[31m-b\x1b[0m\n"), "-b\n");
+
+        let out = assert_fs::NamedTempFile::new("clipboard.txt").unwrap();
+        let script = format!("cat > \"{}\"", out.path().display());
+        copy_with("-b\n", &[&["csv-compare-missing"], &["sh", "-c", &script]]).unwrap();
+        assert_eq!(std::fs::read_to_string(out.path()).unwrap(), "-b\n");
+        assert!(copy_with("x", &[&["csv-compare-missing"]]).is_err());
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Result;
+use csv::StringRecord;
+
+use crate::cli::ReaderArgs;
+use crate::errors::Category;
+use crate::parse::{Table, read_table, same_header};
+
+/// Explicit correspondences of orig to diff columns, read from a CSV file of
+/// `orig,diff` rows such as:
+///
+/// ```csv
+/// cust_id,customer_id
+/// 3,5
+/// ```
+///
+/// Columns are identified by header name or by their 1-based index.
+#[derive(Debug, Default)]
+pub struct ColumnMap {
+    pairs: Vec<(String, String)>,
+}
+
+/// 1-based index of the column `name` refers to.
+fn resolve(
+    name: &str,
+    headers: Option<&StringRecord>,
+    (file, strict): (&str, bool),
+) -> Result<usize> {
+    if let Ok(index) = name.parse::<usize>()
+        && index > 0
+    {
+        return Ok(index);
+    }
+    headers
+        .and_then(|headers| {
+            headers
+                .iter()
+                .position(|header| same_header(header, name, strict))
+        })
+        .map(|position| position + 1)
+        .ok_or_else(|| Category::BadIndex.error(format!("column map: no {file} column `{name}`")))
+}
+
+impl ColumnMap {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let table = read_table(path, &ReaderArgs::default())?;
+        let pairs = table
+            .records
+            .iter()
+            .map(|record| match (record.get(0), record.get(1)) {
+                (Some(orig), Some(diff)) => Ok((orig.to_string(), diff.to_string())),
+                _ => Err(Category::Parse.error(format!(
+                    "column map {}: expected `orig,diff` rows",
+                    path.display()
+                ))),
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { pairs })
+    }
+
+    /// Diff column index by orig column index.
+    pub fn indices(
+        &self,
+        orig: &Table,
+        diff: &Table,
+        strict: bool,
+    ) -> Result<HashMap<usize, usize>> {
+        self.pairs
+            .iter()
+            .map(|(orig_name, diff_name)| {
+                Ok((
+                    resolve(orig_name, orig.headers.as_ref(), ("orig", strict))?,
+                    resolve(diff_name, diff.headers.as_ref(), ("diff", strict))?,
+                ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::FileWriteStr;
+
+    #[test]
+    fn test_indices() {
+        let file = assert_fs::NamedTempFile::new("map.csv").unwrap();
+        file.write_str("cust_id,customer_id\n3,1\n").unwrap();
+        let map = ColumnMap::from_path(file.path()).unwrap();
+        let table = |headers: &[&str]| Table {
+            headers: Some(StringRecord::from(headers.to_vec())),
+            records: Vec::new(),
+        };
+        let (orig, diff) = (table(&["id", "cust_id"]), table(&["customer_id", "id"]));
+        let indices = map.indices(&orig, &diff, true).unwrap();
+        assert_eq!(indices, HashMap::from([(2, 1), (3, 1)]));
+
+        let err = map.indices(&orig, &table(&["id"]), true).unwrap_err();
+        assert_eq!(err.to_string(), "column map: no diff column `customer_id`");
+    }
+}
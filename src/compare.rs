@@ -0,0 +1,1031 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use colored::{Color, Colorize};
+use csv::StringRecord;
+use inquire::Confirm;
+use similar::udiff::UnifiedHunkHeader;
+use similar::{Change, ChangeTag};
+use tempfile::TempDir;
+
+use crate::aggregate;
+use crate::auto_index;
+use crate::chunked::{diff_ops, grouped_ops};
+use crate::cli::{CompareArgs, DiffFormat, OutputFormat};
+use crate::counts::{Counts, diff_counts, line_counts, print_counts};
+use crate::dates::format_timestamp;
+use crate::diff3::{merge3, print_diff3, slices};
+use crate::dir;
+use crate::display::{fit, group_thousands};
+use crate::errors::Category;
+use crate::fingerprint;
+use crate::fixed_width;
+use crate::formats;
+use crate::git::resolve_revisions;
+use crate::html;
+use crate::i18n::tr;
+use crate::join;
+use crate::logging;
+use crate::moves::Moves;
+use crate::notify;
+use crate::parse::{
+    ParseWarnings, display_name, for_each_value, load_column, load_lines, source_records,
+    take_warnings,
+};
+use crate::provenance::Provenance;
+use crate::redact::Masking;
+use crate::report;
+use crate::review;
+use crate::sets::{SetMetrics, partitioned_sets, set_metrics, set_operation};
+use crate::side_by_side;
+use crate::sort::{sort_by_keys, sort_values, total_cmp};
+use crate::spill::{Partitions, Spill};
+use crate::stats::{print_duplicates, print_top_values};
+use crate::template;
+use crate::tool;
+
+fn files_identical(orig: &Path, diff: &Path) -> Result<bool> {
+    if fs::metadata(orig)?.len() != fs::metadata(diff)?.len() {
+        return Ok(false);
+    }
+
+    let mut orig = BufReader::new(File::open(orig)?);
+    let mut diff = BufReader::new(File::open(diff)?);
+    loop {
+        let orig_buf = orig.fill_buf()?;
+        let diff_buf = diff.fill_buf()?;
+        if orig_buf.is_empty() || diff_buf.is_empty() {
+            return Ok(orig_buf.is_empty() && diff_buf.is_empty());
+        }
+        let len = orig_buf.len().min(diff_buf.len());
+        if orig_buf[..len] != diff_buf[..len] {
+            return Ok(false);
+        }
+        orig.consume(len);
+        diff.consume(len);
+    }
+}
+
+pub fn prompt_csv(orig: &[String], diff: &[String]) -> Result<()> {
+    prompt_records(
+        (orig.len(), orig.first().map(String::as_str)),
+        (diff.len(), diff.first().map(String::as_str)),
+    )
+}
+
+/// Asks for confirmation, showing the record count and first record per side.
+fn prompt_records(orig: (usize, Option<&str>), diff: (usize, Option<&str>)) -> Result<()> {
+    let help = tr(
+        "prompt-help",
+        &[
+            ("orig_count", &orig.0.to_string()),
+            ("orig_first", orig.1.unwrap_or("N/A")),
+            ("diff_count", &diff.0.to_string()),
+            ("diff_first", diff.1.unwrap_or("N/A")),
+        ],
+    );
+    let prompt = tr("prompt", &[]);
+    let ans = Confirm::new(&prompt)
+        .with_default(false)
+        .with_help_message(&format!("\n{help}\n"))
+        .prompt();
+
+    match ans {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(Category::UserAbort.error(tr("interrupted", &[]))),
+        Err(err) => Err(err)?,
+    }
+}
+
+/// Writes the values as compared, after filters, transforms and sorting,
+/// to `orig.txt` and `diff.txt` in `dir`.
+fn write_normalized(dir: &Path, orig_lines: &[String], diff_lines: &[String]) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    for (name, lines) in [("orig.txt", orig_lines), ("diff.txt", diff_lines)] {
+        let mut output = BufWriter::new(File::create(dir.join(name))?);
+        for line in lines {
+            writeln!(output, "{line}")?;
+        }
+        output.flush()?;
+    }
+    Ok(())
+}
+
+/// Replaces `--diff-values` by a temporary file of one value per line, read
+/// with `--diff-format lines`. The returned directory must be kept alive
+/// while the file is read.
+fn write_diff_values(args: &CompareArgs) -> Result<Option<(CompareArgs, TempDir)>> {
+    if args.diff_values.is_empty() {
+        return Ok(None);
+    }
+    if args.diff_values.iter().any(|value| value.contains('\n')) {
+        return Err(anyhow!("--diff-values cannot hold multi-line values"));
+    }
+    let dir = tempfile::tempdir()?;
+    let mut resolved = args.clone();
+    resolved.diff = dir.path().join("diff-values");
+    fs::write(&resolved.diff, args.diff_values.join("\n") + "\n")?;
+    resolved.diff_values.clear();
+    resolved.diff_format = DiffFormat::Lines;
+    Ok(Some((resolved, dir)))
+}
+
+pub fn run(args: &CompareArgs) -> Result<()> {
+    if let Some((args, _dir)) = write_diff_values(args)? {
+        return run(&args);
+    }
+    if let Some((args, _blobs)) = resolve_revisions(args)? {
+        return run(&args);
+    }
+    if let Some((args, _dir)) = fixed_width::convert_input(args)? {
+        return run(&args);
+    }
+    if let Some((args, _dir)) = aggregate::write(args)? {
+        return run(&args);
+    }
+    if !args.join.is_empty() || args.key_expr.is_some() || !args.hash_key.is_empty() {
+        return join::run(args);
+    }
+    let res = if args.orig.is_dir() && args.diff.is_dir() {
+        dir::run(args)
+    } else if args.checkpoint.is_some() {
+        return Err(anyhow!(
+            "--checkpoint records finished pairs of a directory comparison, and needs two directories"
+        ));
+    } else {
+        match compare_files(args, !args.print0 && !args.list) {
+            // Like `diff`, and directory mode, a difference is a failure.
+            Ok(true) => {
+                let (orig, diff) = (display_name(&args.orig), display_name(&args.diff));
+                Err(Category::ThresholdExceeded
+                    .error(tr("columns-differ", &[("orig", &orig), ("diff", &diff)])))
+            }
+            res => res.map(|_| ()),
+        }
+    };
+    print_parse_warnings(&mut io::stderr().lock(), &take_warnings())?;
+    res
+}
+
+/// Prints what parsing dropped or altered in each file, if anything.
+fn print_parse_warnings(
+    output: &mut impl Write,
+    warnings: &[(PathBuf, ParseWarnings)],
+) -> Result<()> {
+    if warnings.is_empty() {
+        return Ok(());
+    }
+    writeln!(output, "{}:", tr("parse-warnings", &[]))?;
+    for (path, warnings) in warnings {
+        let parts: Vec<String> = [
+            ("warn-short", warnings.short),
+            ("warn-filtered", warnings.filtered),
+            ("warn-empty", warnings.empty),
+            ("warn-replaced", warnings.replaced),
+        ]
+        .into_iter()
+        .filter(|&(_, count)| count > 0)
+        .map(|(key, count)| tr(key, &[("count", &count.to_string())]))
+        .collect();
+        writeln!(output, "  {}: {}", display_name(path), parts.join(", "))?;
+    }
+    Ok(())
+}
+
+fn pair_fields(args: &CompareArgs) -> [(&'static str, String); 2] {
+    [
+        ("orig", display_name(&args.orig)),
+        ("diff", display_name(&args.diff)),
+    ]
+}
+
+/// Prints the message `key` about the compared pair of files.
+fn announce(key: &str, args: &CompareArgs) {
+    let (orig, diff) = (display_name(&args.orig), display_name(&args.diff));
+    println!("{}", tr(key, &[("orig", &orig), ("diff", &diff)]));
+}
+
+/// Compares the selected columns of two files and returns whether they
+/// differ. Byte-identical files are skipped, with a message if
+/// `announce_identical` is set.
+pub fn compare_files(args: &CompareArgs, announce_identical: bool) -> Result<bool> {
+    if let Some(args) = auto_index::resolve(args)? {
+        return compare_files(&args, announce_identical);
+    }
+    let orig_index = args
+        .orig_index
+        .ok_or_else(|| Category::BadIndex.error("--orig-index is required without --join"))?;
+
+    if !needs_full_pipeline(args)
+        && args.diff_index.is_none_or(|index| index == orig_index)
+        && files_identical(&args.orig, &args.diff)?
+    {
+        logging::info("byte-identical", &pair_fields(args));
+        if announce_identical {
+            announce("files-identical", args);
+        }
+        return Ok(false);
+    }
+
+    if (args.emit.is_some() || args.metrics_only)
+        && let Some(budget) = args.parse.max_memory
+        && args.parse.transform_cmd.is_none()
+        && args.diff_format == DiffFormat::Csv
+        && args.emit_normalized.is_none()
+        && !args.deterministic
+        && args.notify_url.is_none()
+    {
+        logging::info("partitioned", &pair_fields(args));
+        return compare_partitioned(args, orig_index, budget);
+    }
+    if let Some(budget) = external_sort_budget(args) {
+        logging::info("external-sort", &pair_fields(args));
+        return compare_sorted(args, orig_index, budget);
+    }
+
+    let mut orig_lines = load_column(&args.parse, &args.orig, orig_index)?;
+    let mut diff_lines = match args.diff_format {
+        DiffFormat::Csv => load_column(
+            &args.parse,
+            &args.diff,
+            args.diff_index.unwrap_or(orig_index),
+        )?,
+        DiffFormat::Lines => load_lines(&args.parse, &args.diff)?,
+    };
+
+    if args.sort {
+        sort_values(&mut orig_lines, args.sort_order);
+        sort_values(&mut diff_lines, args.sort_order);
+    }
+    // Source records in `--sort-by` order, for annotating the sorted values.
+    let mut sorted_sources = None;
+    if !args.sort_by.is_empty() {
+        let diff_index = args.diff_index.unwrap_or(orig_index);
+        let mut sorted = Vec::new();
+        for (lines, path, index) in [
+            (&mut orig_lines, &args.orig, orig_index),
+            (&mut diff_lines, &args.diff, diff_index),
+        ] {
+            let mut records = source_records(path, index, &args.parse)?;
+            if records.len() != lines.len() {
+                return Err(anyhow!(
+                    "--sort-by needs one value per record of {}, but the column has {} of {} records",
+                    display_name(path),
+                    lines.len(),
+                    records.len()
+                ));
+            }
+            sort_by_keys(lines, &mut records, &args.sort_by, args.sort_order);
+            sorted.push(records);
+        }
+        let mut sorted = sorted.into_iter();
+        sorted_sources = sorted.next().zip(sorted.next());
+    }
+    if let Some(dir) = &args.emit_normalized {
+        write_normalized(dir, &orig_lines, &diff_lines)?;
+    }
+
+    let differ = orig_lines != diff_lines;
+    let labels = labels(args)?;
+    let labels = (labels.0.as_str(), labels.1.as_str());
+    if let Some(path) = &args.html {
+        html::write_report(
+            path,
+            labels,
+            (&orig_lines, &diff_lines),
+            &Masking::new(args),
+            args.chunk_rows,
+            &Provenance::new(args)?,
+        )?;
+        if args.open {
+            html::open(path)?;
+        }
+    }
+    if let Some(url) = &args.notify_url {
+        notify::send(args, url, labels, (&orig_lines, &diff_lines))?;
+    }
+    if args.report_identical && !differ {
+        announce("columns-identical", args);
+        return Ok(false);
+    }
+
+    if args.brief {
+        if differ {
+            announce("files-differ", args);
+        }
+        return Ok(differ);
+    }
+
+    if !args.yes {
+        prompt_csv(&orig_lines, &diff_lines)?;
+    }
+
+    if let Some(tool) = &args.tool {
+        tool::launch(tool, (&args.orig, &args.diff), &orig_lines, &diff_lines)?;
+        return Ok(differ);
+    }
+
+    if let Some(base) = &args.base {
+        let mut base_lines = load_column(&args.parse, base, orig_index)?;
+        if args.sort {
+            sort_values(&mut base_lines, args.sort_order);
+        }
+        print_diff3(&base_lines, &orig_lines, &diff_lines);
+        if args.summary {
+            print_duplicates(&display_name(base), &base_lines);
+            print_summary(args, &orig_lines, &diff_lines);
+        }
+        if let Some(path) = &args.merge_output {
+            let labels = (display_name(&args.orig), display_name(&args.diff));
+            let merged = merge3(
+                &slices(&base_lines),
+                &slices(&orig_lines),
+                &slices(&diff_lines),
+                (&labels.0, &labels.1),
+            );
+            let mut output = BufWriter::new(File::create(path)?);
+            for value in merged {
+                writeln!(output, "{value}")?;
+            }
+            output.flush()?;
+        }
+        return Ok(differ);
+    }
+
+    let headers = headers(args, labels);
+    let headers = (headers.0.as_str(), headers.1.as_str());
+    if let Some(output) = &args.interactive_review {
+        let patch = args.patch_output.as_deref().map(|path| (path, headers));
+        review::run((&orig_lines, &diff_lines), args.chunk_rows, output, patch)?;
+        return Ok(differ);
+    }
+
+    if args.metrics_only {
+        print_metrics(
+            &mut io::stdout().lock(),
+            &set_metrics(&orig_lines, &diff_lines),
+        )?;
+        return Ok(differ);
+    }
+
+    if let Some(path) = &args.template {
+        let context = report::comparison(
+            labels,
+            (&orig_lines, &diff_lines),
+            &Masking::new(args),
+            args.chunk_rows,
+            &Provenance::new(args)?,
+        );
+        let rendered = template::render(&fs::read_to_string(path)?, &context)
+            .map_err(|err| Category::Parse.error(format!("{}: {err}", path.display())))?;
+        print!("{rendered}");
+        return Ok(differ);
+    }
+
+    if args.format != OutputFormat::Diff {
+        print!(
+            "{}",
+            formats::render(args, orig_index, labels, (&orig_lines, &diff_lines))?
+        );
+        return Ok(differ);
+    }
+
+    if args.count {
+        let [added, removed, unchanged] = line_counts(&orig_lines, &diff_lines, args.chunk_rows);
+        println!("{added} {removed} {unchanged}");
+        return Ok(differ);
+    }
+
+    if args.counts_only {
+        print_counts(
+            &mut io::stdout().lock(),
+            &diff_counts(&orig_lines, &diff_lines, args.chunk_rows),
+        )?;
+        if args.checksums {
+            print_checksums(args)?;
+        }
+        return Ok(differ);
+    }
+
+    if let Some(op) = args.emit {
+        let mut values = set_operation(op, &orig_lines, &diff_lines);
+        if args.deterministic {
+            values.sort_unstable();
+        }
+        let mut output: Box<dyn Write> = match &args.output {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(io::stdout().lock()),
+        };
+        let terminator = if args.print0 { '\0' } else { '\n' };
+        for value in values {
+            write!(output, "{value}{terminator}")?;
+        }
+        output.flush()?;
+        print_metrics(&mut io::stderr(), &set_metrics(&orig_lines, &diff_lines))?;
+        return Ok(differ);
+    }
+
+    if args.print0 {
+        print_changed_values(&orig_lines, &diff_lines, args.chunk_rows)?;
+        return Ok(differ);
+    }
+
+    if args.list {
+        print_list(args, &orig_lines, &diff_lines)?;
+        return Ok(differ);
+    }
+
+    let sources = if !args.line_numbers && args.show_columns.is_empty() {
+        None
+    } else if sorted_sources.is_some() {
+        sorted_sources
+    } else {
+        Some((
+            source_records(&args.orig, orig_index, &args.parse)?,
+            source_records(
+                &args.diff,
+                args.diff_index.unwrap_or(orig_index),
+                &args.parse,
+            )?,
+        ))
+    };
+    let (removed, added) = if args.side_by_side {
+        side_by_side::print(args, &orig_lines, &diff_lines)
+    } else {
+        print_diff(
+            args,
+            &orig_lines,
+            &diff_lines,
+            sources.as_ref(),
+            labels,
+            headers,
+        )
+    };
+    if let Some(path) = &args.patch_output {
+        review::write_patch(
+            path,
+            headers,
+            (&orig_lines, &diff_lines),
+            args.chunk_rows,
+            |idx| hunk_selected(args, idx),
+        )?;
+    }
+    if let Some(n) = args.top {
+        print_top_values("removed", &removed, n);
+        print_top_values("added", &added, n);
+    }
+    if args.summary {
+        print_summary(args, &orig_lines, &diff_lines);
+    }
+    if args.checksums {
+        print_checksums(args)?;
+    }
+    Ok(differ)
+}
+
+/// Prints the size and SHA-256 digest of both inputs.
+fn print_checksums(args: &CompareArgs) -> Result<()> {
+    let (orig, diff) = fingerprint::inputs(&args.orig, &args.diff)?;
+    println!("{}: {orig}", display_name(&args.orig));
+    println!("{}: {diff}", display_name(&args.diff));
+    Ok(())
+}
+
+/// `--emit` and `--metrics-only` with `--max-memory`: both columns are
+/// partitioned by hash on disk, sized so a pair of partitions fits `budget`.
+fn compare_partitioned(args: &CompareArgs, orig_index: usize, budget: usize) -> Result<bool> {
+    let size = fs::metadata(&args.orig)?.len() + fs::metadata(&args.diff)?.len();
+    let count = usize::try_from(size)?.div_ceil(budget.max(1));
+    let read = |path: &Path, index: usize| -> Result<(Partitions, Preview)> {
+        let mut partitions = Partitions::new(count)?;
+        let mut preview = Preview::default();
+        for_each_value(path, index, &args.parse, |value| {
+            preview.see(&value);
+            partitions.push(&value)
+        })?;
+        Ok((partitions, preview))
+    };
+    let (orig, orig_preview) = read(&args.orig, orig_index)?;
+    let (diff, diff_preview) = read(&args.diff, args.diff_index.unwrap_or(orig_index))?;
+    if !args.yes {
+        orig_preview.prompt(&diff_preview)?;
+    }
+
+    let mut output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+    let terminator = if args.print0 { '\0' } else { '\n' };
+    let metrics = partitioned_sets(args.emit, orig, diff, |value| {
+        Ok(write!(output, "{value}{terminator}")?)
+    })?;
+    output.flush()?;
+    if args.metrics_only {
+        print_metrics(&mut io::stdout().lock(), &metrics)?;
+    } else {
+        print_metrics(&mut io::stderr(), &metrics)?;
+    }
+    Ok(metrics.only_orig + metrics.only_diff > 0)
+}
+
+/// Record count and first record of a streamed column, for the confirmation
+/// prompt.
+#[derive(Default)]
+struct Preview {
+    len: usize,
+    first: Option<String>,
+}
+
+impl Preview {
+    fn see(&mut self, value: &str) {
+        if self.first.is_none() {
+            self.first = Some(value.to_string());
+        }
+        self.len += 1;
+    }
+
+    fn prompt(&self, diff: &Preview) -> Result<()> {
+        prompt_records(
+            (self.len, self.first.as_deref()),
+            (diff.len, diff.first.as_deref()),
+        )
+    }
+}
+
+/// Whether the output needs both columns read even for byte-identical inputs,
+/// which otherwise only print that they are identical.
+fn needs_full_pipeline(args: &CompareArgs) -> bool {
+    args.emit.is_some()
+        || args.brief
+        || args.interactive_review.is_some()
+        || args.metrics_only
+        || args.base.is_some()
+        || args.diff_format != DiffFormat::Csv
+        || args.emit_normalized.is_some()
+        || args.count
+        || args.counts_only
+        || args.summary
+        || args.format != OutputFormat::Diff
+        || args.template.is_some()
+        || args.checksums
+        || args.notify_url.is_some()
+        || args.top.is_some()
+        || args.html.is_some()
+        || args.patch_output.is_some()
+        || args.side_by_side
+}
+
+/// Memory budget per column for comparing externally sorted columns, when
+/// `--sort` runs with `--max-memory` and the output needs no column in memory.
+fn external_sort_budget(args: &CompareArgs) -> Option<usize> {
+    let in_memory = args.emit.is_some()
+        || args.metrics_only
+        || args.base.is_some()
+        || args.interactive_review.is_some()
+        || args.patch_output.is_some()
+        || args.html.is_some()
+        || args.tool.is_some()
+        || args.print0
+        || args.list
+        || args.count
+        || args.side_by_side
+        || args.top.is_some()
+        || args.summary
+        || args.template.is_some()
+        || args.notify_url.is_some()
+        || args.format != OutputFormat::Diff
+        || args.diff_format != DiffFormat::Csv
+        || args.emit_normalized.is_some()
+        || !args.sort_by.is_empty()
+        || !args.select_hunks.is_empty()
+        || args.parse.transform_cmd.is_some();
+    if !args.sort || in_memory {
+        return None;
+    }
+    args.parse.max_memory.map(|budget| budget / 2)
+}
+
+/// Compares both columns by merging their externally sorted runs, printing
+/// removed and added values as they are found. Returns whether they differ.
+fn compare_sorted(args: &CompareArgs, orig_index: usize, budget: usize) -> Result<bool> {
+    let read = |path: &Path, index: usize| -> Result<(Spill, Preview)> {
+        let mut spill = Spill::sorted(budget, args.sort_order);
+        let mut preview = Preview::default();
+        for_each_value(path, index, &args.parse, |value| {
+            preview.see(&value);
+            spill.push(value)
+        })?;
+        Ok((spill, preview))
+    };
+    let (orig, orig_preview) = read(&args.orig, orig_index)?;
+    let (diff, diff_preview) = read(&args.diff, args.diff_index.unwrap_or(orig_index))?;
+    if !args.yes && !args.brief {
+        orig_preview.prompt(&diff_preview)?;
+    }
+
+    let labels = labels(args)?;
+    let labels = (labels.0.as_str(), labels.1.as_str());
+    let print = !args.brief && !args.counts_only;
+    if print {
+        let headers = headers(args, labels);
+        println!("diff {} {}", labels.0, labels.1);
+        println!("--- {}", headers.0);
+        println!("+++ {}", headers.1);
+    }
+
+    let (mut orig, mut diff) = (orig.into_sorted()?, diff.into_sorted()?);
+    let (mut old, mut new) = (orig.next().transpose()?, diff.next().transpose()?);
+    let mut counts = Counts::default();
+    loop {
+        let ord = match (&old, &new) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(old), Some(new)) => total_cmp(old, new, args.sort_order),
+        };
+        if ord != Ordering::Equal && args.brief {
+            announce("files-differ", args);
+            return Ok(true);
+        }
+        match ord {
+            Ordering::Less => {
+                if print && let Some(value) = &old {
+                    print_change(args, "-", value, ("", ""), Some(Color::Red));
+                }
+                counts.removed += 1;
+                old = orig.next().transpose()?;
+            }
+            Ordering::Greater => {
+                if print && let Some(value) = &new {
+                    print_change(args, "+", value, ("", ""), Some(Color::Green));
+                }
+                counts.added += 1;
+                new = diff.next().transpose()?;
+            }
+            Ordering::Equal => {
+                old = orig.next().transpose()?;
+                new = diff.next().transpose()?;
+            }
+        }
+    }
+
+    let differ = counts != Counts::default();
+    if args.counts_only {
+        print_counts(&mut io::stdout().lock(), &counts)?;
+    } else if args.report_identical && !differ {
+        announce("columns-identical", args);
+    }
+    if args.checksums {
+        print_checksums(args)?;
+    }
+    Ok(differ)
+}
+
+/// Writes every removed and added value, in diff order, each terminated by a
+/// NUL byte for `xargs -0` and similar tools.
+fn print_changed_values(
+    orig_lines: &[String],
+    diff_lines: &[String],
+    chunk_rows: usize,
+) -> Result<()> {
+    let orig = slices(orig_lines);
+    let diff = slices(diff_lines);
+    let mut output = BufWriter::new(io::stdout().lock());
+    let ops = diff_ops(&orig, &diff, chunk_rows);
+    for change in ops
+        .iter()
+        .flat_map(|op| op.iter_changes(&orig[..], &diff[..]))
+    {
+        if change.tag() != ChangeTag::Equal {
+            write!(output, "{}\0", change.value())?;
+        }
+    }
+    output.flush()?;
+    Ok(())
+}
+
+/// Prints each change of `--list` as its sign followed by the value.
+fn print_list(args: &CompareArgs, orig_lines: &[String], diff_lines: &[String]) -> Result<()> {
+    let orig = slices(orig_lines);
+    let diff = slices(diff_lines);
+    let masking = Masking::new(args);
+    let mut output = BufWriter::new(io::stdout().lock());
+    let ops = diff_ops(&orig, &diff, args.chunk_rows);
+    for change in ops
+        .iter()
+        .flat_map(|op| op.iter_changes(&orig[..], &diff[..]))
+        .filter(|change| matches_filter(args, change.value()))
+    {
+        match change.tag() {
+            ChangeTag::Delete => writeln!(output, "-{}", masking.apply(change.value()))?,
+            ChangeTag::Insert => writeln!(output, "+{}", masking.apply(change.value()))?,
+            ChangeTag::Equal => {}
+        }
+    }
+    output.flush()?;
+    Ok(())
+}
+
+fn print_summary(args: &CompareArgs, orig_lines: &[String], diff_lines: &[String]) {
+    print_duplicates(&display_name(&args.orig), orig_lines);
+    print_duplicates(&display_name(&args.diff), diff_lines);
+}
+
+fn print_metrics(output: &mut impl Write, metrics: &SetMetrics) -> Result<()> {
+    writeln!(output, "only in orig: {}", metrics.only_orig)?;
+    writeln!(output, "only in diff: {}", metrics.only_diff)?;
+    writeln!(output, "in both: {}", metrics.both)?;
+    writeln!(output, "jaccard similarity: {:.4}", metrics.jaccard)?;
+    writeln!(output, "overlap coefficient: {:.4}", metrics.overlap)?;
+    Ok(())
+}
+
+/// ` [side:line] ` label and ` (context, columns)` suffix of the value at
+/// `index`, taken from the source record it was extracted from.
+fn annotations(
+    args: &CompareArgs,
+    side: &str,
+    records: Option<&Vec<StringRecord>>,
+    index: Option<usize>,
+) -> (String, String) {
+    let Some(record) = records
+        .zip(index)
+        .and_then(|(records, index)| records.get(index))
+    else {
+        return (String::new(), String::new());
+    };
+    let label = match record.position() {
+        Some(pos) if args.line_numbers => format!(" [{side}:{}] ", pos.line()),
+        _ => String::new(),
+    };
+    let context = if args.show_columns.is_empty() {
+        String::new()
+    } else {
+        let masking = Masking::new(args);
+        let columns: Vec<Cow<str>> = args
+            .show_columns
+            .iter()
+            .map(|&column| masking.apply(record.get(column - 1).unwrap_or("")))
+            .collect();
+        format!(" ({})", columns.join(", ")).dimmed().to_string()
+    };
+    (label, context)
+}
+
+/// File name of `path` for display, or the whole path if it has none.
+pub fn file_name(path: &Path) -> Cow<'_, str> {
+    match path.file_name() {
+        Some(name) => name.to_string_lossy(),
+        None => path.to_string_lossy(),
+    }
+}
+
+/// Header labels of orig and diff: `--label` values, or `a/{file}` and
+/// `b/{file}`.
+fn labels(args: &CompareArgs) -> Result<(String, String)> {
+    let orig = || format!("a/{}", file_name(&args.orig));
+    let diff = || format!("b/{}", file_name(&args.diff));
+    match args.label.as_slice() {
+        [] => Ok((orig(), diff())),
+        [orig] => Ok((orig.clone(), diff())),
+        [orig, diff] => Ok((orig.clone(), diff.clone())),
+        _ => Err(anyhow!(
+            "--label can be given at most twice, for orig and diff"
+        )),
+    }
+}
+
+/// `---`/`+++` header names: the labels, followed by the modification time
+/// of the files not labeled by `--label` unless `--deterministic` was given,
+/// as GNU diff does.
+fn headers(args: &CompareArgs, labels: (&str, &str)) -> (String, String) {
+    let header = |label: &str, path: &Path, labeled: bool| {
+        let modified = fs::metadata(path).and_then(|metadata| metadata.modified());
+        match modified {
+            Ok(time) if !labeled && !args.deterministic => {
+                format!("{label}\t{}", format_timestamp(time))
+            }
+            _ => label.to_string(),
+        }
+    };
+    (
+        header(labels.0, &args.orig, !args.label.is_empty()),
+        header(labels.1, &args.diff, args.label.len() > 1),
+    )
+}
+
+/// Whether the hunk at the 0-based `idx` is selected by `--select-hunks`.
+pub fn hunk_selected(args: &CompareArgs, idx: usize) -> bool {
+    args.select_hunks.is_empty() || args.select_hunks.contains(&(idx + 1))
+}
+
+/// Prints a diff line, fitted to `--max-width`, with the annotation label
+/// before its first and the context after its last piece.
+fn print_change(
+    args: &CompareArgs,
+    tag: &str,
+    value: &str,
+    (label, context): (&str, &str),
+    color: Option<Color>,
+) {
+    let value = Masking::new(args).apply(value);
+    let pieces = fit(&value, args.max_width, args.wrap);
+    let last = pieces.len() - 1;
+    for (idx, piece) in pieces.into_iter().enumerate() {
+        let (tag, piece) = match color {
+            Some(color) => (tag.color(color), piece.color(color)),
+            None => (tag.normal(), piece.normal()),
+        };
+        let label = if idx == 0 { label } else { "" };
+        let context = if idx == last { context } else { "" };
+        println!("{tag}{label}{piece}{context}");
+    }
+}
+
+/// Whether a changed value passes `--show-matching`.
+fn matches_filter(args: &CompareArgs, value: &str) -> bool {
+    args.show_matching
+        .as_ref()
+        .is_none_or(|regex| regex.is_match(value))
+}
+
+/// Tracks `--max-hunks` and `--max-lines`, counting the changes left out
+/// once either is reached.
+struct Truncation {
+    max_hunks: Option<usize>,
+    max_lines: Option<usize>,
+    hunks: usize,
+    lines: usize,
+    printing: bool,
+    hidden: usize,
+}
+
+impl Truncation {
+    fn new(args: &CompareArgs) -> Self {
+        Self {
+            max_hunks: args.max_hunks,
+            max_lines: args.max_lines,
+            hunks: 0,
+            lines: 0,
+            printing: true,
+            hidden: 0,
+        }
+    }
+
+    fn lines_left(&self) -> bool {
+        self.max_lines.is_none_or(|max| self.lines < max)
+    }
+
+    /// Whether to print the next hunk.
+    fn hunk(&mut self) -> bool {
+        self.printing &= self.max_hunks.is_none_or(|max| self.hunks < max) && self.lines_left();
+        self.hunks += 1;
+        self.printing
+    }
+
+    /// Whether to print the next line of a hunk, a change or context.
+    fn line(&mut self, change: bool) -> bool {
+        self.printing &= self.lines_left();
+        if self.printing {
+            self.lines += 1;
+        } else if change {
+            self.hidden += 1;
+        }
+        self.printing
+    }
+}
+
+/// Prints a unified diff of both columns and returns the removed and added
+/// values.
+fn print_diff<'a>(
+    args: &CompareArgs,
+    orig_lines: &'a [String],
+    diff_lines: &'a [String],
+    sources: Option<&(Vec<StringRecord>, Vec<StringRecord>)>,
+    labels: (&str, &str),
+    headers: (&str, &str),
+) -> (Vec<&'a str>, Vec<&'a str>) {
+    let orig_records = sources.map(|(orig, _)| orig);
+    let diff_records = sources.map(|(_, diff)| diff);
+    println!("diff {} {}", labels.0, labels.1);
+    println!("--- {}", headers.0);
+    println!("+++ {}", headers.1);
+
+    let orig_slices: Vec<&str> = orig_lines.iter().map(String::as_str).collect();
+    let diff_slices: Vec<&str> = diff_lines.iter().map(String::as_str).collect();
+
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    let hunks = grouped_ops(&orig_slices, &diff_slices, args.chunk_rows, 3);
+    let moves = if args.detect_moves || args.ignore_moves {
+        Moves::detect(hunks.iter().flatten(), &orig_slices, &diff_slices)
+    } else {
+        Moves::default()
+    };
+    let is_moved = |change: &Change<&str>| match change.tag() {
+        ChangeTag::Delete => moves.moved_to(change.old_index()).is_some(),
+        ChangeTag::Insert => moves.is_moved_here(change.new_index()),
+        ChangeTag::Equal => false,
+    };
+    // Whether a change is printed, before truncation: moves show once, at
+    // their orig position, and `--show-matching` hides other changes.
+    let shows = |change: &Change<&str>| match change.tag() {
+        ChangeTag::Equal => !args.changes_only,
+        _ if is_moved(change) => {
+            !args.ignore_moves
+                && moves.moved_to(change.old_index()).is_some()
+                && matches_filter(args, change.value())
+        }
+        _ => matches_filter(args, change.value()),
+    };
+    let mut truncation = Truncation::new(args);
+    for (idx, ops) in hunks.iter().enumerate() {
+        if !hunk_selected(args, idx) {
+            continue;
+        }
+        let changes = || {
+            ops.iter()
+                .flat_map(|op| op.iter_changes(&orig_slices[..], &diff_slices[..]))
+        };
+        let record =
+            |change: &Change<&'a str>, removed: &mut Vec<&'a str>, added: &mut Vec<&'a str>| {
+                match change.tag() {
+                    ChangeTag::Delete if !is_moved(change) => removed.push(change.value()),
+                    ChangeTag::Insert if !is_moved(change) => added.push(change.value()),
+                    _ => {}
+                }
+            };
+        if !changes().any(|change| change.tag() != ChangeTag::Equal && shows(&change)) {
+            for change in changes() {
+                record(&change, &mut removed, &mut added);
+            }
+            continue;
+        }
+        if truncation.hunk() {
+            println!("{}", UnifiedHunkHeader::new(ops).to_string().cyan());
+        }
+        for change in changes() {
+            record(&change, &mut removed, &mut added);
+            if !shows(&change) || !truncation.line(change.tag() != ChangeTag::Equal) {
+                continue;
+            }
+            match change.tag() {
+                ChangeTag::Delete if is_moved(&change) => {
+                    let from = change.old_index().unwrap_or(0) + 1;
+                    let to = moves.moved_to(change.old_index()).unwrap_or(0) + 1;
+                    let label = format!(" [moved {from} -> {to}] ");
+                    print_change(args, "~", change.value(), (&label, ""), Some(Color::Yellow));
+                }
+                ChangeTag::Delete => {
+                    let (label, context) =
+                        annotations(args, "orig", orig_records, change.old_index());
+                    print_change(
+                        args,
+                        "-",
+                        change.value(),
+                        (&label, &context),
+                        Some(Color::Red),
+                    )
+                }
+                ChangeTag::Insert => {
+                    let (label, context) =
+                        annotations(args, "diff", diff_records, change.new_index());
+                    print_change(
+                        args,
+                        "+",
+                        change.value(),
+                        (&label, &context),
+                        Some(Color::Green),
+                    )
+                }
+                ChangeTag::Equal => print_change(args, " ", change.value(), ("", ""), None),
+            }
+        }
+    }
+    if truncation.hidden > 0 {
+        let count = group_thousands(truncation.hidden);
+        println!("{}", tr("more-changes", &[("count", &count)]).dimmed());
+    }
+    (removed, added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::FileWriteStr;
+
+    #[test]
+    fn test_files_identical() {
+        let orig = assert_fs::NamedTempFile::new("orig.csv").unwrap();
+        let diff = assert_fs::NamedTempFile::new("diff.csv").unwrap();
+        orig.write_str("1,2,3\n4,5,6").unwrap();
+        diff.write_str("1,2,3\n4,5,6").unwrap();
+        assert!(files_identical(orig.path(), diff.path()).unwrap());
+
+        diff.write_str("1,2,3\n4,5,7").unwrap();
+        assert!(!files_identical(orig.path(), diff.path()).unwrap());
+    }
+}
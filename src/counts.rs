@@ -0,0 +1,87 @@
+use std::io::Write;
+
+use anyhow::Result;
+use similar::DiffOp;
+
+use crate::chunked::diff_ops;
+use crate::diff3::slices;
+
+/// Aggregate numbers of a comparison, without any values.
+#[derive(Debug, Default, PartialEq)]
+pub struct Counts {
+    pub removed: usize,
+    pub added: usize,
+    pub changed: usize,
+}
+
+/// Counts of the diff of both columns. Replaced values pair up as changes,
+/// the rest of a replacement counts as removed or added.
+pub fn diff_counts(orig: &[String], diff: &[String], chunk_rows: usize) -> Counts {
+    let (orig, diff) = (slices(orig), slices(diff));
+    let mut counts = Counts::default();
+    for op in diff_ops(&orig, &diff, chunk_rows) {
+        match op {
+            DiffOp::Equal { .. } => {}
+            DiffOp::Delete { old_len, .. } => counts.removed += old_len,
+            DiffOp::Insert { new_len, .. } => counts.added += new_len,
+            DiffOp::Replace {
+                old_len, new_len, ..
+            } => {
+                let changed = old_len.min(new_len);
+                counts.changed += changed;
+                counts.removed += old_len - changed;
+                counts.added += new_len - changed;
+            }
+        }
+    }
+    counts
+}
+
+/// Numbers of added, removed and unchanged values of the diff of both columns,
+/// each changed value counting as removed and added.
+pub fn line_counts(orig: &[String], diff: &[String], chunk_rows: usize) -> [usize; 3] {
+    let (orig, diff) = (slices(orig), slices(diff));
+    let mut counts = [0; 3];
+    for op in diff_ops(&orig, &diff, chunk_rows) {
+        match op {
+            DiffOp::Equal { len, .. } => counts[2] += len,
+            DiffOp::Delete { old_len, .. } => counts[1] += old_len,
+            DiffOp::Insert { new_len, .. } => counts[0] += new_len,
+            DiffOp::Replace {
+                old_len, new_len, ..
+            } => {
+                counts[0] += new_len;
+                counts[1] += old_len;
+            }
+        }
+    }
+    counts
+}
+
+pub fn print_counts(output: &mut impl Write, counts: &Counts) -> Result<()> {
+    writeln!(output, "removed: {}", counts.removed)?;
+    writeln!(output, "added: {}", counts.added)?;
+    writeln!(output, "changed: {}", counts.changed)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_counts() {
+        let orig: Vec<String> = ["a", "b", "c", "d", "e"].map(String::from).to_vec();
+        let diff: Vec<String> = ["a", "B", "c", "e", "f", "g"].map(String::from).to_vec();
+        assert_eq!(
+            diff_counts(&orig, &diff, usize::MAX),
+            Counts {
+                removed: 1,
+                added: 2,
+                changed: 1
+            }
+        );
+        assert_eq!(diff_counts(&orig, &orig, usize::MAX), Counts::default());
+        assert_eq!(line_counts(&orig, &diff, usize::MAX), [3, 2, 3]);
+    }
+}
@@ -0,0 +1,186 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = i64::from(month);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Proleptic Gregorian date of a number of days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Formats a file timestamp like GNU diff headers do, in UTC:
+/// `2024-02-29 12:30:05.000000000 +0000`.
+pub fn format_timestamp(time: SystemTime) -> String {
+    let (secs, nanos) = match time.duration_since(UNIX_EPOCH) {
+        Ok(elapsed) => (elapsed.as_secs() as i64, elapsed.subsec_nanos()),
+        Err(err) => {
+            let before = err.duration();
+            let secs = -(before.as_secs() as i64);
+            match before.subsec_nanos() {
+                0 => (secs, 0),
+                nanos => (secs - 1, 1_000_000_000 - nanos),
+            }
+        }
+    };
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let secs = secs.rem_euclid(86_400);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}.{nanos:09} +0000",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Number of days of `month` in `year` of the Gregorian calendar.
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn take_number(input: &mut &str, max_digits: usize) -> Option<i64> {
+    let len = input
+        .bytes()
+        .take(max_digits)
+        .take_while(u8::is_ascii_digit)
+        .count();
+    if len == 0 {
+        return None;
+    }
+    let (digits, rest) = input.split_at(len);
+    *input = rest;
+    digits.parse().ok()
+}
+
+/// Parses `value` according to a strftime-like `format` and returns seconds
+/// since the Unix epoch. Supported specifiers are `%Y`, `%m`, `%d`, `%H`,
+/// `%M`, `%S` and `%%`; everything else must match literally.
+pub fn parse_datetime(value: &str, format: &str) -> Option<i64> {
+    let (mut year, mut month, mut day) = (1970, 1, 1);
+    let (mut hour, mut minute, mut second) = (0, 0, 0);
+    let mut input = value.trim();
+    let mut spec = format.chars();
+
+    while let Some(c) = spec.next() {
+        if c != '%' {
+            input = input.strip_prefix(c)?;
+            continue;
+        }
+        match spec.next()? {
+            'Y' => year = take_number(&mut input, 4)?,
+            'm' => month = take_number(&mut input, 2)?,
+            'd' => day = take_number(&mut input, 2)?,
+            'H' => hour = take_number(&mut input, 2)?,
+            'M' => minute = take_number(&mut input, 2)?,
+            'S' => second = take_number(&mut input, 2)?,
+            '%' => input = input.strip_prefix('%')?,
+            _ => return None,
+        }
+    }
+    if !input.is_empty()
+        || !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+    let days = days_from_civil(year, month as u32, day as u32);
+    Some(days * 86_400 + hour * 3600 + minute * 60 + second)
+}
+
+/// Parses a duration such as `5s`, `2m`, `1h` or `1d` (plain numbers are
+/// seconds) into seconds.
+pub fn parse_duration(value: &str) -> Result<i64, String> {
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(pos) => value.split_at(pos),
+        None => (value, "s"),
+    };
+    let scale = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        _ => {
+            return Err(format!(
+                "unknown duration unit `{unit}`, expected s, m, h or d"
+            ));
+        }
+    };
+    number
+        .parse::<i64>()
+        .ok()
+        .and_then(|number| number.checked_mul(scale))
+        .ok_or_else(|| format!("invalid duration `{value}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_datetime() {
+        assert_eq!(parse_datetime("1970-01-01", "%Y-%m-%d"), Some(0));
+        assert_eq!(
+            parse_datetime("2024-02-29 12:30:05", "%Y-%m-%d %H:%M:%S"),
+            Some(1_709_209_805)
+        );
+        assert_eq!(
+            parse_datetime("29/02/2024", "%d/%m/%Y"),
+            Some(1_709_164_800)
+        );
+        assert_eq!(parse_datetime("2024-13-01", "%Y-%m-%d"), None);
+        assert_eq!(parse_datetime("2024-01-01x", "%Y-%m-%d"), None);
+        assert_eq!(parse_datetime("2023-02-29", "%Y-%m-%d"), None);
+        assert_eq!(parse_datetime("2024-04-31", "%Y-%m-%d"), None);
+        assert_eq!(parse_datetime("1900-02-29", "%Y-%m-%d"), None);
+        assert!(parse_datetime("2000-02-29", "%Y-%m-%d").is_some());
+        assert!(parse_datetime("2023-12-31", "%Y-%m-%d").is_some());
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        let time = UNIX_EPOCH + std::time::Duration::new(1_709_209_805, 5);
+        assert_eq!(
+            format_timestamp(time),
+            "2024-02-29 12:30:05.000000005 +0000"
+        );
+        assert_eq!(
+            format_timestamp(UNIX_EPOCH - std::time::Duration::from_secs(1)),
+            "1969-12-31 23:59:59.000000000 +0000"
+        );
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("5s"), Ok(5));
+        assert_eq!(parse_duration("90"), Ok(90));
+        assert_eq!(parse_duration("2m"), Ok(120));
+        assert_eq!(parse_duration("1d"), Ok(86_400));
+        assert!(parse_duration("5w").is_err());
+        assert!(parse_duration("s").is_err());
+        assert!(parse_duration("9223372036854775807d").is_err());
+    }
+}
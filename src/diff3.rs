@@ -0,0 +1,273 @@
+use std::ops::Range;
+
+use colored::Colorize;
+use similar::{Algorithm, DiffOp, capture_diff_slices};
+
+/// Classification of a region where orig or diff departs from base.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkKind {
+    OnlyOrig,
+    OnlyDiff,
+    /// Both sides made the same change.
+    Both,
+    Conflict,
+}
+
+impl ChunkKind {
+    fn label(self) -> &'static str {
+        match self {
+            ChunkKind::OnlyOrig => "only in orig",
+            ChunkKind::OnlyDiff => "only in diff",
+            ChunkKind::Both => "changed in both",
+            ChunkKind::Conflict => "conflicting",
+        }
+    }
+}
+
+/// Region of a three-way comparison, as ranges into base, orig and diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Chunk {
+    Stable {
+        base: Range<usize>,
+    },
+    Changed {
+        kind: ChunkKind,
+        base: Range<usize>,
+        orig: Range<usize>,
+        diff: Range<usize>,
+    },
+}
+
+/// For every base line, the index of the line it is matched with in `other`.
+fn matches(base: &[&str], other: &[&str]) -> Vec<Option<usize>> {
+    let mut res = vec![None; base.len()];
+    for op in capture_diff_slices(Algorithm::Myers, base, other) {
+        if let DiffOp::Equal {
+            old_index,
+            new_index,
+            len,
+        } = op
+        {
+            for offset in 0..len {
+                res[old_index + offset] = Some(new_index + offset);
+            }
+        }
+    }
+    res
+}
+
+/// Splits the three inputs into stable regions, where all of them agree, and
+/// changed regions classified the way `diff3` does.
+pub fn diff3(base: &[&str], orig: &[&str], diff: &[&str]) -> Vec<Chunk> {
+    let orig_matches = matches(base, orig);
+    let diff_matches = matches(base, diff);
+    let (mut i, mut j, mut k) = (0, 0, 0);
+    let mut chunks = Vec::new();
+
+    loop {
+        let stable_start = i;
+        while i < base.len() && orig_matches[i] == Some(j) && diff_matches[i] == Some(k) {
+            (i, j, k) = (i + 1, j + 1, k + 1);
+        }
+        if i > stable_start {
+            chunks.push(Chunk::Stable {
+                base: stable_start..i,
+            });
+        }
+
+        let next =
+            (i..base.len()).find_map(|idx| Some((idx, orig_matches[idx]?, diff_matches[idx]?)));
+        let (next_i, next_j, next_k) = next.unwrap_or((base.len(), orig.len(), diff.len()));
+        if (next_i, next_j, next_k) == (i, j, k) {
+            if i == base.len() {
+                return chunks;
+            }
+            continue;
+        }
+
+        let (base_part, orig_part, diff_part) =
+            (&base[i..next_i], &orig[j..next_j], &diff[k..next_k]);
+        let kind = if orig_part == base_part {
+            ChunkKind::OnlyDiff
+        } else if diff_part == base_part {
+            ChunkKind::OnlyOrig
+        } else if orig_part == diff_part {
+            ChunkKind::Both
+        } else {
+            ChunkKind::Conflict
+        };
+        chunks.push(Chunk::Changed {
+            kind,
+            base: i..next_i,
+            orig: j..next_j,
+            diff: k..next_k,
+        });
+        (i, j, k) = (next_i, next_j, next_k);
+    }
+}
+
+/// Merges the changes of orig and diff into base, wrapping conflicting
+/// regions in `<<<<<<<`/`=======`/`>>>>>>>` markers.
+pub fn merge3(base: &[&str], orig: &[&str], diff: &[&str], labels: (&str, &str)) -> Vec<String> {
+    let mut merged = Vec::with_capacity(base.len());
+    for chunk in diff3(base, orig, diff) {
+        match chunk {
+            Chunk::Stable { base: range } => {
+                merged.extend(base[range].iter().map(|value| value.to_string()))
+            }
+            Chunk::Changed {
+                kind: ChunkKind::OnlyDiff,
+                diff: range,
+                ..
+            } => merged.extend(diff[range].iter().map(|value| value.to_string())),
+            Chunk::Changed {
+                kind: ChunkKind::OnlyOrig | ChunkKind::Both,
+                orig: range,
+                ..
+            } => merged.extend(orig[range].iter().map(|value| value.to_string())),
+            Chunk::Changed {
+                kind: ChunkKind::Conflict,
+                orig: orig_range,
+                diff: diff_range,
+                ..
+            } => {
+                merged.push(format!("<<<<<<< {}", labels.0));
+                merged.extend(orig[orig_range].iter().map(|value| value.to_string()));
+                merged.push("=======".to_string());
+                merged.extend(diff[diff_range].iter().map(|value| value.to_string()));
+                merged.push(format!(">>>>>>> {}", labels.1));
+            }
+        }
+    }
+    merged
+}
+
+fn range_header(range: &Range<usize>) -> String {
+    format!("{},{}", range.start + 1, range.len())
+}
+
+pub fn slices(lines: &[String]) -> Vec<&str> {
+    lines.iter().map(String::as_str).collect()
+}
+
+pub fn print_diff3(base: &[String], orig: &[String], diff: &[String]) {
+    let (base, orig, diff) = (slices(base), slices(orig), slices(diff));
+    let chunks = diff3(&base, &orig, &diff);
+    let mut counts = [0; 4];
+    for chunk in &chunks {
+        let Chunk::Changed {
+            kind,
+            base: base_range,
+            orig: orig_range,
+            diff: diff_range,
+        } = chunk
+        else {
+            continue;
+        };
+        counts[*kind as usize] += 1;
+        let header = format!(
+            "@@ base {} orig {} diff {} @@ {}",
+            range_header(base_range),
+            range_header(orig_range),
+            range_header(diff_range),
+            kind.label()
+        );
+        if *kind == ChunkKind::Conflict {
+            println!("{}", header.red());
+        } else {
+            println!("{}", header.cyan());
+        }
+        for value in &base[base_range.clone()] {
+            println!("  base: {value}");
+        }
+        if *kind != ChunkKind::OnlyDiff {
+            for value in &orig[orig_range.clone()] {
+                println!("{}", format!("  orig: {value}").red());
+            }
+        }
+        if matches!(kind, ChunkKind::OnlyDiff | ChunkKind::Conflict) {
+            for value in &diff[diff_range.clone()] {
+                println!("{}", format!("  diff: {value}").green());
+            }
+        }
+    }
+    println!(
+        "{} only in orig, {} only in diff, {} changed in both, {} conflicting",
+        counts[ChunkKind::OnlyOrig as usize],
+        counts[ChunkKind::OnlyDiff as usize],
+        counts[ChunkKind::Both as usize],
+        counts[ChunkKind::Conflict as usize]
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(base: &[&str], orig: &[&str], diff: &[&str]) -> Vec<(ChunkKind, Range<usize>)> {
+        diff3(base, orig, diff)
+            .into_iter()
+            .filter_map(|chunk| match chunk {
+                Chunk::Changed { kind, base, .. } => Some((kind, base)),
+                Chunk::Stable { .. } => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_diff3() {
+        let base = ["a", "b", "c", "d", "e"];
+        assert_eq!(
+            kinds(
+                &base,
+                &["a", "B", "c", "d", "e"],
+                &["a", "b", "c", "D", "e", "f"]
+            ),
+            [
+                (ChunkKind::OnlyOrig, 1..2),
+                (ChunkKind::OnlyDiff, 3..4),
+                (ChunkKind::OnlyDiff, 5..5)
+            ]
+        );
+        assert_eq!(
+            kinds(
+                &base,
+                &["a", "x", "c", "d", "e"],
+                &["a", "y", "c", "d", "e"]
+            ),
+            [(ChunkKind::Conflict, 1..2)]
+        );
+        assert_eq!(
+            kinds(
+                &base,
+                &["x", "b", "c", "d", "e"],
+                &["x", "b", "c", "d", "e"]
+            ),
+            [(ChunkKind::Both, 0..1)]
+        );
+        assert!(kinds(&base, &base, &base).is_empty());
+    }
+
+    #[test]
+    fn test_merge3() {
+        let base = ["a", "b", "c", "d", "e", "f", "g"];
+        let orig = ["a", "B", "c", "d", "e", "f", "x"];
+        let diff = ["a", "b", "c", "D", "e", "f", "y"];
+        assert_eq!(
+            merge3(&base, &orig, &diff, ("orig.csv", "diff.csv")),
+            [
+                "a",
+                "B",
+                "c",
+                "D",
+                "e",
+                "f",
+                "<<<<<<< orig.csv",
+                "x",
+                "=======",
+                "y",
+                ">>>>>>> diff.csv"
+            ]
+        );
+    }
+}
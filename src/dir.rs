@@ -0,0 +1,388 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use anyhow::{Result, anyhow};
+use colored::Colorize;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use walkdir::WalkDir;
+
+use crate::checkpoint::Checkpoint;
+use crate::cli::{CompareArgs, DiffFormat, OutputFormat};
+use crate::compare::compare_files;
+use crate::errors::Category;
+use crate::html::{self, IndexEntry};
+use crate::i18n::tr;
+use crate::logging;
+
+/// Result of comparing one relative path of both directories.
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Identical,
+    Differ,
+    OnlyOrig,
+    OnlyDiff,
+    Error(String),
+}
+
+/// `--include` and `--exclude` patterns, matched against the relative path and
+/// the file name.
+struct Filter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern)?);
+    }
+    Ok(builder.build()?)
+}
+
+impl Filter {
+    fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Self {
+            include: if include.is_empty() {
+                None
+            } else {
+                Some(glob_set(include)?)
+            },
+            exclude: glob_set(exclude)?,
+        })
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        let name = path.file_name().map(Path::new).unwrap_or(path);
+        let matches = |set: &GlobSet| set.is_match(path) || set.is_match(name);
+        self.include.as_ref().is_none_or(matches) && !matches(&self.exclude)
+    }
+}
+
+/// Files below `dir`, relative to it, that pass `filter`. Only direct
+/// children unless `recursive`.
+fn relative_files(dir: &Path, recursive: bool, filter: &Filter) -> Result<BTreeSet<PathBuf>> {
+    let walker = WalkDir::new(dir).min_depth(1);
+    let walker = if recursive {
+        walker
+    } else {
+        walker.max_depth(1)
+    };
+    let mut files = BTreeSet::new();
+    for entry in walker {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path().strip_prefix(dir)?;
+        if filter.matches(path) {
+            files.insert(path.to_path_buf());
+        }
+    }
+    Ok(files)
+}
+
+/// Path of the HTML report of a pair, relative to the `--html` directory.
+fn report_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".html");
+    PathBuf::from(name)
+}
+
+fn compare_pair(args: &CompareArgs, path: &Path) -> Outcome {
+    let mut pair = args.clone();
+    pair.orig = args.orig.join(path);
+    pair.diff = args.diff.join(path);
+    pair.yes = true;
+    pair.html = args.html.as_ref().map(|dir| dir.join(report_path(path)));
+    pair.open = false;
+    // Concurrent diffs would interleave, so parallel runs report one line per pair.
+    pair.brief |= args.jobs > 1;
+    let outcome = match compare_files(&pair, args.report_identical) {
+        Ok(true) => Outcome::Differ,
+        Ok(false) => Outcome::Identical,
+        Err(err) => Outcome::Error(format!("{err:#}")),
+    };
+    let file = ("file", path.display().to_string());
+    match &outcome {
+        Outcome::Error(_) => logging::warn("pair", &[file, ("outcome", outcome.status())]),
+        _ => logging::info("pair", &[file, ("outcome", outcome.status())]),
+    }
+    outcome
+}
+
+/// The outcome recorded in the checkpoint for `path`, or else that of
+/// comparing the pair, which is then recorded. Errored pairs are retried on
+/// the next resume.
+fn resume_or_compare(args: &CompareArgs, checkpoint: Option<&Checkpoint>, path: &Path) -> Outcome {
+    let Some(checkpoint) = checkpoint else {
+        return compare_pair(args, path);
+    };
+    if let Some(differ) = checkpoint.get(path) {
+        logging::info("resumed", &[("file", path.display().to_string())]);
+        return if differ {
+            Outcome::Differ
+        } else {
+            Outcome::Identical
+        };
+    }
+    let outcome = compare_pair(args, path);
+    if matches!(outcome, Outcome::Identical | Outcome::Differ)
+        && let Err(err) = checkpoint.record(path, outcome == Outcome::Differ)
+    {
+        eprintln!("{}", format!("Failed to update checkpoint: {err}").yellow());
+        logging::warn("checkpoint", &[("error", err.to_string())]);
+    }
+    outcome
+}
+
+/// Applies `f` to all `items` on `jobs` threads, in completion order.
+fn parallel_map<T: Sync, R: Send>(items: &[T], jobs: usize, f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(items.len()) {
+            let (sender, next, f) = (sender.clone(), &next, &f);
+            scope.spawn(move || {
+                while let Some(item) = items.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    sender.send(f(item)).unwrap();
+                }
+            });
+        }
+        drop(sender);
+        receiver.iter().collect()
+    })
+}
+
+impl Outcome {
+    fn status(&self) -> String {
+        match self {
+            Outcome::Identical => "identical".to_string(),
+            Outcome::Differ => "differ".to_string(),
+            Outcome::OnlyOrig => "only in orig".to_string(),
+            Outcome::OnlyDiff => "only in diff".to_string(),
+            Outcome::Error(err) => format!("error: {err}"),
+        }
+    }
+}
+
+fn write_index(args: &CompareArgs, dir: &Path, outcomes: &[(PathBuf, Outcome)]) -> Result<()> {
+    let statuses: Vec<String> = outcomes
+        .iter()
+        .map(|(_, outcome)| outcome.status())
+        .collect();
+    let reports: Vec<PathBuf> = outcomes.iter().map(|(path, _)| report_path(path)).collect();
+    let entries: Vec<IndexEntry> = outcomes
+        .iter()
+        .zip(&statuses)
+        .zip(&reports)
+        .map(|(((path, outcome), status), report)| IndexEntry {
+            path,
+            status,
+            passed: *outcome == Outcome::Identical,
+            report: dir.join(report).exists().then_some(report.as_path()),
+        })
+        .collect();
+    let title = format!("{} → {}", args.orig.display(), args.diff.display());
+    html::write_index(dir, &title, &entries)
+}
+
+fn print_summary(outcomes: &[(PathBuf, Outcome)]) {
+    let count = |matches: fn(&Outcome) -> bool| {
+        outcomes
+            .iter()
+            .filter(|(_, outcome)| matches(outcome))
+            .count()
+    };
+    let lines = [
+        (
+            "summary-identical",
+            count(|outcome| *outcome == Outcome::Identical),
+        ),
+        (
+            "summary-differing",
+            count(|outcome| *outcome == Outcome::Differ),
+        ),
+        (
+            "summary-only-orig",
+            count(|outcome| *outcome == Outcome::OnlyOrig),
+        ),
+        (
+            "summary-only-diff",
+            count(|outcome| *outcome == Outcome::OnlyDiff),
+        ),
+        (
+            "summary-errored",
+            count(|outcome| matches!(outcome, Outcome::Error(_))),
+        ),
+    ]
+    .map(|(key, count)| (format!("{}:", tr(key, &[])), count));
+    let width = lines
+        .iter()
+        .map(|(label, _)| label.len())
+        .max()
+        .unwrap_or(0);
+    println!("{}", tr("summary", &[]).bold());
+    for (label, count) in lines {
+        println!("  {label:width$} {count}");
+    }
+}
+
+/// Compares the files of two directories pairwise by relative path and prints
+/// an aggregate summary. Fails unless every pair is identical.
+pub fn run(args: &CompareArgs) -> Result<()> {
+    if args.base.is_some()
+        || args.emit.is_some()
+        || args.metrics_only
+        || args.counts_only
+        || args.count
+        || args.template.is_some()
+        || args.format != OutputFormat::Diff
+        || args.diff_format != DiffFormat::Csv
+        || args.emit_normalized.is_some()
+        || args.interactive_review.is_some()
+        || args.patch_output.is_some()
+    {
+        return Err(anyhow!(
+            "--base, --emit, --metrics-only, --counts-only, --count, --template, --format, --diff-format, --emit-normalized, --interactive-review and --patch-output compare single files"
+        ));
+    }
+
+    let filter = Filter::new(&args.include, &args.exclude)?;
+    let orig_files = relative_files(&args.orig, args.recursive, &filter)?;
+    let diff_files = relative_files(&args.diff, args.recursive, &filter)?;
+    let paths: Vec<&PathBuf> = orig_files.union(&diff_files).collect();
+    let checkpoint = args
+        .checkpoint
+        .as_deref()
+        .map(|path| Checkpoint::open(path, args))
+        .transpose()?;
+    let outcome = |path: &PathBuf| {
+        let outcome = match (orig_files.contains(path), diff_files.contains(path)) {
+            (true, true) => resume_or_compare(args, checkpoint.as_ref(), path),
+            (true, false) => Outcome::OnlyOrig,
+            _ => Outcome::OnlyDiff,
+        };
+        let only_in = |dir: &Path| {
+            let (dir, path) = (dir.display().to_string(), path.display().to_string());
+            println!("{}", tr("only-in", &[("dir", &dir), ("path", &path)]));
+        };
+        match &outcome {
+            Outcome::OnlyOrig => only_in(&args.orig),
+            Outcome::OnlyDiff => only_in(&args.diff),
+            Outcome::Error(err) => {
+                eprintln!(
+                    "{}",
+                    format!("Error comparing {}: {err}", path.display()).red()
+                )
+            }
+            Outcome::Identical | Outcome::Differ => {}
+        }
+        (path.to_path_buf(), outcome)
+    };
+    let mut outcomes: Vec<_> = if args.jobs > 1 {
+        parallel_map(&paths, args.jobs, |path| outcome(path))
+    } else {
+        paths.into_iter().map(outcome).collect()
+    };
+    outcomes.sort_by(|a, b| a.0.cmp(&b.0));
+
+    print_summary(&outcomes);
+    if let Some(dir) = &args.html {
+        write_index(args, dir, &outcomes)?;
+        if args.open {
+            html::open(&dir.join("index.html"))?;
+        }
+    }
+    let failed = outcomes
+        .iter()
+        .filter(|(_, outcome)| *outcome != Outcome::Identical)
+        .count();
+    if failed > 0 {
+        return Err(Category::ThresholdExceeded.error(tr(
+            "pairs-not-identical",
+            &[
+                ("failed", &failed.to_string()),
+                ("total", &outcomes.len().to_string()),
+            ],
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::{FileWriteStr, PathChild};
+
+    #[test]
+    fn test_directory_compare() {
+        let orig = assert_fs::TempDir::new().unwrap();
+        let diff = assert_fs::TempDir::new().unwrap();
+        orig.child("same.csv").write_str("1,a\n2,b").unwrap();
+        diff.child("same.csv").write_str("1,a\n2,b").unwrap();
+        orig.child("sub/changed.csv").write_str("1,a").unwrap();
+        diff.child("sub/changed.csv").write_str("2,a").unwrap();
+        orig.child("gone.csv").write_str("1").unwrap();
+        diff.child("broken.csv").write_str("1").unwrap();
+        orig.child("broken.csv").write_str("\"1").unwrap();
+
+        let all = Filter::new(&[], &[]).unwrap();
+        let files = relative_files(orig.path(), false, &all).unwrap();
+        assert_eq!(
+            files.into_iter().collect::<Vec<_>>(),
+            [
+                PathBuf::from("broken.csv"),
+                PathBuf::from("gone.csv"),
+                PathBuf::from("same.csv")
+            ]
+        );
+        let files = relative_files(diff.path(), true, &all).unwrap();
+        assert!(files.contains(Path::new("sub/changed.csv")));
+
+        let filter = Filter::new(&["*.csv".to_string()], &["b*".to_string()]).unwrap();
+        let files = relative_files(orig.path(), true, &filter).unwrap();
+        assert_eq!(
+            files.into_iter().collect::<Vec<_>>(),
+            [
+                PathBuf::from("gone.csv"),
+                PathBuf::from("same.csv"),
+                PathBuf::from("sub/changed.csv")
+            ]
+        );
+
+        let mut args = crate::cli::tests::compare_args(&["-o", "1", "--brief"]);
+        args.orig = orig.path().to_path_buf();
+        args.diff = diff.path().to_path_buf();
+        assert_eq!(
+            compare_pair(&args, Path::new("same.csv")),
+            Outcome::Identical
+        );
+        assert_eq!(
+            compare_pair(&args, Path::new("sub/changed.csv")),
+            Outcome::Differ
+        );
+        assert!(run(&args).is_err());
+
+        // Errors keep their causes.
+        orig.child("short.csv").write_str("1\n").unwrap();
+        diff.child("short.csv").write_str("1\n").unwrap();
+        let mut args = crate::cli::tests::compare_args(&["-o", "2", "--brief", "--strict"]);
+        args.orig = orig.path().to_path_buf();
+        args.diff = diff.path().to_path_buf();
+        let Outcome::Error(err) = compare_pair(&args, Path::new("short.csv")) else {
+            panic!("expected an error");
+        };
+        assert!(err.starts_with("reading "), "{err}");
+        assert!(
+            err.ends_with("short.csv: Record at line 1 has no column 2"),
+            "{err}"
+        );
+
+        let mut squares = parallel_map(&[1, 2, 3, 4, 5], 3, |n| n * n);
+        squares.sort_unstable();
+        assert_eq!(squares, [1, 4, 9, 16, 25]);
+    }
+}
@@ -0,0 +1,102 @@
+use std::borrow::Cow;
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const ELLIPSIS: char = '…';
+
+/// Byte offset of the longest prefix of `value` that fits in `width` columns.
+fn fitting_prefix(value: &str, width: usize) -> usize {
+    let mut used = 0;
+    for (idx, c) in value.char_indices() {
+        used += c.width().unwrap_or(0);
+        if used > width {
+            return idx;
+        }
+    }
+    value.len()
+}
+
+/// Shortens `value` to at most `max_width` terminal columns, ending with an
+/// ellipsis when it had to be cut.
+pub fn truncate(value: &str, max_width: usize) -> Cow<'_, str> {
+    if value.width() <= max_width {
+        return Cow::Borrowed(value);
+    }
+    let end = fitting_prefix(value, max_width.saturating_sub(1));
+    Cow::Owned(format!("{}{ELLIPSIS}", &value[..end]))
+}
+
+/// Pads `value` with spaces to `width` terminal columns.
+pub fn pad(value: &str, width: usize) -> String {
+    let fill = width.saturating_sub(value.width());
+    format!("{value}{}", " ".repeat(fill))
+}
+
+/// Splits `value` into pieces of at most `max_width` terminal columns.
+pub fn wrap(value: &str, max_width: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut rest = value;
+    while rest.width() > max_width {
+        // Always take at least one character so wide characters cannot stall.
+        let end = match fitting_prefix(rest, max_width) {
+            0 => rest.chars().next().map_or(rest.len(), char::len_utf8),
+            end => end,
+        };
+        let (piece, tail) = rest.split_at(end);
+        pieces.push(piece);
+        rest = tail;
+    }
+    if !rest.is_empty() || pieces.is_empty() {
+        pieces.push(rest);
+    }
+    pieces
+}
+
+/// Lines `value` is displayed as: itself, truncated to `max_width`, or
+/// wrapped at `max_width` columns.
+pub fn fit(value: &str, max_width: Option<usize>, wrap_lines: bool) -> Vec<Cow<'_, str>> {
+    match max_width {
+        None => vec![Cow::Borrowed(value)],
+        Some(width) if wrap_lines => wrap(value, width).into_iter().map(Cow::Borrowed).collect(),
+        Some(width) => vec![truncate(value, width)],
+    }
+}
+
+/// `count` with commas between groups of thousands, e.g. `4,812`.
+pub fn group_thousands(count: usize) -> String {
+    let digits = count.to_string();
+    let mut res = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            res.push(',');
+        }
+        res.push(digit);
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_thousands() {
+        assert_eq!(group_thousands(0), "0");
+        assert_eq!(group_thousands(812), "812");
+        assert_eq!(group_thousands(4812), "4,812");
+        assert_eq!(group_thousands(1_234_567), "1,234,567");
+    }
+
+    #[test]
+    fn test_fit() {
+        assert_eq!(truncate("abcdef", 6), "abcdef");
+        assert_eq!(truncate("abcdef", 4), "abc…");
+        assert_eq!(truncate("日本語の名前", 7), "日本語…");
+        assert_eq!(wrap("abcdefg", 3), ["abc", "def", "g"]);
+        assert_eq!(wrap("日本語", 3), ["日", "本", "語"]);
+        assert_eq!(wrap("日本", 1), ["日", "本"]);
+        assert_eq!(fit("abc", None, true), ["abc"]);
+        assert_eq!(pad("日本", 6), "日本  ");
+        assert_eq!(pad("abc", 2), "abc");
+    }
+}
@@ -0,0 +1,138 @@
+use std::fmt;
+use std::io;
+
+use clap::ValueEnum;
+use inquire::InquireError;
+
+use crate::json::Value;
+
+/// How a failing run reports its error on stderr.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorFormat {
+    #[default]
+    Text,
+    /// One JSON object with a stable `code`, the message and its causes
+    Json,
+}
+
+/// Stable error categories for wrappers to branch on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    /// A file could not be read or written
+    Io,
+    /// An input, schema, rule or template is malformed
+    Parse,
+    /// A column index or name does not exist in the input
+    BadIndex,
+    /// The user declined or interrupted a prompt
+    UserAbort,
+    /// The inputs differ, or violate a schema, beyond what is accepted
+    ThresholdExceeded,
+    Other,
+}
+
+impl Category {
+    pub fn code(self) -> &'static str {
+        match self {
+            Category::Io => "io",
+            Category::Parse => "parse",
+            Category::BadIndex => "bad-index",
+            Category::UserAbort => "user-abort",
+            Category::ThresholdExceeded => "threshold-exceeded",
+            Category::Other => "other",
+        }
+    }
+
+    /// Wraps `message` into an error of this category.
+    pub fn error(self, message: impl Into<String>) -> anyhow::Error {
+        anyhow::Error::new(Categorized {
+            category: self,
+            message: message.into(),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Categorized {
+    category: Category,
+    message: String,
+}
+
+impl fmt::Display for Categorized {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Categorized {}
+
+/// Category of `err`: an explicit one from `Category::error`, else derived
+/// from the first I/O, CSV or prompt error in its chain.
+pub fn category(err: &anyhow::Error) -> Category {
+    for cause in err.chain() {
+        if let Some(categorized) = cause.downcast_ref::<Categorized>() {
+            return categorized.category;
+        }
+        if let Some(err) = cause.downcast_ref::<csv::Error>() {
+            return if err.is_io_error() {
+                Category::Io
+            } else {
+                Category::Parse
+            };
+        }
+        if cause.is::<io::Error>() {
+            return Category::Io;
+        }
+        if let Some(InquireError::OperationCanceled | InquireError::OperationInterrupted) =
+            cause.downcast_ref::<InquireError>()
+        {
+            return Category::UserAbort;
+        }
+    }
+    Category::Other
+}
+
+/// `err` as a JSON object: `{"code": ..., "message": ..., "causes": [...]}`.
+pub fn to_json(err: &anyhow::Error) -> Value {
+    Value::Object(vec![
+        (
+            "code".to_string(),
+            Value::String(category(err).code().to_string()),
+        ),
+        ("message".to_string(), Value::String(err.to_string())),
+        (
+            "causes".to_string(),
+            Value::Array(
+                err.chain()
+                    .skip(1)
+                    .map(|cause| Value::String(cause.to_string()))
+                    .collect(),
+            ),
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_category() {
+        let missing = std::fs::File::open("/nonexistent/orig.csv")
+            .context("opening orig.csv")
+            .unwrap_err();
+        assert_eq!(category(&missing), Category::Io);
+        let json = to_json(&missing);
+        assert_eq!(json.get("code").unwrap().as_str(), Some("io"));
+        assert_eq!(
+            json.get("message").unwrap().as_str(),
+            Some("opening orig.csv")
+        );
+        assert_eq!(json.get("causes").unwrap().as_array().unwrap().len(), 1);
+
+        let index = Category::BadIndex.error("Record at line 2 has no column 3");
+        assert_eq!(category(&index), Category::BadIndex);
+        assert_eq!(category(&anyhow::anyhow!("other")), Category::Other);
+    }
+}
@@ -0,0 +1,501 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use anyhow::{Result, anyhow};
+use csv::StringRecord;
+
+use crate::errors::Category;
+
+/// A column of a record, by 1-based index or header name.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnRef {
+    Index(usize),
+    Name(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Functions callable in expressions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Func {
+    /// `col(N)` or `col('name')`: the field of a column.
+    Col,
+    /// `concat(a, b, ...)`
+    Concat,
+    /// `substr(text, start[, len])`, with a 0-based start in characters.
+    Substr,
+    Lower,
+    Upper,
+    Trim,
+    Len,
+}
+
+impl Func {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "col" => Func::Col,
+            "concat" => Func::Concat,
+            "substr" => Func::Substr,
+            "lower" => Func::Lower,
+            "upper" => Func::Upper,
+            "trim" => Func::Trim,
+            "len" => Func::Len,
+            _ => return None,
+        })
+    }
+
+    /// Minimum and maximum number of arguments.
+    fn arity(self) -> (usize, usize) {
+        match self {
+            Func::Concat => (1, usize::MAX),
+            Func::Substr => (2, 3),
+            Func::Col | Func::Lower | Func::Upper | Func::Trim | Func::Len => (1, 1),
+        }
+    }
+}
+
+/// An expression over the fields of a record, as given to `--where` or
+/// `--key-expr`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Text(String),
+    Number(f64),
+    Column(ColumnRef),
+    Call(Func, Vec<Expr>),
+    Compare(CmpOp, Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+/// Result of evaluating an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    Number(f64),
+    Bool(bool),
+}
+
+impl Value {
+    fn number(&self) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            Value::Text(text) => text.trim().parse().ok(),
+            Value::Bool(_) => None,
+        }
+    }
+
+    pub fn truthy(&self) -> bool {
+        match self {
+            Value::Bool(value) => *value,
+            Value::Number(number) => *number != 0.0,
+            Value::Text(text) => !text.is_empty(),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Text(text) => write!(f, "{text}"),
+            Value::Number(number) => write!(f, "{number}"),
+            Value::Bool(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// Compares numerically when both values are numbers, as text otherwise.
+fn compare(a: &Value, b: &Value) -> Ordering {
+    match (a.number(), b.number()) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        _ => a.to_string().cmp(&b.to_string()),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Text(String),
+    Ident(String),
+    Column(ColumnRef),
+    Op(CmpOp),
+    LParen,
+    RParen,
+    Comma,
+}
+
+fn quoted(chars: &mut std::iter::Peekable<std::str::Chars>, quote: char) -> Result<String, String> {
+    let mut res = String::new();
+    loop {
+        match chars.next() {
+            // A doubled quote stands for the quote itself, as in SQL.
+            Some(c) if c == quote && chars.peek() == Some(&quote) => {
+                chars.next();
+                res.push(quote);
+            }
+            Some(c) if c == quote => return Ok(res),
+            Some(c) => res.push(c),
+            None => return Err(format!("unterminated {quote}")),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let token = match c {
+            '(' | ')' => {
+                chars.next();
+                if c == '(' {
+                    Token::LParen
+                } else {
+                    Token::RParen
+                }
+            }
+            ',' => {
+                chars.next();
+                Token::Comma
+            }
+            '\'' => {
+                chars.next();
+                Token::Text(quoted(&mut chars, '\'')?)
+            }
+            '"' | '`' => {
+                chars.next();
+                Token::Column(ColumnRef::Name(quoted(&mut chars, c)?))
+            }
+            '$' => {
+                chars.next();
+                let mut digits = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                    digits.push(c);
+                    chars.next();
+                }
+                match digits.parse() {
+                    Ok(index) if index > 0 => Token::Column(ColumnRef::Index(index)),
+                    _ => return Err("expected a 1-based column index after `$`".to_string()),
+                }
+            }
+            '=' | '!' | '<' | '>' => {
+                chars.next();
+                let next = chars.peek().copied();
+                let (op, two) = match (c, next) {
+                    ('=', Some('=')) => (CmpOp::Eq, true),
+                    ('=', _) => (CmpOp::Eq, false),
+                    ('!', Some('=')) => (CmpOp::Ne, true),
+                    ('<', Some('>')) => (CmpOp::Ne, true),
+                    ('<', Some('=')) => (CmpOp::Le, true),
+                    ('<', _) => (CmpOp::Lt, false),
+                    ('>', Some('=')) => (CmpOp::Ge, true),
+                    ('>', _) => (CmpOp::Gt, false),
+                    _ => return Err("unexpected `!`".to_string()),
+                };
+                if two {
+                    chars.next();
+                }
+                Token::Op(op)
+            }
+            c if c.is_ascii_digit() || c == '.' || c == '-' => {
+                let mut number = String::from(c);
+                chars.next();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+                    number.push(c);
+                    chars.next();
+                }
+                Token::Number(
+                    number
+                        .parse()
+                        .map_err(|_| format!("invalid number `{number}`"))?,
+                )
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut ident = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+                    ident.push(c);
+                    chars.next();
+                }
+                Token::Ident(ident)
+            }
+            c => return Err(format!("unexpected `{c}`")),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Ident(ident)) if ident.eq_ignore_ascii_case(keyword) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        while self.keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let left = self.primary()?;
+        if let Some(&Token::Op(op)) = self.peek() {
+            self.pos += 1;
+            return Ok(Expr::Compare(op, Box::new(left), Box::new(self.primary()?)));
+        }
+        Ok(left)
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Expr::Number(number)),
+            Some(Token::Text(text)) => Ok(Expr::Text(text)),
+            Some(Token::Column(column)) => Ok(Expr::Column(column)),
+            Some(Token::Ident(name)) if self.peek() == Some(&Token::LParen) => self.call(&name),
+            Some(Token::Ident(name)) => Ok(Expr::Column(ColumnRef::Name(name))),
+            Some(Token::LParen) => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => Err("expected `)`".to_string()),
+                }
+            }
+            Some(token) => Err(format!("unexpected {token:?}")),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+}
+
+impl Parser {
+    fn call(&mut self, name: &str) -> Result<Expr, String> {
+        let func = Func::from_name(name).ok_or_else(|| format!("unknown function `{name}`"))?;
+        self.pos += 1;
+        let mut args = Vec::new();
+        if self.peek() == Some(&Token::RParen) {
+            self.pos += 1;
+        } else {
+            loop {
+                args.push(self.or()?);
+                match self.next() {
+                    Some(Token::Comma) => {}
+                    Some(Token::RParen) => break,
+                    _ => return Err(format!("expected `,` or `)` in `{name}(...)`")),
+                }
+            }
+        }
+        let (min, max) = func.arity();
+        if args.len() < min || args.len() > max {
+            return Err(format!("wrong number of arguments to `{name}`"));
+        }
+        Ok(Expr::Call(func, args))
+    }
+}
+
+impl Expr {
+    /// Parses an expression such as `amount > 100 AND region = 'EU'`. Bare or
+    /// double-quoted names refer to header columns and `$N` to the N-th
+    /// column; `'text'` and numbers are literals. Functions: `col`,
+    /// `concat`, `substr`, `lower`, `upper`, `trim` and `len`.
+    pub fn parse(source: &str) -> Result<Expr, String> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            pos: 0,
+        };
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected {token:?}")),
+        }
+    }
+
+    pub fn eval(&self, record: &StringRecord, headers: Option<&StringRecord>) -> Result<Value> {
+        Ok(match self {
+            Expr::Text(text) => Value::Text(text.clone()),
+            Expr::Number(number) => Value::Number(*number),
+            Expr::Column(column) => Value::Text(column_value(column, record, headers)?.to_string()),
+            Expr::Compare(op, left, right) => {
+                let ord = compare(&left.eval(record, headers)?, &right.eval(record, headers)?);
+                Value::Bool(match op {
+                    CmpOp::Eq => ord == Ordering::Equal,
+                    CmpOp::Ne => ord != Ordering::Equal,
+                    CmpOp::Lt => ord == Ordering::Less,
+                    CmpOp::Le => ord != Ordering::Greater,
+                    CmpOp::Gt => ord == Ordering::Greater,
+                    CmpOp::Ge => ord != Ordering::Less,
+                })
+            }
+            Expr::Call(func, args) => call(*func, args, record, headers)?,
+            Expr::Not(expr) => Value::Bool(!expr.eval(record, headers)?.truthy()),
+            Expr::And(left, right) => Value::Bool(
+                left.eval(record, headers)?.truthy() && right.eval(record, headers)?.truthy(),
+            ),
+            Expr::Or(left, right) => Value::Bool(
+                left.eval(record, headers)?.truthy() || right.eval(record, headers)?.truthy(),
+            ),
+        })
+    }
+
+    /// Whether `record` passes the expression used as a filter.
+    pub fn matches(&self, record: &StringRecord, headers: Option<&StringRecord>) -> Result<bool> {
+        Ok(self.eval(record, headers)?.truthy())
+    }
+}
+
+fn call(
+    func: Func,
+    args: &[Expr],
+    record: &StringRecord,
+    headers: Option<&StringRecord>,
+) -> Result<Value> {
+    let values = args
+        .iter()
+        .map(|arg| arg.eval(record, headers))
+        .collect::<Result<Vec<_>>>()?;
+    let text = |idx: usize| values[idx].to_string();
+    let integer = |idx: usize| {
+        values[idx]
+            .number()
+            .filter(|number| *number >= 0.0)
+            .map(|number| number as usize)
+            .ok_or_else(|| anyhow!("expected a non-negative number, got `{}`", values[idx]))
+    };
+    Ok(match func {
+        Func::Col => {
+            let column = match &values[0] {
+                Value::Number(_) => ColumnRef::Index(integer(0)?.max(1)),
+                value => ColumnRef::Name(value.to_string()),
+            };
+            Value::Text(column_value(&column, record, headers)?.to_string())
+        }
+        Func::Concat => Value::Text(values.iter().map(Value::to_string).collect()),
+        Func::Substr => {
+            let len = if values.len() > 2 {
+                integer(2)?
+            } else {
+                usize::MAX
+            };
+            Value::Text(text(0).chars().skip(integer(1)?).take(len).collect())
+        }
+        Func::Lower => Value::Text(text(0).to_lowercase()),
+        Func::Upper => Value::Text(text(0).to_uppercase()),
+        Func::Trim => Value::Text(text(0).trim().to_string()),
+        Func::Len => Value::Number(text(0).chars().count() as f64),
+    })
+}
+
+fn column_value<'a>(
+    column: &ColumnRef,
+    record: &'a StringRecord,
+    headers: Option<&StringRecord>,
+) -> Result<&'a str> {
+    let index = match column {
+        ColumnRef::Index(index) => index - 1,
+        ColumnRef::Name(name) => headers
+            .and_then(|headers| headers.iter().position(|header| header == name))
+            .ok_or_else(|| {
+                Category::BadIndex.error(format!(
+                    "unknown column `{name}` (names need --with-headers)"
+                ))
+            })?,
+    };
+    Ok(record.get(index).unwrap_or(""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expr() {
+        let headers = StringRecord::from(vec!["amount", "region", "first name"]);
+        let record = StringRecord::from(vec!["150", "EU", "O'Brien"]);
+        let matches = |source: &str| {
+            Expr::parse(source)
+                .unwrap()
+                .matches(&record, Some(&headers))
+                .unwrap()
+        };
+        assert!(matches("amount > 100 AND region = 'EU'"));
+        assert!(!matches("amount > 100 AND NOT region = 'EU'"));
+        assert!(matches("amount < 20 or (region <> 'US' and $1 >= 150)"));
+        assert!(matches("amount > 99.5"));
+        assert!(matches("\"first name\" = 'O''Brien'"));
+        assert!(!matches("region > 'Z'"));
+
+        assert!(Expr::parse("amount >").is_err());
+        assert!(Expr::parse("(amount > 1").is_err());
+        assert!(Expr::parse("'open").is_err());
+        let unknown = Expr::parse("missing = 1").unwrap();
+        assert!(unknown.matches(&record, Some(&headers)).is_err());
+    }
+
+    #[test]
+    fn test_functions() {
+        let headers = StringRecord::from(vec!["order_id", "line", "code"]);
+        let record = StringRecord::from(vec!["A17", "3", " 2024-01-05T10:00 "]);
+        let eval = |source: &str| {
+            Expr::parse(source)
+                .unwrap()
+                .eval(&record, Some(&headers))
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(eval("concat(col('order_id'), '-', col('line'))"), "A17-3");
+        assert_eq!(eval("substr(trim(col(3)), 0, 10)"), "2024-01-05");
+        assert_eq!(eval("lower(order_id)"), "a17");
+        assert_eq!(eval("len(order_id)"), "3");
+        assert!(eval("len(order_id) = 3 and upper('a') = 'A'") == "true");
+
+        assert!(Expr::parse("nope(1)").is_err());
+        assert!(Expr::parse("substr('a')").is_err());
+        assert!(Expr::parse("concat('a',").is_err());
+    }
+}
@@ -0,0 +1,180 @@
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use anyhow::{Result, anyhow};
+
+use crate::api;
+use crate::errors;
+use crate::json::{self, Value};
+
+unsafe fn text<'a>(value: *const c_char, name: &str) -> Result<&'a str> {
+    if value.is_null() {
+        return Err(anyhow!("`{name}` is null"));
+    }
+    // SAFETY: non-null pointers are NUL-terminated strings, per the caller.
+    Ok(unsafe { CStr::from_ptr(value) }.to_str()?)
+}
+
+unsafe fn bytes<'a>(data: *const u8, len: usize, name: &str) -> Result<&'a [u8]> {
+    match (data.is_null(), len) {
+        (true, 0) => Ok(&[]),
+        (true, _) => Err(anyhow!("`{name}` is null")),
+        // SAFETY: `data` points to `len` readable bytes, per the caller.
+        (false, _) => Ok(unsafe { std::slice::from_raw_parts(data, len) }),
+    }
+}
+
+unsafe fn options(options: *const c_char) -> Result<Value> {
+    if options.is_null() {
+        return Ok(Value::Null);
+    }
+    json::parse(unsafe { text(options, "options") }?)
+}
+
+/// The JSON document of a comparison, or of its error or panic, as a string
+/// the caller releases with `csvcmp_free`.
+fn document(compare: impl FnOnce() -> Result<Value>) -> *mut c_char {
+    let res = panic::catch_unwind(AssertUnwindSafe(compare))
+        .unwrap_or_else(|_| Err(anyhow!("the comparison panicked")));
+    let document = match res {
+        Ok(result) => result,
+        Err(err) => errors::to_json(&err),
+    };
+    // JSON escapes control characters, so the document has no NUL bytes.
+    CString::new(document.to_string()).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Compares a column of the `orig` and `diff` CSV texts; `options` is a JSON
+/// object as taken by `api::compare`, or null. Returns a NUL-terminated JSON
+/// document, the comparison or an `{"code", "message", "causes"}` error, to
+/// be released with `csvcmp_free`.
+///
+/// # Safety
+///
+/// `orig` and `diff` must be NUL-terminated strings, and `options` one or
+/// null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn csvcmp_compare(
+    orig: *const c_char,
+    diff: *const c_char,
+    options: *const c_char,
+) -> *mut c_char {
+    document(|| unsafe {
+        api::compare(
+            text(orig, "orig")?,
+            text(diff, "diff")?,
+            &self::options(options)?,
+        )
+    })
+}
+
+/// Like `csvcmp_compare`, for CSV data given as byte buffers, which need not
+/// be NUL-terminated or UTF-8.
+///
+/// # Safety
+///
+/// `orig` and `diff` must point to `orig_len` and `diff_len` readable bytes,
+/// and `options` be a NUL-terminated string or null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn csvcmp_compare_bytes(
+    orig: *const u8,
+    orig_len: usize,
+    diff: *const u8,
+    diff_len: usize,
+    options: *const c_char,
+) -> *mut c_char {
+    document(|| unsafe {
+        api::compare_bytes(
+            bytes(orig, orig_len, "orig")?,
+            bytes(diff, diff_len, "diff")?,
+            &self::options(options)?,
+        )
+    })
+}
+
+/// Allocates `len` bytes for a host without its own allocator, such as a
+/// browser passing input buffers into WebAssembly memory.
+#[unsafe(no_mangle)]
+pub extern "C" fn csvcmp_alloc(len: usize) -> *mut u8 {
+    let mut buffer = Vec::<u8>::with_capacity(len);
+    let data = buffer.as_mut_ptr();
+    std::mem::forget(buffer);
+    data
+}
+
+/// Releases a buffer from `csvcmp_alloc`.
+///
+/// # Safety
+///
+/// `data` must come from `csvcmp_alloc(len)` and not be released yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn csvcmp_dealloc(data: *mut u8, len: usize) {
+    // SAFETY: `data` was allocated as a `Vec` of capacity `len`.
+    drop(unsafe { Vec::from_raw_parts(data, 0, len) });
+}
+
+/// Releases a document returned by `csvcmp_compare`.
+///
+/// # Safety
+///
+/// `document` must come from `csvcmp_compare` and not be released yet, or
+/// be null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn csvcmp_free(document: *mut c_char) {
+    if !document.is_null() {
+        // SAFETY: the document was created by `CString::into_raw`.
+        drop(unsafe { CString::from_raw(document) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(orig: &str, diff: &str, options: Option<&str>) -> Value {
+        let (orig, diff) = (CString::new(orig).unwrap(), CString::new(diff).unwrap());
+        let options = options.map(|options| CString::new(options).unwrap());
+        unsafe {
+            let document = csvcmp_compare(
+                orig.as_ptr(),
+                diff.as_ptr(),
+                options
+                    .as_ref()
+                    .map_or(ptr::null(), |options| options.as_ptr()),
+            );
+            let value = json::parse(CStr::from_ptr(document).to_str().unwrap()).unwrap();
+            csvcmp_free(document);
+            value
+        }
+    }
+
+    #[test]
+    fn test_csvcmp_compare() {
+        let result = call("a\nb\n", "a\nc\n", Some(r#"{"orig_index": 1}"#));
+        assert_eq!(result.get("identical"), Some(&Value::Bool(false)));
+        let result = call("a\n", "a\n", None);
+        assert_eq!(result.get("code").unwrap().as_str(), Some("parse"));
+
+        let (orig, diff) = (b"a\n\xff\n", b"a\n");
+        let options = CString::new(r#"{"orig_index": 1, "lossy_utf8": true}"#).unwrap();
+        unsafe {
+            let buffer = csvcmp_alloc(orig.len());
+            ptr::copy_nonoverlapping(orig.as_ptr(), buffer, orig.len());
+            let document = csvcmp_compare_bytes(
+                buffer,
+                orig.len(),
+                diff.as_ptr(),
+                diff.len(),
+                options.as_ptr(),
+            );
+            let result = json::parse(CStr::from_ptr(document).to_str().unwrap()).unwrap();
+            assert_eq!(
+                result.get("counts").unwrap().get("removed"),
+                Some(&Value::Number(1.0))
+            );
+            csvcmp_free(document);
+            csvcmp_dealloc(buffer, orig.len());
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::json::Value;
+use crate::sha256::{self, Sha256};
+
+/// Size and SHA-256 digest of an input file, so reports can be tied to the
+/// exact files they were produced from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fingerprint {
+    pub size: u64,
+    pub sha256: String,
+}
+
+impl Fingerprint {
+    pub fn of(path: &Path) -> Result<Self> {
+        Ok(Self {
+            size: fs::metadata(path)?.len(),
+            sha256: sha256::to_hex(&sha256::hash_file(path)?),
+        })
+    }
+
+    pub fn of_bytes(data: &[u8]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(data);
+        Self {
+            size: data.len() as u64,
+            sha256: sha256::to_hex(&hasher.finalize()),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("size".to_string(), Value::Number(self.size as f64)),
+            ("sha256".to_string(), Value::String(self.sha256.clone())),
+        ])
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} bytes, sha256 {}", self.size, self.sha256)
+    }
+}
+
+/// Fingerprints of both inputs.
+pub fn inputs(orig: &Path, diff: &Path) -> Result<(Fingerprint, Fingerprint)> {
+    Ok((Fingerprint::of(orig)?, Fingerprint::of(diff)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::FileWriteStr;
+
+    #[test]
+    fn test_fingerprint() {
+        let file = assert_fs::NamedTempFile::new("orig.csv").unwrap();
+        file.write_str("abc").unwrap();
+        let fingerprint = Fingerprint::of(file.path()).unwrap();
+        assert_eq!(fingerprint, Fingerprint::of_bytes(b"abc"));
+        assert_eq!(
+            fingerprint.to_string(),
+            "3 bytes, sha256 ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+
+use anyhow::{Result, anyhow};
+use tempfile::TempDir;
+
+use crate::cli::{CompareArgs, Inputs, ReaderArgs};
+use crate::compare::file_name;
+use crate::errors::Category;
+use crate::parse::csv_delimiter;
+use crate::tokenize::{Split, Tokenized};
+
+/// Field widths of `--fixed-width` records, from `--widths` or the
+/// `--widths-file` listing them separated by commas or newlines, with `#`
+/// starting a comment.
+pub fn widths(args: &ReaderArgs) -> Result<Vec<usize>> {
+    let Some(path) = &args.widths_file else {
+        return Ok(args.widths.clone());
+    };
+    let spec = fs::read_to_string(path)?;
+    spec.lines()
+        .map(|line| line.split_once('#').map_or(line, |(spec, _)| spec))
+        .flat_map(|line| line.split(','))
+        .map(str::trim)
+        .filter(|width| !width.is_empty())
+        .map(|width| match width.parse::<usize>() {
+            Ok(width) if width > 0 => Ok(width),
+            _ => Err(Category::Parse.error(format!(
+                "{}: `{width}` is not a field width",
+                path.display()
+            ))),
+        })
+        .collect()
+}
+
+/// Converts the one input `--fixed-width orig` or `diff` names to a temporary
+/// CSV file, line for line, so both inputs read as CSV. The returned
+/// directory must be kept alive while the file is read.
+pub fn convert_input(args: &CompareArgs) -> Result<Option<(CompareArgs, TempDir)>> {
+    let (path, name) = match args.parse.reader.fixed_width {
+        Some(Inputs::Orig) => (&args.orig, "orig.csv"),
+        Some(Inputs::Diff) => (&args.diff, "diff.csv"),
+        _ => return Ok(None),
+    };
+    if path.is_dir() {
+        return Err(anyhow!(
+            "--fixed-width {} needs a file, not a directory",
+            name.trim_end_matches(".csv")
+        ));
+    }
+    let dir = tempfile::tempdir()?;
+    let target = dir.path().join(name);
+    let delimiter = csv_delimiter(&args.parse.reader).ok_or_else(|| {
+        anyhow!("--fixed-width orig or diff needs a single-byte --delimiter for the other input")
+    })?;
+    let mut input = Tokenized::new(
+        BufReader::new(File::open(path)?),
+        Split::Widths(widths(&args.parse.reader)?),
+        delimiter,
+    );
+    let mut output = BufWriter::new(File::create(&target)?);
+    io::copy(&mut input, &mut output)?;
+    output.flush()?;
+
+    let mut resolved = args.clone();
+    let defaults = [
+        format!("a/{}", file_name(&args.orig)),
+        format!("b/{}", file_name(&args.diff)),
+    ];
+    let given = resolved.label.len();
+    resolved.label.extend(defaults.into_iter().skip(given));
+    if name == "orig.csv" {
+        resolved.orig = target;
+    } else {
+        resolved.diff = target;
+    }
+    resolved.parse.reader.fixed_width = None;
+    Ok(Some((resolved, dir)))
+}
@@ -0,0 +1,356 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Result;
+use csv::StringRecord;
+use similar::ChangeTag;
+
+use crate::chunked::diff_ops;
+use crate::cli::{CompareArgs, DiffFormat, OutputFormat};
+use crate::counts::{Counts, diff_counts};
+use crate::diff3::slices;
+use crate::json::Value;
+use crate::parse::source_records;
+use crate::redact::Masking;
+use crate::sha256::{Sha256, to_hex};
+use crate::stats::ranked_values;
+
+/// Number of most frequent changed values listed in summaries.
+const TOP_CHANGED: usize = 5;
+
+/// Removed and added values of the diff of both columns.
+fn changed_values<'a>(
+    orig: &[&'a str],
+    diff: &[&'a str],
+    chunk_rows: usize,
+) -> (Vec<&'a str>, Vec<&'a str>) {
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    for op in diff_ops(orig, diff, chunk_rows) {
+        for change in op.iter_changes(orig, diff) {
+            match change.tag() {
+                ChangeTag::Delete => removed.push(change.value()),
+                ChangeTag::Insert => added.push(change.value()),
+                ChangeTag::Equal => {}
+            }
+        }
+    }
+    (removed, added)
+}
+
+fn text(value: impl Into<String>) -> Value {
+    Value::String(value.into())
+}
+
+fn object(entries: Vec<(&str, Value)>) -> Value {
+    Value::Object(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+    )
+}
+
+/// Escapes the characters Slack's mrkdwn reserves for links and mentions.
+fn mrkdwn_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Slack Block Kit payload: a header, the counts, the most frequent removed
+/// and added values and a link to the full report.
+fn slack(
+    labels: (&str, &str),
+    counts: &Counts,
+    top: [(&str, Vec<(String, usize)>); 2],
+    report_url: Option<&str>,
+) -> Value {
+    let mrkdwn = |value: String| object(vec![("type", text("mrkdwn")), ("text", text(value))]);
+    let identical = *counts == Counts::default();
+    let verdict = if identical { "identical" } else { "differ" };
+    let mut blocks = vec![
+        object(vec![
+            ("type", text("header")),
+            (
+                "text",
+                object(vec![
+                    ("type", text("plain_text")),
+                    (
+                        "text",
+                        text(format!("{} → {}: {verdict}", labels.0, labels.1)),
+                    ),
+                ]),
+            ),
+        ]),
+        object(vec![
+            ("type", text("section")),
+            (
+                "fields",
+                Value::Array(vec![
+                    mrkdwn(format!("*Removed*\n{}", counts.removed)),
+                    mrkdwn(format!("*Added*\n{}", counts.added)),
+                    mrkdwn(format!("*Changed*\n{}", counts.changed)),
+                ]),
+            ),
+        ]),
+    ];
+    for (kind, values) in top.iter().filter(|(_, values)| !values.is_empty()) {
+        let lines: Vec<String> = values
+            .iter()
+            .map(|(value, count)| format!("• `{}` ×{count}", mrkdwn_escape(value)))
+            .collect();
+        blocks.push(object(vec![
+            ("type", text("section")),
+            (
+                "text",
+                mrkdwn(format!("*Top {kind} values*\n{}", lines.join("\n"))),
+            ),
+        ]));
+    }
+    if let Some(url) = report_url {
+        blocks.push(object(vec![
+            ("type", text("context")),
+            (
+                "elements",
+                Value::Array(vec![mrkdwn(format!("<{url}|Full report>"))]),
+            ),
+        ]));
+    }
+    object(vec![
+        (
+            "text",
+            text(format!("{} → {}: {verdict}", labels.0, labels.1)),
+        ),
+        ("blocks", Value::Array(blocks)),
+    ])
+}
+
+/// Escapes a Prometheus label value.
+fn label_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Share of rows left unchanged by the diff, as `2 * equal / total` like
+/// difflib's ratio; empty columns are fully similar.
+fn similarity_ratio(counts: &Counts, (orig, diff): (usize, usize)) -> f64 {
+    if orig + diff == 0 {
+        return 1.0;
+    }
+    let equal = orig - counts.removed - counts.changed;
+    2.0 * equal as f64 / (orig + diff) as f64
+}
+
+/// Prometheus gauges of the comparison, labelled with both inputs.
+fn prometheus(labels: (&str, &str), counts: &Counts, rows: (usize, usize)) -> String {
+    let labels = format!(
+        "orig=\"{}\",diff=\"{}\"",
+        label_escape(labels.0),
+        label_escape(labels.1)
+    );
+    let identical = usize::from(*counts == Counts::default());
+    let gauges: [(&str, &str, String); 5] = [
+        (
+            "rows_added",
+            "Rows only in the diff column",
+            counts.added.to_string(),
+        ),
+        (
+            "rows_removed",
+            "Rows only in the orig column",
+            counts.removed.to_string(),
+        ),
+        (
+            "rows_changed",
+            "Rows with a changed value",
+            counts.changed.to_string(),
+        ),
+        (
+            "similarity_ratio",
+            "Share of rows left unchanged, from 0 to 1",
+            format!("{:.6}", similarity_ratio(counts, rows)),
+        ),
+        (
+            "identical",
+            "1 if the columns are identical",
+            identical.to_string(),
+        ),
+    ];
+    let mut output = String::new();
+    for (name, help, value) in gauges {
+        output += &format!(
+            "# HELP csv_compare_{name} {help}\n# TYPE csv_compare_{name} gauge\ncsv_compare_{name}{{{labels}}} {value}\n"
+        );
+    }
+    output
+}
+
+/// Line of the `index`th value in its source file: the line of its record,
+/// or for files of plain lines without records, the value's own.
+fn source_line(records: &[StringRecord], index: Option<usize>) -> u64 {
+    let Some(index) = index else {
+        return 1;
+    };
+    records
+        .get(index)
+        .and_then(|record| record.position())
+        .map_or(index as u64 + 1, |pos| pos.line())
+}
+
+/// GitLab Code Quality findings, one per removed or added row, located at
+/// the row in its file. Fingerprints hash the value and its occurrence so
+/// findings stay stable across runs when unrelated rows move.
+fn gitlab_codequality(
+    (orig_path, diff_path): (&Path, &Path),
+    (orig, diff): (&[&str], &[&str]),
+    (orig_records, diff_records): (&[StringRecord], &[StringRecord]),
+    masking: &Masking,
+    chunk_rows: usize,
+) -> Value {
+    let mut occurrences: HashMap<(ChangeTag, &str), usize> = HashMap::new();
+    let mut findings = Vec::new();
+    for op in diff_ops(orig, diff, chunk_rows) {
+        for change in op.iter_changes(orig, diff) {
+            let (kind, path, line) = match change.tag() {
+                ChangeTag::Delete => (
+                    "removed",
+                    orig_path,
+                    source_line(orig_records, change.old_index()),
+                ),
+                ChangeTag::Insert => (
+                    "added",
+                    diff_path,
+                    source_line(diff_records, change.new_index()),
+                ),
+                ChangeTag::Equal => continue,
+            };
+            let occurrence = occurrences
+                .entry((change.tag(), change.value()))
+                .or_default();
+            *occurrence += 1;
+            let path = path.display().to_string();
+            let mut hasher = Sha256::new();
+            for part in [kind, &path, change.value(), &occurrence.to_string()] {
+                hasher.update(part.as_bytes());
+                hasher.update(b"\0");
+            }
+            findings.push(object(vec![
+                (
+                    "description",
+                    text(format!("Row {kind}: {}", masking.apply(change.value()))),
+                ),
+                ("check_name", text(format!("csv-compare/{kind}"))),
+                ("fingerprint", text(to_hex(&hasher.finalize()))),
+                ("severity", text("minor")),
+                (
+                    "location",
+                    object(vec![
+                        ("path", text(path)),
+                        ("lines", object(vec![("begin", Value::Number(line as f64))])),
+                    ]),
+                ),
+            ]));
+        }
+    }
+    Value::Array(findings)
+}
+
+/// The comparison in `--format`, for formats other than the diff.
+pub fn render(
+    args: &CompareArgs,
+    orig_index: usize,
+    labels: (&str, &str),
+    (orig_lines, diff_lines): (&[String], &[String]),
+) -> Result<String> {
+    let (orig, diff) = (slices(orig_lines), slices(diff_lines));
+    let counts = diff_counts(orig_lines, diff_lines, args.chunk_rows);
+    let masking = Masking::new(args);
+    let top = |values: &[&str]| -> Vec<(String, usize)> {
+        ranked_values(values.iter().copied())
+            .into_iter()
+            .take(TOP_CHANGED)
+            .map(|(value, count)| (masking.apply(value).into_owned(), count))
+            .collect()
+    };
+    Ok(match args.format {
+        OutputFormat::Diff => unreachable!("diffs are printed by compare"),
+        OutputFormat::Slack => {
+            let (removed, added) = changed_values(&orig, &diff, args.chunk_rows);
+            let top = [("removed", top(&removed)), ("added", top(&added))];
+            slack(labels, &counts, top, args.report_url.as_deref()).to_string() + "\n"
+        }
+        OutputFormat::Prometheus => {
+            prometheus(labels, &counts, (orig_lines.len(), diff_lines.len()))
+        }
+        OutputFormat::GitlabCodequality => {
+            let orig_records = source_records(&args.orig, orig_index, &args.parse)?;
+            let diff_index = args.diff_index.unwrap_or(orig_index);
+            let diff_records = match args.diff_format {
+                DiffFormat::Csv => source_records(&args.diff, diff_index, &args.parse)?,
+                DiffFormat::Lines => Vec::new(),
+            };
+            let findings = gitlab_codequality(
+                (&args.orig, &args.diff),
+                (&orig, &diff),
+                (&orig_records, &diff_records),
+                &masking,
+                args.chunk_rows,
+            );
+            findings.to_string() + "\n"
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_slack() {
+        let counts = Counts {
+            removed: 2,
+            added: 0,
+            changed: 0,
+        };
+        let top = [("removed", vec![("<b>".to_string(), 2)]), ("added", vec![])];
+        let payload = slack(
+            ("a/x.csv", "b/x.csv"),
+            &counts,
+            top,
+            Some("https://ci/report"),
+        );
+        let payload = json::parse(&payload.to_string()).unwrap();
+        let blocks = payload.get("blocks").unwrap().as_array().unwrap();
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(
+            blocks[2].get("text").unwrap().get("text").unwrap().as_str(),
+            Some("*Top removed values*\n• `&lt;b&gt;` ×2")
+        );
+        assert_eq!(
+            payload.get("text").unwrap().as_str(),
+            Some("a/x.csv → b/x.csv: differ")
+        );
+    }
+
+    #[test]
+    fn test_prometheus() {
+        let counts = Counts {
+            removed: 1,
+            added: 0,
+            changed: 1,
+        };
+        let output = prometheus(("a\"b.csv", "c.csv"), &counts, (4, 3));
+        assert!(output.contains("# TYPE csv_compare_rows_removed gauge\n"));
+        assert!(
+            output.contains("csv_compare_rows_changed{orig=\"a\\\"b.csv\",diff=\"c.csv\"} 1\n")
+        );
+        assert!(output.contains(
+            "csv_compare_similarity_ratio{orig=\"a\\\"b.csv\",diff=\"c.csv\"} 0.571429\n"
+        ));
+        assert!(output.contains("csv_compare_identical{orig=\"a\\\"b.csv\",diff=\"c.csv\"} 0\n"));
+    }
+}
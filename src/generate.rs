@@ -0,0 +1,144 @@
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+use csv::Writer;
+use fastrand::Rng;
+
+use crate::cli::GenerateArgs;
+
+type Row = Vec<String>;
+
+fn random_value(rng: &mut Rng) -> String {
+    if rng.bool() {
+        rng.u32(..100_000).to_string()
+    } else {
+        (0..rng.usize(3..10)).map(|_| rng.alphanumeric()).collect()
+    }
+}
+
+fn random_row(rng: &mut Rng, id: usize, columns: usize) -> Row {
+    let mut row = vec![format!("id{id}")];
+    row.extend((1..columns).map(|_| random_value(rng)));
+    row
+}
+
+/// Generates orig rows and a diff derived from them by applying the requested
+/// number of removes, edits, adds and reorders, in that order.
+fn generate(args: &GenerateArgs, rng: &mut Rng) -> Result<(Vec<Row>, Vec<Row>)> {
+    if args.columns == 0 {
+        return Err(anyhow!("At least one column is required"));
+    }
+    if args.removes > args.rows {
+        return Err(anyhow!("Cannot remove more rows than generated"));
+    }
+
+    let orig: Vec<Row> = (0..args.rows)
+        .map(|id| random_row(rng, id, args.columns))
+        .collect();
+    let mut diff = orig.clone();
+
+    for _ in 0..args.removes {
+        diff.remove(rng.usize(..diff.len()));
+    }
+    if !diff.is_empty() {
+        for _ in 0..args.edits {
+            let row = rng.usize(..diff.len());
+            let column = if args.columns > 1 {
+                rng.usize(1..args.columns)
+            } else {
+                0
+            };
+            diff[row][column] = random_value(rng);
+        }
+    }
+    for id in args.rows..args.rows + args.adds {
+        let row = random_row(rng, id, args.columns);
+        diff.insert(rng.usize(..=diff.len()), row);
+    }
+    if !diff.is_empty() {
+        for _ in 0..args.reorders {
+            let row = diff.remove(rng.usize(..diff.len()));
+            diff.insert(rng.usize(..=diff.len()), row);
+        }
+    }
+    Ok((orig, diff))
+}
+
+fn write_rows(path: &Path, rows: &[Row], columns: usize, with_headers: bool) -> Result<()> {
+    let mut writer = Writer::from_path(path)?;
+    if with_headers {
+        let mut headers = vec!["id".to_string()];
+        headers.extend((2..=columns).map(|column| format!("column{column}")));
+        writer.write_record(&headers)?;
+    }
+    for row in rows {
+        writer.write_record(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn run(args: &GenerateArgs) -> Result<()> {
+    let seed = args.seed.unwrap_or_else(|| fastrand::u64(..));
+    let mut rng = Rng::with_seed(seed);
+    let (orig, diff) = generate(args, &mut rng)?;
+
+    write_rows(&args.orig, &orig, args.columns, args.with_headers)?;
+    write_rows(&args.diff, &diff, args.columns, args.with_headers)?;
+    println!(
+        "wrote {} ({} rows) and {} ({} rows), seed {seed}",
+        args.orig.display(),
+        orig.len(),
+        args.diff.display(),
+        diff.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    use crate::cli::{Cli, Command};
+
+    fn generate_args(args: &[&str]) -> GenerateArgs {
+        let mut argv = vec!["csv-compare", "generate", "orig.csv", "diff.csv"];
+        argv.extend(args);
+        match Cli::parse_from(argv).command {
+            Command::Generate(args) => args,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_generate() {
+        let args = generate_args(&[
+            "--rows",
+            "50",
+            "--adds",
+            "3",
+            "--removes",
+            "5",
+            "--edits",
+            "2",
+        ]);
+        let (orig, diff) = generate(&args, &mut Rng::with_seed(7)).unwrap();
+        assert_eq!(orig.len(), 50);
+        assert_eq!(diff.len(), 48);
+        assert!(orig.iter().all(|row| row.len() == 5));
+        assert_eq!(
+            diff.iter()
+                .filter(|row| row[0].trim_start_matches("id").parse::<usize>().unwrap() >= 50)
+                .count(),
+            3
+        );
+
+        let (same_orig, same_diff) = generate(&args, &mut Rng::with_seed(7)).unwrap();
+        assert_eq!(orig, same_orig);
+        assert_eq!(diff, same_diff);
+
+        let args = generate_args(&["--rows", "2", "--removes", "3"]);
+        assert!(generate(&args, &mut Rng::with_seed(7)).is_err());
+    }
+}
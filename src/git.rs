@@ -0,0 +1,164 @@
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Result, anyhow};
+use tempfile::{NamedTempFile, TempPath};
+
+use crate::cli::CompareArgs;
+use crate::parse::{forget_display_name, set_display_name};
+
+/// Splits `file.csv@REV` into the path and the revision, unless a file with
+/// that exact name exists.
+fn split_revision(path: &Path) -> Option<(PathBuf, String)> {
+    if path.exists() {
+        return None;
+    }
+    let (file, rev) = path.to_str()?.rsplit_once('@')?;
+    if file.is_empty() || rev.is_empty() {
+        return None;
+    }
+    Some((PathBuf::from(file), rev.to_string()))
+}
+
+/// Contents of `path` at the git revision `rev`, written to a temporary file.
+fn read_blob(path: &Path, rev: &str) -> Result<TempPath> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("{} is not a file", path.display()))?;
+    let mut spec = OsString::from(format!("{rev}:./"));
+    spec.push(name);
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("show")
+        .arg(spec)
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git show {rev}:{} failed: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let mut file = NamedTempFile::new()?;
+    file.write_all(&output.stdout)?;
+    Ok(file.into_temp_path())
+}
+
+/// The committed contents of an input in a temporary file, which messages
+/// show as `file.csv@REV` while it lives.
+pub struct Blob(TempPath);
+
+impl Blob {
+    fn new(path: TempPath, name: String) -> Blob {
+        set_display_name(&path, name);
+        Blob(path)
+    }
+}
+
+impl Drop for Blob {
+    fn drop(&mut self) {
+        forget_display_name(&self.0);
+    }
+}
+
+/// Replaces sides given as `file.csv@REV` (or the orig side with
+/// `--orig-rev`) by temporary files holding the committed contents. The
+/// returned blobs must be kept alive while the files are read.
+pub fn resolve_revisions(args: &CompareArgs) -> Result<Option<(CompareArgs, Vec<Blob>)>> {
+    let orig = match &args.orig_rev {
+        Some(rev) => Some((args.orig.clone(), rev.clone())),
+        None => split_revision(&args.orig),
+    };
+    let diff = split_revision(&args.diff);
+    if orig.is_none() && diff.is_none() {
+        return Ok(None);
+    }
+
+    let mut resolved = args.clone();
+    resolved.orig_rev = None;
+    let mut blobs = Vec::new();
+    let mut labels = Vec::new();
+    for (side, prefix, revision, target) in [
+        (&args.orig, "a", orig, &mut resolved.orig),
+        (&args.diff, "b", diff, &mut resolved.diff),
+    ] {
+        let name = |path: &Path| {
+            path.file_name()
+                .map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+        };
+        match revision {
+            Some((path, rev)) => {
+                let blob = read_blob(&path, &rev)?;
+                *target = blob.to_path_buf();
+                blobs.push(Blob::new(blob, format!("{}@{rev}", path.display())));
+                labels.push(format!("{prefix}/{}@{rev}", name(&path)));
+            }
+            None => labels.push(format!("{prefix}/{}", name(side))),
+        }
+    }
+    let given = resolved.label.len();
+    resolved.label.extend(labels.into_iter().skip(given));
+    Ok(Some((resolved, blobs)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::display_name;
+    use assert_fs::prelude::{FileWriteStr, PathChild};
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    #[test]
+    fn test_read_blob() {
+        let repo = assert_fs::TempDir::new().unwrap();
+        let file = repo.child("data.csv");
+        file.write_str("1,a\n").unwrap();
+        git(repo.path(), &["init", "-q"]);
+        git(repo.path(), &["add", "data.csv"]);
+        git(repo.path(), &["commit", "-q", "-m", "data"]);
+        file.write_str("1,b\n").unwrap();
+
+        let blob = read_blob(file.path(), "HEAD").unwrap();
+        assert_eq!(std::fs::read_to_string(&blob).unwrap(), "1,a\n");
+        assert!(read_blob(file.path(), "HEAD~5").is_err());
+
+        let spec = PathBuf::from(format!("{}@HEAD", file.path().display()));
+        assert_eq!(
+            split_revision(&spec),
+            Some((file.path().to_path_buf(), "HEAD".to_string()))
+        );
+        assert_eq!(split_revision(file.path()), None);
+
+        let mut args = crate::cli::tests::compare_args(&["-o", "1"]);
+        args.orig = spec;
+        args.diff = file.path().to_path_buf();
+        let (resolved, blobs) = resolve_revisions(&args).unwrap().unwrap();
+        let name = format!("{}@HEAD", file.path().display());
+        assert_eq!(display_name(&resolved.orig), name);
+        assert_eq!(
+            display_name(&resolved.diff),
+            file.path().display().to_string()
+        );
+        drop(blobs);
+        assert_ne!(display_name(&resolved.orig), name);
+    }
+}
@@ -0,0 +1,215 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{Result, anyhow};
+use similar::ChangeTag;
+use similar::udiff::UnifiedHunkHeader;
+
+use crate::chunked::grouped_ops;
+use crate::provenance::Provenance;
+use crate::redact::Masking;
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; font-family: monospace; }
+td { padding: 0 0.5em; white-space: pre; }
+.hunk { color: #0086b3; background: #f0f8ff; }
+.del { background: #ffecec; }
+.ins { background: #eaffea; }
+.pass { color: #1a7f37; }
+.fail { color: #cf222e; }
+.provenance pre { white-space: pre-wrap; word-break: break-all; }";
+
+fn escape(value: &str) -> String {
+    let mut res = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&#39;"),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+fn create(path: &Path) -> Result<BufWriter<File>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    Ok(BufWriter::new(File::create(path)?))
+}
+
+fn write_head(writer: &mut impl Write, title: &str) -> Result<()> {
+    writeln!(writer, "<!DOCTYPE html>")?;
+    writeln!(writer, "<html><head><meta charset=\"utf-8\">")?;
+    writeln!(writer, "<title>{}</title>", escape(title))?;
+    writeln!(writer, "<style>{STYLE}</style></head><body>")?;
+    writeln!(writer, "<h1>{}</h1>", escape(title))?;
+    Ok(())
+}
+
+/// Opens `path` in the default browser, without waiting for it.
+pub fn open(path: &Path) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+    command
+        .arg(path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| anyhow!("Failed to open {}: {err}", path.display()))?;
+    Ok(())
+}
+
+/// Writes the diff of both columns as a standalone HTML page.
+pub fn write_report(
+    path: &Path,
+    labels: (&str, &str),
+    (orig_lines, diff_lines): (&[String], &[String]),
+    masking: &Masking,
+    chunk_rows: usize,
+    provenance: &Provenance,
+) -> Result<()> {
+    let orig: Vec<&str> = orig_lines.iter().map(String::as_str).collect();
+    let diff: Vec<&str> = diff_lines.iter().map(String::as_str).collect();
+
+    let mut writer = create(path)?;
+    write_head(&mut writer, &format!("{} → {}", labels.0, labels.1))?;
+    writeln!(writer, "<ul class=\"inputs\">")?;
+    let (orig_input, diff_input) = &provenance.inputs;
+    for (label, fingerprint) in [(labels.0, orig_input), (labels.1, diff_input)] {
+        writeln!(writer, "<li>{}: {fingerprint}</li>", escape(label))?;
+    }
+    writeln!(writer, "</ul>")?;
+    writeln!(
+        writer,
+        "<details class=\"provenance\"><summary>Provenance</summary><pre>{}</pre></details>",
+        escape(&provenance.to_json().to_string())
+    )?;
+    let hunks = grouped_ops(&orig, &diff, chunk_rows, 3);
+    if hunks.is_empty() {
+        writeln!(writer, "<p class=\"pass\">No differences.</p>")?;
+    }
+    writeln!(writer, "<table>")?;
+    for ops in &hunks {
+        writeln!(
+            writer,
+            "<tr class=\"hunk\"><td colspan=\"2\">{}</td></tr>",
+            UnifiedHunkHeader::new(ops)
+        )?;
+        for op in ops {
+            for change in op.iter_changes(&orig[..], &diff[..]) {
+                let (class, tag) = match change.tag() {
+                    ChangeTag::Delete => ("del", "-"),
+                    ChangeTag::Insert => ("ins", "+"),
+                    ChangeTag::Equal => ("ctx", " "),
+                };
+                writeln!(
+                    writer,
+                    "<tr class=\"{class}\"><td>{tag}</td><td>{}</td></tr>",
+                    escape(&masking.apply(change.value()))
+                )?;
+            }
+        }
+    }
+    writeln!(writer, "</table></body></html>")?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// A compared file pair of a directory run, as listed in the index.
+pub struct IndexEntry<'a> {
+    pub path: &'a Path,
+    pub status: &'a str,
+    pub passed: bool,
+    /// Report of the pair, relative to the index.
+    pub report: Option<&'a Path>,
+}
+
+/// Writes `index.html` into `dir`, linking the per-pair reports.
+pub fn write_index(dir: &Path, title: &str, entries: &[IndexEntry]) -> Result<()> {
+    let mut writer = create(&dir.join("index.html"))?;
+    write_head(&mut writer, title)?;
+    let passed = entries.iter().filter(|entry| entry.passed).count();
+    writeln!(
+        writer,
+        "<p>{passed} of {} file pairs passed.</p>",
+        entries.len()
+    )?;
+    writeln!(writer, "<table>")?;
+    for entry in entries {
+        let path = escape(&entry.path.display().to_string());
+        let name = match entry.report {
+            Some(report) => format!(
+                "<a href=\"{}\">{path}</a>",
+                escape(&report.display().to_string())
+            ),
+            None => path,
+        };
+        let class = if entry.passed { "pass" } else { "fail" };
+        writeln!(
+            writer,
+            "<tr><td>{name}</td><td class=\"{class}\">{}</td></tr>",
+            escape(entry.status)
+        )?;
+    }
+    writeln!(writer, "</table></body></html>")?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::{FileWriteStr, PathChild};
+
+    #[test]
+    fn test_write_report() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("report.html");
+        let orig = vec!["a".to_string(), "<b>".to_string()];
+        let diff = vec!["a".to_string(), "c & d".to_string()];
+        let input = dir.child("x.csv");
+        input.write_str("abc").unwrap();
+        let mut args = crate::cli::tests::compare_args(&["-o", "1"]);
+        args.orig = input.to_path_buf();
+        args.diff = input.to_path_buf();
+        let provenance = Provenance::new(&args).unwrap();
+        write_report(
+            &path,
+            ("a/x.csv", "b/x.csv"),
+            (&orig, &diff),
+            &Masking::Plain,
+            usize::MAX,
+            &provenance,
+        )
+        .unwrap();
+        let html = fs::read_to_string(&path).unwrap();
+        assert!(html.contains("<li>a/x.csv: 3 bytes, sha256 ba7816bf"));
+        assert!(html.contains("<details class=\"provenance\">"));
+        assert!(html.contains("<tr class=\"del\"><td>-</td><td>&lt;b&gt;</td></tr>"));
+        assert!(html.contains("<tr class=\"ins\"><td>+</td><td>c &amp; d</td></tr>"));
+
+        let entries = [IndexEntry {
+            path: Path::new("x.csv"),
+            status: "differ",
+            passed: false,
+            report: Some(Path::new("x.csv.html")),
+        }];
+        write_index(dir.path(), "run", &entries).unwrap();
+        let index = fs::read_to_string(dir.path().join("index.html")).unwrap();
+        assert!(index.contains("<a href=\"x.csv.html\">x.csv</a>"));
+        assert!(index.contains("0 of 1 file pairs passed."));
+    }
+}
@@ -0,0 +1,184 @@
+use std::env;
+use std::sync::OnceLock;
+
+use clap::ValueEnum;
+
+/// Language of prompts, summaries and error messages.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Locale {
+    En,
+    De,
+}
+
+/// Messages by key, in English and German. `{name}` placeholders are
+/// filled in by `tr`.
+const MESSAGES: &[(&str, &str, &str)] = &[
+    ("prompt", "Is this correct?", "Ist das korrekt?"),
+    (
+        "prompt-help",
+        "orig has {orig_count} records, first record: {orig_first}\ndiff has {diff_count} records, first record: {diff_first}",
+        "orig hat {orig_count} Datensätze, erster Datensatz: {orig_first}\ndiff hat {diff_count} Datensätze, erster Datensatz: {diff_first}",
+    ),
+    (
+        "interrupted",
+        "User Interruption: The process has been interrupted. Exiting...",
+        "Abbruch durch den Benutzer: Der Vorgang wurde unterbrochen. Beende...",
+    ),
+    (
+        "files-identical",
+        "Files {orig} and {diff} are identical",
+        "Dateien {orig} und {diff} sind identisch",
+    ),
+    (
+        "files-differ",
+        "Files {orig} and {diff} differ",
+        "Dateien {orig} und {diff} sind verschieden",
+    ),
+    (
+        "columns-identical",
+        "Columns of {orig} and {diff} are identical",
+        "Spalten von {orig} und {diff} sind identisch",
+    ),
+    (
+        "columns-differ",
+        "Columns of {orig} and {diff} differ",
+        "Spalten von {orig} und {diff} sind verschieden",
+    ),
+    ("only-in", "Only in {dir}: {path}", "Nur in {dir}: {path}"),
+    ("summary", "Summary", "Zusammenfassung"),
+    ("summary-identical", "identical", "identisch"),
+    ("summary-differing", "differing", "verschieden"),
+    ("summary-only-orig", "only in orig", "nur in orig"),
+    ("summary-only-diff", "only in diff", "nur in diff"),
+    ("summary-errored", "errored", "fehlerhaft"),
+    (
+        "pairs-not-identical",
+        "{failed} of {total} file pairs are not identical",
+        "{failed} von {total} Dateipaaren sind nicht identisch",
+    ),
+    (
+        "matched-keys",
+        "{matched} matched keys, {changed} changed",
+        "{matched} passende Schlüssel, {changed} geändert",
+    ),
+    (
+        "column-total",
+        "total of {column}: {old} -> {new} ({change})",
+        "Summe von {column}: {old} -> {new} ({change})",
+    ),
+    (
+        "duplicate-keys",
+        "{file}: {count} rows repeat an earlier key and are not compared",
+        "{file}: {count} Zeilen wiederholen einen früheren Schlüssel und werden nicht verglichen",
+    ),
+    (
+        "likely-renamed",
+        "column `{orig}` likely renamed to `{diff}`",
+        "Spalte `{orig}` vermutlich in `{diff}` umbenannt",
+    ),
+    (
+        "auto-index",
+        "Comparing orig column {orig} with diff column {diff}, {shared}% of sampled values shared",
+        "Vergleiche orig-Spalte {orig} mit diff-Spalte {diff}, {shared}% der Stichprobenwerte gemeinsam",
+    ),
+    (
+        "more-changes",
+        "… and {count} more changes",
+        "… und {count} weitere Änderungen",
+    ),
+    ("resolve-hunk", "Resolve hunk:", "Abschnitt auflösen:"),
+    (
+        "resolved",
+        "Resolved {count} hunks, wrote {path}",
+        "{count} Abschnitte aufgelöst, {path} geschrieben",
+    ),
+    ("wrote", "Wrote {path}", "{path} geschrieben"),
+    ("parse-warnings", "Warnings", "Warnungen"),
+    (
+        "warn-short",
+        "{count} records too short for the column",
+        "{count} Datensätze zu kurz für die Spalte",
+    ),
+    (
+        "warn-filtered",
+        "{count} records dropped by filters",
+        "{count} Datensätze durch Filter verworfen",
+    ),
+    ("warn-empty", "{count} empty values", "{count} leere Werte"),
+    (
+        "warn-replaced",
+        "{count} values with invalid UTF-8 replaced",
+        "{count} Werte mit ungültigem UTF-8 ersetzt",
+    ),
+];
+
+static LOCALE: OnceLock<Locale> = OnceLock::new();
+
+/// Locale named by a `LANG`-style value such as `de_DE.UTF-8`.
+fn from_env_value(value: &str) -> Option<Locale> {
+    match value.get(..2)?.to_ascii_lowercase().as_str() {
+        "de" => Some(Locale::De),
+        "en" => Some(Locale::En),
+        _ => None,
+    }
+}
+
+/// Selects the locale: `--lang`, else `CSV_COMPARE_LANG`, else the usual
+/// `LC_ALL`, `LC_MESSAGES` and `LANG` variables, else English.
+pub fn init(lang: Option<Locale>) {
+    let locale = lang
+        .or_else(|| {
+            ["CSV_COMPARE_LANG", "LC_ALL", "LC_MESSAGES", "LANG"]
+                .iter()
+                .filter_map(|name| env::var(name).ok())
+                .find(|value| !value.is_empty())
+                .and_then(|value| from_env_value(&value))
+        })
+        .unwrap_or(Locale::En);
+    let _ = LOCALE.set(locale);
+}
+
+fn translate(locale: Locale, key: &str, args: &[(&str, &str)]) -> String {
+    let Some(&(_, en, de)) = MESSAGES.iter().find(|(name, _, _)| *name == key) else {
+        return key.to_string();
+    };
+    let mut message = match locale {
+        Locale::En => en,
+        Locale::De => de,
+    }
+    .to_string();
+    for (name, value) in args {
+        message = message.replace(&format!("{{{name}}}"), value);
+    }
+    message
+}
+
+/// The message `key` in the selected locale, with `args` filled in.
+pub fn tr(key: &str, args: &[(&str, &str)]) -> String {
+    translate(*LOCALE.get().unwrap_or(&Locale::En), key, args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate() {
+        let args = [("orig", "a.csv"), ("diff", "b.csv")];
+        assert_eq!(
+            translate(Locale::En, "files-differ", &args),
+            "Files a.csv and b.csv differ"
+        );
+        assert_eq!(
+            translate(Locale::De, "files-differ", &args),
+            "Dateien a.csv und b.csv sind verschieden"
+        );
+        assert_eq!(translate(Locale::De, "unknown", &[]), "unknown");
+        assert_eq!(from_env_value("de_DE.UTF-8"), Some(Locale::De));
+        assert_eq!(from_env_value("C"), None);
+        for (key, en, de) in MESSAGES {
+            let placeholders = |message: &str| message.matches('{').count();
+            assert_eq!(placeholders(en), placeholders(de), "{key}");
+        }
+    }
+}
@@ -0,0 +1,724 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use anyhow::{Result, anyhow};
+use colored::{Color, Colorize};
+use csv::{StringRecord, Terminator, WriterBuilder};
+
+use crate::cli::{CompareArgs, ParseArgs, SetOperation};
+use crate::column_map::ColumnMap;
+use crate::compare::prompt_csv;
+use crate::counts::{Counts, print_counts};
+use crate::display::truncate;
+use crate::errors::Category;
+use crate::expr::Expr;
+use crate::i18n::tr;
+use crate::parse::{Table, display_name, field, normalize_field, read_table, same_header};
+use crate::redact::Masking;
+use crate::rules::Rules;
+use crate::sha256::{Sha256, to_hex};
+
+/// Key columns and normalized compared values of a record.
+type Keyed = (Vec<String>, Vec<String>);
+
+/// Key columns, key expression and hashed key columns of a join.
+type Keys<'a> = (&'a [usize], Option<&'a Expr>, &'a [usize]);
+
+/// Hash identifying a row by its `columns`, 128 bits of SHA-256 in hex.
+/// Fields are length-prefixed so that `ab,c` and `a,bc` hash apart.
+fn hash_key(record: &StringRecord, columns: &[usize]) -> Result<String> {
+    let mut hasher = Sha256::new();
+    for &column in columns {
+        let value = field(record, column)?;
+        hasher.update(&(value.len() as u64).to_le_bytes());
+        hasher.update(value.as_bytes());
+    }
+    Ok(to_hex(&hasher.finalize()[..16]))
+}
+
+/// Compared values for a key present in both files.
+#[derive(Debug, PartialEq)]
+struct Joined {
+    key: Vec<String>,
+    old: Vec<String>,
+    new: Vec<String>,
+}
+
+/// A column compared between both files, with 1-based indices per side.
+#[derive(Debug, PartialEq)]
+struct Column {
+    header: Option<String>,
+    orig: usize,
+    diff: usize,
+}
+
+impl Column {
+    fn name(&self) -> String {
+        self.header.clone().unwrap_or_else(|| self.orig.to_string())
+    }
+}
+
+fn header(table: &Table, index: usize) -> Option<String> {
+    table
+        .headers
+        .as_ref()
+        .and_then(|headers| headers.get(index - 1))
+        .map(str::to_string)
+}
+
+/// Diff index of the orig column `index`: taken from the column map, else
+/// matched by header name when both files have headers, and by position
+/// otherwise. `None` if no diff header matches.
+fn diff_column(
+    index: usize,
+    (orig, diff): (&Table, &Table),
+    map: &HashMap<usize, usize>,
+    strict: bool,
+) -> Option<usize> {
+    match (map.get(&index), header(orig, index), &diff.headers) {
+        (Some(&mapped), _, _) => Some(mapped),
+        (None, Some(name), Some(headers)) => headers
+            .iter()
+            .position(|other| same_header(other, &name, strict))
+            .map(|position| position + 1),
+        _ => Some(index),
+    }
+}
+
+/// Columns to compare: the selected column, or every non-key column when no
+/// index is given, with their `diff_column`.
+fn compared_columns(
+    args: &CompareArgs,
+    (orig, diff): (&Table, &Table),
+    map: &HashMap<usize, usize>,
+) -> Result<Vec<Column>> {
+    if let Some(orig_index) = args.orig_index {
+        let mapped = map.get(&orig_index).copied();
+        return Ok(vec![Column {
+            header: header(orig, orig_index),
+            orig: orig_index,
+            diff: args.diff_index.or(mapped).unwrap_or(orig_index),
+        }]);
+    }
+    if args.parse.with_prefix.is_some() {
+        return Err(anyhow!(
+            "--with-prefix needs a column selected with --orig-index"
+        ));
+    }
+
+    let width = match &orig.headers {
+        Some(headers) => headers.len(),
+        None => orig.records.first().map_or(0, |record| record.len()),
+    };
+    Ok((1..=width)
+        .filter(|index| !args.join.contains(index) && !args.hash_key.contains(index))
+        .filter_map(|index| {
+            Some(Column {
+                header: header(orig, index),
+                orig: index,
+                diff: diff_column(index, (orig, diff), map, args.strict_headers)?,
+            })
+        })
+        .collect())
+}
+
+/// Minimum Jaccard similarity of the distinct values of two columns for one
+/// to count as a probable rename of the other.
+const RENAME_SIMILARITY: f64 = 0.5;
+
+fn distinct_values(table: &Table, index: usize) -> HashSet<&str> {
+    table
+        .records
+        .iter()
+        .filter_map(|record| record.get(index))
+        .collect()
+}
+
+/// Headers of orig columns missing from the diff headers, paired with the
+/// unmatched diff column whose distinct values are most alike, best pairs
+/// first.
+fn probable_renames<'a>(
+    args: &CompareArgs,
+    (orig, diff): (&'a Table, &'a Table),
+    map: &HashMap<usize, usize>,
+) -> Vec<(String, String)> {
+    let (Some(orig_headers), Some(diff_headers)) = (&orig.headers, &diff.headers) else {
+        return Vec::new();
+    };
+    let unmatched =
+        |headers: &StringRecord, other: &StringRecord, table: &'a Table, mapped: &[usize]| {
+            headers
+                .iter()
+                .enumerate()
+                .filter(|&(index, name)| {
+                    !args.join.contains(&(index + 1))
+                        && !args.hash_key.contains(&(index + 1))
+                        && !mapped.contains(&(index + 1))
+                        && !other
+                            .iter()
+                            .any(|other| same_header(other, name, args.strict_headers))
+                })
+                .map(|(index, name)| (name.to_string(), distinct_values(table, index)))
+                .collect::<Vec<_>>()
+        };
+    let orig_mapped: Vec<usize> = map.keys().copied().collect();
+    let diff_mapped: Vec<usize> = map.values().copied().collect();
+    let orig_columns = unmatched(orig_headers, diff_headers, orig, &orig_mapped);
+    let diff_columns = unmatched(diff_headers, orig_headers, diff, &diff_mapped);
+
+    let mut candidates = Vec::new();
+    for (orig_name, orig_values) in &orig_columns {
+        for (diff_name, diff_values) in &diff_columns {
+            let union = orig_values.union(diff_values).count();
+            let shared = orig_values.intersection(diff_values).count();
+            let similarity = if union == 0 {
+                0.0
+            } else {
+                shared as f64 / union as f64
+            };
+            if similarity >= RENAME_SIMILARITY {
+                candidates.push((similarity, orig_name, diff_name));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let (mut orig_taken, mut diff_taken) = (HashSet::new(), HashSet::new());
+    let mut renames = Vec::new();
+    for (_, orig_name, diff_name) in candidates {
+        if !orig_taken.contains(orig_name) && !diff_taken.contains(diff_name) {
+            orig_taken.insert(orig_name);
+            diff_taken.insert(diff_name);
+            renames.push((orig_name.clone(), diff_name.clone()));
+        }
+    }
+    renames
+}
+
+/// Key of `record`: its key columns, then the key expression and the hash of
+/// the hashed key columns, if given.
+fn record_key(
+    record: &StringRecord,
+    (keys, key_expr, hashed): Keys,
+    headers: Option<&StringRecord>,
+) -> Result<Vec<String>> {
+    let mut key = keys
+        .iter()
+        .map(|&key| field(record, key).map(str::to_string))
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(expr) = key_expr {
+        key.push(expr.eval(record, headers)?.to_string());
+    }
+    if !hashed.is_empty() {
+        key.push(hash_key(record, hashed)?);
+    }
+    Ok(key)
+}
+
+/// Key and normalized values of each record, skipping records filtered out by
+/// the parse options.
+fn keyed_values(
+    table: &Table,
+    keys: Keys,
+    indices: &[usize],
+    args: &ParseArgs,
+) -> Result<Vec<Keyed>> {
+    let mut res = Vec::with_capacity(table.records.len());
+    'records: for record in &table.records {
+        let key = record_key(record, keys, table.headers.as_ref())?;
+        let mut values = Vec::with_capacity(indices.len());
+        for &index in indices {
+            match normalize_field(field(record, index)?, args) {
+                Some(value) => values.push(value),
+                None => continue 'records,
+            }
+        }
+        res.push((key, values));
+    }
+    Ok(res)
+}
+
+/// Number of records repeating the key of an earlier record.
+fn duplicate_keys(values: &[Keyed]) -> usize {
+    let mut seen = HashSet::new();
+    values.iter().filter(|(key, _)| !seen.insert(key)).count()
+}
+
+/// Inner join of both sides on their keys, in orig order. The first record of
+/// a duplicated key wins.
+fn join_values(orig: &[Keyed], diff: &[Keyed]) -> Vec<Joined> {
+    let mut diff_by_key: HashMap<&[String], &[String]> = HashMap::new();
+    for (key, values) in diff {
+        diff_by_key.entry(key).or_insert(values);
+    }
+
+    let mut seen = HashSet::new();
+    orig.iter()
+        .filter(|(key, _)| seen.insert(key))
+        .filter_map(|(key, old)| {
+            diff_by_key.get(key.as_slice()).map(|new| Joined {
+                key: key.clone(),
+                old: old.clone(),
+                new: new.to_vec(),
+            })
+        })
+        .collect()
+}
+
+fn decimals(value: &str) -> usize {
+    value.split_once('.').map_or(0, |(_, frac)| frac.len())
+}
+
+/// Numeric difference `new - old`, formatted with the precision of the inputs.
+fn delta(old: &str, new: &str) -> Option<String> {
+    let old_num: f64 = old.trim().parse().ok()?;
+    let new_num: f64 = new.trim().parse().ok()?;
+    let precision = decimals(old.trim()).max(decimals(new.trim()));
+    Some(format!("{:+.precision$}", new_num - old_num))
+}
+
+/// Sum of the `index`th compared value of every record and the precision of
+/// its inputs, if the values are numbers. Empty values are skipped.
+fn total(values: &[Keyed], index: usize) -> Option<(f64, usize)> {
+    let (mut sum, mut precision, mut any) = (0.0, 0, false);
+    for value in values.iter().map(|(_, values)| values[index].trim()) {
+        if value.is_empty() {
+            continue;
+        }
+        sum += value.parse::<f64>().ok()?;
+        precision = precision.max(decimals(value));
+        any = true;
+    }
+    any.then_some((sum, precision))
+}
+
+/// Change of a column total, absolute and in percent of the orig total.
+fn total_change(old: f64, new: f64, precision: usize) -> String {
+    let change = format!("{:+.precision$}", new - old);
+    if old == 0.0 {
+        return change;
+    }
+    format!("{change}, {:+.2}%", (new - old) / old.abs() * 100.0)
+}
+
+/// Key of every record of `table`, with the record.
+fn keyed_records<'a>(table: &'a Table, keys: Keys) -> Result<Vec<(Vec<String>, &'a StringRecord)>> {
+    table
+        .records
+        .iter()
+        .map(|record| Ok((record_key(record, keys, table.headers.as_ref())?, record)))
+        .collect()
+}
+
+/// Full rows of the key set `op` selects, as `--emit` writes them: orig rows
+/// for `intersection`, `only-orig` and `union`, followed by the `only-diff`
+/// rows for `union`. Rows keep their file order (key order with
+/// `--deterministic`) and the header of the file they come from.
+fn emit_rows(
+    args: &CompareArgs,
+    op: SetOperation,
+    (orig, diff): (&Table, &Table),
+    (orig_keys, diff_keys): (Keys, Keys),
+) -> Result<()> {
+    let (orig_rows, diff_rows) = (
+        keyed_records(orig, orig_keys)?,
+        keyed_records(diff, diff_keys)?,
+    );
+    let key_set = |rows: &[(Vec<String>, _)]| -> HashSet<Vec<String>> {
+        rows.iter().map(|(key, _)| key.clone()).collect()
+    };
+    let (orig_set, diff_set) = (key_set(&orig_rows), key_set(&diff_rows));
+    let mut rows: Vec<_> = match op {
+        SetOperation::Intersection => orig_rows
+            .into_iter()
+            .filter(|(key, _)| diff_set.contains(key))
+            .collect(),
+        SetOperation::OnlyOrig => orig_rows
+            .into_iter()
+            .filter(|(key, _)| !diff_set.contains(key))
+            .collect(),
+        SetOperation::OnlyDiff => diff_rows
+            .into_iter()
+            .filter(|(key, _)| !orig_set.contains(key))
+            .collect(),
+        SetOperation::Union => orig_rows
+            .into_iter()
+            .chain(
+                diff_rows
+                    .into_iter()
+                    .filter(|(key, _)| !orig_set.contains(key)),
+            )
+            .collect(),
+    };
+    if args.deterministic {
+        rows.sort_by(|a, b| a.0.cmp(&b.0));
+    }
+
+    let output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut writer = WriterBuilder::new()
+        .flexible(true)
+        .terminator(if args.print0 {
+            Terminator::Any(b'\0')
+        } else {
+            Terminator::Any(b'\n')
+        })
+        .from_writer(output);
+    let headers = match op {
+        SetOperation::OnlyDiff => &diff.headers,
+        _ => &orig.headers,
+    };
+    if let Some(headers) = headers {
+        writer.write_record(headers)?;
+    }
+    for (_, record) in rows {
+        writer.write_record(record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// `value` as a CSV field, quoted if it holds a comma, quote or line break.
+fn csv_field(value: &str) -> String {
+    // A record of one empty field would be written as `""`.
+    if value.is_empty() {
+        return String::new();
+    }
+    let mut writer = WriterBuilder::new()
+        .terminator(Terminator::Any(b'\n'))
+        .from_writer(Vec::new());
+    // Writing to memory cannot fail.
+    writer.write_record([value]).unwrap();
+    let record = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+    record.strip_suffix('\n').unwrap_or(&record).to_string()
+}
+
+/// Compared values of a row as a CSV record, with the changed fields in
+/// `color` and the others dimmed.
+fn colored_row(values: &[String], changes: &[bool], color: Color, masking: &Masking) -> String {
+    values
+        .iter()
+        .map(|value| csv_field(&masking.apply(value)))
+        .zip(changes)
+        .map(|(value, &changed)| {
+            if changed {
+                value.color(color).bold().to_string()
+            } else {
+                value.dimmed().to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(&",".dimmed().to_string())
+}
+
+pub fn run(args: &CompareArgs) -> Result<()> {
+    let orig = read_table(&args.orig, &args.parse.reader)?;
+    let diff = read_table(&args.diff, &args.parse.reader)?;
+    let mut rules = match &args.rules {
+        Some(path) => Rules::from_path(path)?,
+        None => Rules::default(),
+    };
+    if let Some(seconds) = args.time_tolerance {
+        rules = rules.with_time_tolerance(seconds);
+    }
+    let map = match &args.column_map {
+        Some(path) => ColumnMap::from_path(path)?.indices(&orig, &diff, args.strict_headers)?,
+        None => HashMap::new(),
+    };
+    let columns = compared_columns(args, (&orig, &diff), &map)?;
+    let full_row = args.orig_index.is_none();
+    if full_row {
+        for (orig_name, diff_name) in probable_renames(args, (&orig, &diff), &map) {
+            println!(
+                "{}",
+                tr(
+                    "likely-renamed",
+                    &[("orig", &orig_name), ("diff", &diff_name)]
+                )
+                .yellow()
+            );
+        }
+    }
+
+    let orig_indices: Vec<usize> = columns.iter().map(|column| column.orig).collect();
+    let diff_indices: Vec<usize> = columns.iter().map(|column| column.diff).collect();
+    let diff_key_columns = |columns: &[usize]| -> Result<Vec<usize>> {
+        columns
+            .iter()
+            .map(|&column| {
+                diff_column(column, (&orig, &diff), &map, args.strict_headers).ok_or_else(|| {
+                    Category::BadIndex.error(format!(
+                        "no diff column matches key column `{}`",
+                        header(&orig, column).unwrap_or_default()
+                    ))
+                })
+            })
+            .collect()
+    };
+    let (diff_join, diff_hash_key) = (
+        diff_key_columns(&args.join)?,
+        diff_key_columns(&args.hash_key)?,
+    );
+    let orig_keys = (
+        args.join.as_slice(),
+        args.key_expr.as_ref(),
+        args.hash_key.as_slice(),
+    );
+    let diff_keys = (
+        diff_join.as_slice(),
+        args.key_expr.as_ref(),
+        diff_hash_key.as_slice(),
+    );
+    let orig_values = keyed_values(&orig, orig_keys, &orig_indices, &args.parse)?;
+    let diff_values = keyed_values(&diff, diff_keys, &diff_indices, &args.parse)?;
+
+    let rows = |values: &[Keyed]| -> Vec<String> {
+        values.iter().map(|(_, values)| values.join(",")).collect()
+    };
+    if !args.yes {
+        prompt_csv(&rows(&orig_values), &rows(&diff_values))?;
+    }
+    if let Some(op) = args.emit {
+        return emit_rows(args, op, (&orig, &diff), (orig_keys, diff_keys));
+    }
+
+    for (path, values) in [(&args.orig, &orig_values), (&args.diff, &diff_values)] {
+        let count = duplicate_keys(values);
+        if count > 0 {
+            let file = display_name(path);
+            eprintln!(
+                "{}",
+                tr(
+                    "duplicate-keys",
+                    &[("file", &file), ("count", &count.to_string())]
+                )
+                .yellow()
+            );
+        }
+    }
+
+    let masking = Masking::new(args);
+    let mut joined = join_values(&orig_values, &diff_values);
+    if args.deterministic {
+        joined.sort_by(|a, b| a.key.cmp(&b.key));
+    }
+    let mut changed = 0;
+    let mut column_changes = vec![0; columns.len()];
+    for Joined { key, old, new } in &joined {
+        let changes: Vec<bool> = columns
+            .iter()
+            .zip(old)
+            .zip(new)
+            .map(|((column, old), new)| {
+                !rules
+                    .get(column.header.as_deref(), column.orig)
+                    .equal(old, new)
+            })
+            .collect();
+        if !changes.contains(&true) {
+            continue;
+        }
+        changed += 1;
+        for (count, _) in column_changes.iter_mut().zip(&changes).filter(|(_, c)| **c) {
+            *count += 1;
+        }
+        if args.counts_only {
+            continue;
+        }
+        let key = key
+            .iter()
+            .map(|key| masking.apply(key))
+            .collect::<Vec<_>>()
+            .join(",");
+        if args.row_diff {
+            println!("{}", key.cyan());
+            println!(
+                "{}{}",
+                "-".red(),
+                colored_row(old, &changes, Color::Red, &masking)
+            );
+            println!(
+                "{}{}",
+                "+".green(),
+                colored_row(new, &changes, Color::Green, &masking)
+            );
+            continue;
+        }
+        for (((column, old), new), _) in columns
+            .iter()
+            .zip(old)
+            .zip(new)
+            .zip(&changes)
+            .filter(|(_, changed)| **changed)
+        {
+            let name = if full_row {
+                format!("{}: ", column.name())
+            } else {
+                String::new()
+            };
+            let delta = delta(old, new)
+                .filter(|_| masking.is_plain())
+                .map(|delta| format!(" ({delta})"));
+            let (old, new) = (masking.apply(old), masking.apply(new));
+            let (old, new) = match args.max_width {
+                Some(width) => (
+                    truncate(&old, width).into_owned(),
+                    truncate(&new, width).into_owned(),
+                ),
+                None => (old.into_owned(), new.into_owned()),
+            };
+            println!(
+                "{}: {name}{} -> {}{}",
+                key.cyan(),
+                old.red(),
+                new.green(),
+                delta.unwrap_or_default()
+            );
+        }
+    }
+    if args.counts_only {
+        let keys = |values: &[Keyed]| -> HashSet<Vec<String>> {
+            values.iter().map(|(key, _)| key.clone()).collect()
+        };
+        let (orig_keys, diff_keys) = (keys(&orig_values), keys(&diff_values));
+        let counts = Counts {
+            removed: orig_keys.difference(&diff_keys).count(),
+            added: diff_keys.difference(&orig_keys).count(),
+            changed,
+        };
+        let mut output = io::stdout().lock();
+        print_counts(&mut output, &counts)?;
+        for (column, count) in columns.iter().zip(column_changes) {
+            writeln!(output, "  {}: {count}", column.name())?;
+        }
+        return Ok(());
+    }
+    let (matched, changed) = (joined.len().to_string(), changed.to_string());
+    println!(
+        "{}",
+        tr(
+            "matched-keys",
+            &[("matched", &matched), ("changed", &changed)]
+        )
+    );
+    if args.totals {
+        for (index, column) in columns.iter().enumerate() {
+            let (Some((old, old_precision)), Some((new, new_precision))) =
+                (total(&orig_values, index), total(&diff_values, index))
+            else {
+                continue;
+            };
+            let precision = old_precision.max(new_precision);
+            println!(
+                "{}",
+                tr(
+                    "column-total",
+                    &[
+                        ("column", &column.name()),
+                        ("old", &format!("{old:.precision$}")),
+                        ("new", &format!("{new:.precision$}")),
+                        ("change", &total_change(old, new, precision)),
+                    ]
+                )
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::FileWriteStr;
+
+    use crate::cli::ReaderArgs;
+
+    fn keyed(rows: &[(&str, &str)]) -> Vec<Keyed> {
+        rows.iter()
+            .map(|(key, value)| (vec![key.to_string()], vec![value.to_string()]))
+            .collect()
+    }
+
+    #[test]
+    fn test_join_values() {
+        let orig = keyed(&[("a", "1.50"), ("b", "2"), ("c", "3"), ("a", "9")]);
+        let diff = keyed(&[("c", "4"), ("a", "1.25"), ("d", "5")]);
+        let joined = join_values(&orig, &diff);
+        assert_eq!(joined.len(), 2);
+        assert_eq!(joined[0].key, ["a"]);
+        assert_eq!(joined[0].old, ["1.50"]);
+        assert_eq!(joined[0].new, ["1.25"]);
+        assert_eq!(joined[1].key, ["c"]);
+        assert_eq!(duplicate_keys(&orig), 1);
+        assert_eq!(duplicate_keys(&diff), 0);
+
+        assert_eq!(delta("1.50", "1.25").unwrap(), "-0.25");
+        assert_eq!(delta("3", "4").unwrap(), "+1");
+        assert_eq!(delta("x", "4"), None);
+
+        assert_eq!(total(&orig, 0), Some((15.5, 2)));
+        assert_eq!(total(&keyed(&[("a", "1"), ("b", "x")]), 0), None);
+        assert_eq!(total_change(16.5, 10.25, 2), "-6.25, -37.88%");
+        assert_eq!(total_change(0.0, 2.0, 0), "+2");
+
+        assert_eq!(csv_field("a, b"), "\"a, b\"");
+        assert_eq!(csv_field("\"c\""), "\"\"\"c\"\"\"");
+        assert_eq!(csv_field("d"), "d");
+        assert_eq!(csv_field(""), "");
+    }
+
+    #[test]
+    fn test_compared_columns() {
+        let orig = assert_fs::NamedTempFile::new("orig.csv").unwrap();
+        let diff = assert_fs::NamedTempFile::new("diff.csv").unwrap();
+        orig.write_str("id,name,price\n1,a,2").unwrap();
+        diff.write_str("price,id,extra\n2,1,x").unwrap();
+        let reader = ReaderArgs {
+            with_headers: true,
+            ..Default::default()
+        };
+        let orig = read_table(orig.path(), &reader).unwrap();
+        let diff = read_table(diff.path(), &reader).unwrap();
+
+        let args = crate::cli::tests::compare_args(&["--join", "2", "--with-headers"]);
+        assert_eq!(
+            compared_columns(&args, (&orig, &diff), &HashMap::new()).unwrap(),
+            [
+                Column {
+                    header: Some("id".to_string()),
+                    orig: 1,
+                    diff: 2
+                },
+                Column {
+                    header: Some("price".to_string()),
+                    orig: 3,
+                    diff: 1
+                },
+            ]
+        );
+        assert_eq!(
+            diff_column(1, (&orig, &diff), &HashMap::new(), false),
+            Some(2)
+        );
+        assert_eq!(diff_column(2, (&orig, &diff), &HashMap::new(), false), None);
+
+        let expr = Expr::parse("concat(id, '-', name)").unwrap();
+        let keyed =
+            keyed_values(&orig, (&[], Some(&expr), &[]), &[3], &ParseArgs::default()).unwrap();
+        assert_eq!(keyed, [(vec!["1-a".to_string()], vec!["2".to_string()])]);
+
+        let keyed = keyed_values(&orig, (&[], None, &[1, 2]), &[3], &ParseArgs::default()).unwrap();
+        assert_eq!(keyed[0].0, ["415731d648dc4502281c89ab8e013022"]);
+
+        let renamed = assert_fs::NamedTempFile::new("renamed.csv").unwrap();
+        renamed.write_str("id,label,price\n1,a,2").unwrap();
+        let renamed = read_table(renamed.path(), &reader).unwrap();
+        let args = crate::cli::tests::compare_args(&["--join", "1", "--with-headers"]);
+        assert_eq!(
+            probable_renames(&args, (&orig, &renamed), &HashMap::new()),
+            [("name".to_string(), "label".to_string())]
+        );
+    }
+}
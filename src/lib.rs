@@ -0,0 +1,114 @@
+//! The comparison engine behind the `csv-compare` command line tool. See
+//! [`api::compare`] for comparing CSV texts from other programs.
+
+mod aggregate;
+pub mod api;
+mod apply;
+mod auto_index;
+mod cache;
+mod checkpoint;
+mod chunked;
+mod cli;
+mod clipboard;
+mod column_map;
+mod compare;
+mod counts;
+mod dates;
+mod diff3;
+mod dir;
+mod display;
+mod errors;
+mod expr;
+mod ffi;
+mod fingerprint;
+mod fixed_width;
+mod formats;
+mod generate;
+mod git;
+mod html;
+mod i18n;
+mod join;
+pub mod json;
+mod logging;
+mod merge;
+mod moves;
+mod notify;
+mod parse;
+mod provenance;
+mod redact;
+mod report;
+mod review;
+mod rules;
+mod serve;
+mod sets;
+mod sha256;
+mod side_by_side;
+mod sort;
+mod spill;
+mod stats;
+mod template;
+mod tokenize;
+mod tool;
+mod transform;
+mod validate;
+
+use std::env;
+use std::process::ExitCode;
+use std::time::Instant;
+
+use anyhow::Result;
+
+use cli::{Cli, Command};
+use errors::ErrorFormat;
+
+/// Runs the command line tool.
+pub fn main() -> ExitCode {
+    let cli = Cli::parse_args();
+    let res = match &cli.command {
+        Command::Compare(args) if args.copy => clipboard::run_copying(),
+        _ => run(&cli).map(|()| ExitCode::SUCCESS),
+    };
+    match res {
+        Ok(code) => code,
+        Err(err) => {
+            match cli.errors {
+                ErrorFormat::Text => eprintln!("Error: {err:?}"),
+                ErrorFormat::Json => eprintln!("{}", errors::to_json(&err)),
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: &Cli) -> Result<()> {
+    i18n::init(cli.lang);
+    logging::init(cli.log_file.as_deref())?;
+    let command_line: Vec<String> = env::args_os()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    logging::info(
+        "start",
+        &[
+            ("version", env!("CARGO_PKG_VERSION").to_string()),
+            ("args", command_line.join(" ")),
+        ],
+    );
+    let start = Instant::now();
+
+    let res = match &cli.command {
+        Command::Compare(args) => compare::run(args),
+        Command::Stats(args) => stats::run(args),
+        Command::Merge(args) => merge::run(args),
+        Command::Generate(args) => generate::run(args),
+        Command::Validate(args) => validate::run(args),
+        Command::Serve(args) => serve::run(args),
+        Command::Apply(args) => apply::run(args),
+        Command::Report(args) => report::run(args),
+    };
+    let elapsed = ("elapsed_ms", start.elapsed().as_millis().to_string());
+    match &res {
+        Ok(()) => logging::info("finish", &[elapsed]),
+        Err(err) => logging::warn("finish", &[elapsed, ("error", format!("{err:#}"))]),
+    }
+    res
+}
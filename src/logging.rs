@@ -0,0 +1,88 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+use anyhow::Result;
+
+use crate::dates::format_timestamp;
+
+static LOG: OnceLock<Mutex<BufWriter<File>>> = OnceLock::new();
+
+/// Opens the `--log-file`; events are dropped when no file is given.
+pub fn init(path: Option<&Path>) -> Result<()> {
+    if let Some(path) = path {
+        let _ = LOG.set(Mutex::new(BufWriter::new(File::create(path)?)));
+    }
+    Ok(())
+}
+
+pub fn enabled() -> bool {
+    LOG.get().is_some()
+}
+
+/// Quotes a logfmt value when it contains spaces, quotes or `=`.
+fn quote(value: &str) -> String {
+    if !value.is_empty() && !value.contains([' ', '"', '=', '\n', '\t']) {
+        return value.to_string();
+    }
+    format!("{value:?}")
+}
+
+fn format_line(time: SystemTime, level: &str, event: &str, fields: &[(&str, String)]) -> String {
+    let mut line = format!(
+        "ts={} level={level} event={event}",
+        quote(&format_timestamp(time))
+    );
+    for (key, value) in fields {
+        line.push_str(&format!(" {key}={}", quote(value)));
+    }
+    line
+}
+
+fn write(level: &str, event: &str, fields: &[(&str, String)]) {
+    let Some(log) = LOG.get() else {
+        return;
+    };
+    let line = format_line(SystemTime::now(), level, event, fields);
+    if let Ok(mut log) = log.lock() {
+        // Logging must never fail the comparison itself.
+        let _ = writeln!(log, "{line}").and_then(|_| log.flush());
+    }
+}
+
+/// Logs an `info` event as one logfmt line.
+pub fn info(event: &str, fields: &[(&str, String)]) {
+    write("info", event, fields);
+}
+
+/// Logs a `warn` event as one logfmt line.
+pub fn warn(event: &str, fields: &[(&str, String)]) {
+    write("warn", event, fields);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_format_line() {
+        let time = UNIX_EPOCH + Duration::from_secs(86_400);
+        assert_eq!(
+            format_line(
+                time,
+                "info",
+                "parsed",
+                &[
+                    ("file", "my file.csv".to_string()),
+                    ("values", "3".to_string())
+                ]
+            ),
+            "ts=\"1970-01-02 00:00:00.000000000 +0000\" level=info event=parsed file=\"my file.csv\" values=3"
+        );
+        assert_eq!(quote(""), "\"\"");
+        assert_eq!(quote("a=b"), "\"a=b\"");
+    }
+}
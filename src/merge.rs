@@ -0,0 +1,115 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{self, Write};
+
+use anyhow::Result;
+use csv::{StringRecord, Writer};
+
+use crate::cli::{MergeArgs, MergeStrategy};
+use crate::parse::{field, read_table};
+
+/// Merges rows of `orig` and `diff` matched by their key column. Rows present
+/// on one side only are always kept; matched rows that differ are resolved by
+/// `strategy`. Orig rows come first in their original order, followed by the
+/// rows only present in diff.
+fn merge_records<'a>(
+    orig: &'a [StringRecord],
+    diff: &'a [StringRecord],
+    on: usize,
+    strategy: MergeStrategy,
+) -> Result<Vec<&'a StringRecord>> {
+    let mut diff_by_key: HashMap<&str, VecDeque<usize>> = HashMap::new();
+    for (idx, record) in diff.iter().enumerate() {
+        diff_by_key
+            .entry(field(record, on)?)
+            .or_default()
+            .push_back(idx);
+    }
+
+    let mut matched = vec![false; diff.len()];
+    let mut merged = Vec::with_capacity(orig.len().max(diff.len()));
+    for record in orig {
+        let Some(idx) = diff_by_key
+            .get_mut(field(record, on)?)
+            .and_then(VecDeque::pop_front)
+        else {
+            merged.push(record);
+            continue;
+        };
+        matched[idx] = true;
+        let other = &diff[idx];
+        if record == other {
+            merged.push(record);
+            continue;
+        }
+        match strategy {
+            MergeStrategy::Union => merged.extend([record, other]),
+            MergeStrategy::PreferOrig => merged.push(record),
+            MergeStrategy::PreferDiff => merged.push(other),
+        }
+    }
+    merged.extend(
+        diff.iter()
+            .zip(matched)
+            .filter(|(_, matched)| !matched)
+            .map(|(record, _)| record),
+    );
+    Ok(merged)
+}
+
+pub fn run(args: &MergeArgs) -> Result<()> {
+    let orig = read_table(&args.orig, &args.reader)?;
+    let diff = read_table(&args.diff, &args.reader)?;
+    let merged = merge_records(&orig.records, &diff.records, args.on, args.strategy)?;
+
+    let output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut writer = Writer::from_writer(output);
+    if let Some(headers) = &orig.headers {
+        writer.write_record(headers)?;
+    }
+    for record in merged {
+        writer.write_record(record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(rows: &[&[&str]]) -> Vec<StringRecord> {
+        rows.iter()
+            .map(|row| StringRecord::from(row.to_vec()))
+            .collect()
+    }
+
+    fn merged(strategy: MergeStrategy) -> Vec<Vec<String>> {
+        let orig = records(&[&["1", "a"], &["2", "b"], &["3", "c"]]);
+        let diff = records(&[&["2", "B"], &["1", "a"], &["4", "d"]]);
+        merge_records(&orig, &diff, 1, strategy)
+            .unwrap()
+            .into_iter()
+            .map(|record| record.iter().map(str::to_string).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_records() {
+        assert_eq!(
+            merged(MergeStrategy::Union),
+            [["1", "a"], ["2", "b"], ["2", "B"], ["3", "c"], ["4", "d"]]
+        );
+        assert_eq!(
+            merged(MergeStrategy::PreferOrig),
+            [["1", "a"], ["2", "b"], ["3", "c"], ["4", "d"]]
+        );
+        assert_eq!(
+            merged(MergeStrategy::PreferDiff),
+            [["1", "a"], ["2", "B"], ["3", "c"], ["4", "d"]]
+        );
+    }
+}
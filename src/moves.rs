@@ -0,0 +1,71 @@
+use std::collections::{HashMap, VecDeque};
+
+use similar::{ChangeTag, DiffOp};
+
+/// Removed values that reappear as added values elsewhere, by their 0-based
+/// positions on both sides. Each removal pairs with the first later-listed
+/// addition of the same value, in order of appearance.
+#[derive(Debug, Default, PartialEq)]
+pub struct Moves {
+    to_diff: HashMap<usize, usize>,
+    from_orig: HashMap<usize, usize>,
+}
+
+impl Moves {
+    pub fn detect<'a>(
+        ops: impl IntoIterator<Item = &'a DiffOp>,
+        orig: &[&str],
+        diff: &[&str],
+    ) -> Self {
+        let mut removed: HashMap<&str, VecDeque<usize>> = HashMap::new();
+        let mut added = Vec::new();
+        for op in ops {
+            for change in op.iter_changes(orig, diff) {
+                match (change.tag(), change.old_index(), change.new_index()) {
+                    (ChangeTag::Delete, Some(index), _) => {
+                        removed.entry(change.value()).or_default().push_back(index);
+                    }
+                    (ChangeTag::Insert, _, Some(index)) => added.push((change.value(), index)),
+                    _ => {}
+                }
+            }
+        }
+
+        let mut moves = Self::default();
+        for (value, new_index) in added {
+            if let Some(old_index) = removed.get_mut(value).and_then(VecDeque::pop_front) {
+                moves.to_diff.insert(old_index, new_index);
+                moves.from_orig.insert(new_index, old_index);
+            }
+        }
+        moves
+    }
+
+    /// Diff position the orig value at `index` moved to.
+    pub fn moved_to(&self, index: Option<usize>) -> Option<usize> {
+        index.and_then(|index| self.to_diff.get(&index).copied())
+    }
+
+    /// Whether the diff value at `index` was moved from elsewhere.
+    pub fn is_moved_here(&self, index: Option<usize>) -> bool {
+        index.is_some_and(|index| self.from_orig.contains_key(&index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar::{Algorithm, capture_diff_slices};
+
+    #[test]
+    fn test_detect() {
+        let orig = ["a", "b", "c", "d"];
+        let diff = ["b", "c", "a", "d", "e"];
+        let ops = capture_diff_slices(Algorithm::Myers, &orig, &diff);
+        let moves = Moves::detect(&ops, &orig, &diff);
+        assert_eq!(moves.moved_to(Some(0)), Some(2));
+        assert!(moves.is_moved_here(Some(2)));
+        assert!(!moves.is_moved_here(Some(4)));
+        assert_eq!(moves.moved_to(Some(3)), None);
+    }
+}
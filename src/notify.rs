@@ -0,0 +1,108 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::{Result, anyhow};
+
+use crate::cli::CompareArgs;
+use crate::counts::{Counts, diff_counts};
+use crate::json::Value;
+use crate::provenance::Provenance;
+use crate::redact::Masking;
+use crate::report;
+
+/// Keys of the comparison document sent without `--notify-full`.
+const SUMMARY_KEYS: &[&str] = &["orig", "diff", "identical", "counts", "inputs"];
+
+const NULL_DEVICE: &str = if cfg!(windows) { "NUL" } else { "/dev/null" };
+
+fn exceeds(counts: &Counts, threshold: usize) -> bool {
+    counts.removed + counts.added + counts.changed > threshold
+}
+
+fn summary(document: Value) -> Value {
+    match document {
+        Value::Object(entries) => Value::Object(
+            entries
+                .into_iter()
+                .filter(|(key, _)| SUMMARY_KEYS.contains(&key.as_str()))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// POSTs `payload` as JSON to `url` with curl.
+fn post(url: &str, payload: &Value) -> Result<()> {
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--request", "POST"])
+        .args(["--header", "Content-Type: application/json"])
+        .args(["--data-binary", "@-", "--output", NULL_DEVICE, "--", url])
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|err| anyhow!("Failed to run curl for --notify-url: {err}"))?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(payload.to_string().as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow!("Notifying {url} failed: curl exited with {status}"));
+    }
+    Ok(())
+}
+
+/// Sends the comparison summary, or the full result with `--notify-full`, to
+/// `--notify-url` when more than `--notify-threshold` values differ.
+pub fn send(
+    args: &CompareArgs,
+    url: &str,
+    labels: (&str, &str),
+    (orig_lines, diff_lines): (&[String], &[String]),
+) -> Result<()> {
+    if !exceeds(
+        &diff_counts(orig_lines, diff_lines, args.chunk_rows),
+        args.notify_threshold,
+    ) {
+        return Ok(());
+    }
+    let document = report::comparison(
+        labels,
+        (orig_lines, diff_lines),
+        &Masking::new(args),
+        args.chunk_rows,
+        &Provenance::new(args)?,
+    );
+    let payload = if args.notify_full {
+        document
+    } else {
+        summary(document)
+    };
+    post(url, &payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let counts = Counts {
+            removed: 1,
+            added: 0,
+            changed: 1,
+        };
+        assert!(exceeds(&counts, 1));
+        assert!(!exceeds(&counts, 2));
+
+        let document = Value::Object(vec![
+            ("orig".to_string(), Value::String("a/x.csv".to_string())),
+            ("hunks".to_string(), Value::Array(vec![])),
+            ("identical".to_string(), Value::Bool(false)),
+        ]);
+        assert_eq!(
+            summary(document).to_string(),
+            r#"{"orig":"a/x.csv","identical":false}"#
+        );
+    }
+}
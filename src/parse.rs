@@ -0,0 +1,968 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::iter;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use csv::{ByteRecord, Reader, ReaderBuilder, StringRecord, Terminator};
+
+use crate::cache::ColumnCache;
+use crate::cli::{Inputs, ParseArgs, ReaderArgs};
+use crate::errors::Category;
+use crate::fixed_width;
+use crate::logging;
+use crate::spill::Spill;
+use crate::tokenize::{Split, Tokenized};
+use crate::transform::transform_values;
+
+const DELIM: &str = "/";
+
+fn filter_prefix(orig: &str) -> String {
+    orig.split_once(DELIM).unwrap_or(("", "")).1.to_string()
+}
+
+pub fn normalize_bool(value: String) -> String {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" | "y" => "true".to_string(),
+        "false" | "0" | "no" | "n" => "false".to_string(),
+        _ => value,
+    }
+}
+
+/// Rounds numeric values to `decimals` places and keeps other values as is.
+fn round_number(value: String, decimals: usize) -> String {
+    match value.trim().parse::<f64>() {
+        Ok(number) if number.is_finite() => {
+            let rounded = format!("{number:.decimals$}");
+            match rounded.strip_prefix('-') {
+                Some(positive) if positive.parse::<f64>() == Ok(0.0) => positive.to_string(),
+                _ => rounded,
+            }
+        }
+        _ => value,
+    }
+}
+
+/// Applies the `--with-prefix` filter and value normalizations of `args` to a
+/// field, returning `None` for fields that are filtered out.
+pub fn normalize_field(field: &str, args: &ParseArgs) -> Option<String> {
+    let mut value = match args.with_prefix.as_deref() {
+        Some(with_prefix) if field.starts_with(with_prefix) => filter_prefix(field),
+        Some(_) => return None,
+        None => field.to_string(),
+    };
+    if args.normalize_bools {
+        value = normalize_bool(value);
+    }
+    if let Some(decimals) = args.round {
+        value = round_number(value, decimals);
+    }
+    Some(value)
+}
+
+/// Minimum number of fields before normalization is spread across threads.
+const PARALLEL_THRESHOLD: usize = 100_000;
+
+fn normalize_fields(fields: &[String], args: &ParseArgs) -> Vec<String> {
+    let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    if threads == 1 || fields.len() < PARALLEL_THRESHOLD {
+        return fields
+            .iter()
+            .filter_map(|field| normalize_field(field, args))
+            .collect();
+    }
+
+    let chunk_size = fields.len().div_ceil(threads);
+    thread::scope(|scope| {
+        let handles: Vec<_> = fields
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .filter_map(|field| normalize_field(field, args))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+/// How lines split into fields when the CSV reader cannot split them itself:
+/// by `--fixed-width` widths or a multi-byte `--delimiter`.
+fn split(args: &ReaderArgs) -> Result<Option<Split>> {
+    if args.fixed_width == Some(Inputs::Both) {
+        return Ok(Some(Split::Widths(fixed_width::widths(args)?)));
+    }
+    Ok(args
+        .delimiter
+        .as_deref()
+        .filter(|delimiter| delimiter.len() > 1)
+        .map(|delimiter| Split::Separator(delimiter.as_bytes().to_vec())))
+}
+
+/// The field delimiter of `--delimiter` when the CSV reader handles it, a
+/// single byte.
+pub fn csv_delimiter(args: &ReaderArgs) -> Option<u8> {
+    match args.delimiter.as_deref().map(str::as_bytes) {
+        None => Some(b','),
+        Some(&[byte]) => Some(byte),
+        Some(_) => None,
+    }
+}
+
+fn reader_builder(args: &ReaderArgs) -> ReaderBuilder {
+    let tokenized = args.fixed_width == Some(Inputs::Both) || csv_delimiter(args).is_none();
+    let mut builder = ReaderBuilder::new();
+    builder
+        .has_headers(args.with_headers)
+        .flexible(args.missing_as_empty)
+        .quoting(!args.raw || tokenized)
+        .delimiter(csv_delimiter(args).unwrap_or(b','))
+        .terminator(match args.terminator {
+            Some(terminator) if !tokenized => terminator,
+            _ => Terminator::CRLF,
+        });
+    builder
+}
+
+/// Consumes the first `lines` lines of `reader`, for `--skip-rows`.
+fn skip_lines(reader: &mut impl BufRead, lines: usize) -> io::Result<()> {
+    let mut line = Vec::new();
+    for _ in 0..lines {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// `input` past the `--skip-rows` lines, as CSV.
+fn csv_input<'a>(mut input: impl BufRead + 'a, args: &ReaderArgs) -> Result<Box<dyn Read + 'a>> {
+    skip_lines(&mut input, args.skip_rows)?;
+    match split(args)? {
+        Some(split) => Ok(Box::new(Tokenized::new(
+            input,
+            split,
+            csv_delimiter(args).unwrap_or(b','),
+        ))),
+        None => Ok(Box::new(input)),
+    }
+}
+
+fn csv_reader(path: impl AsRef<Path>, args: &ReaderArgs) -> Result<Reader<Box<dyn Read>>> {
+    let input = csv_input(BufReader::new(File::open(path)?), args)?;
+    Ok(reader_builder(args).from_reader(input))
+}
+
+/// `record` with its line number counted from the start of the file, before
+/// the lines `--skip-rows` skipped.
+fn unskipped(mut record: StringRecord, args: &ReaderArgs) -> StringRecord {
+    if let Some(mut position) = record.position().cloned() {
+        position.set_line(position.line() + args.skip_rows as u64);
+        record.set_position(Some(position));
+    }
+    record
+}
+
+/// Escapes bytes that are not valid UTF-8 as `\xNN`, keeping distinct invalid
+/// sequences distinct.
+fn escape_bytes(field: &[u8]) -> String {
+    let mut res = String::with_capacity(field.len());
+    for chunk in field.utf8_chunks() {
+        res.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            res.push_str(&format!("\\x{byte:02X}"));
+        }
+    }
+    res
+}
+
+fn decode_record(record: &ByteRecord, args: &ReaderArgs) -> StringRecord {
+    let mut decoded: StringRecord = record
+        .iter()
+        .map(|field| {
+            if args.bytes {
+                Cow::Owned(escape_bytes(field))
+            } else {
+                String::from_utf8_lossy(field)
+            }
+        })
+        .collect::<Vec<_>>()
+        .iter()
+        .map(Cow::as_ref)
+        .collect();
+    decoded.set_position(record.position().cloned());
+    decoded
+}
+
+/// `records` without the last `count`, for `--skip-last`. Errors pass through
+/// right away.
+fn without_last<'a>(
+    mut records: impl Iterator<Item = Result<StringRecord>> + 'a,
+    count: usize,
+) -> Box<dyn Iterator<Item = Result<StringRecord>> + 'a> {
+    if count == 0 {
+        return Box::new(records);
+    }
+    let mut pending = VecDeque::with_capacity(count + 1);
+    Box::new(iter::from_fn(move || {
+        loop {
+            match records.next()? {
+                Ok(record) => pending.push_back(record),
+                Err(err) => return Some(Err(err)),
+            }
+            if pending.len() > count {
+                return pending.pop_front().map(Ok);
+            }
+        }
+    }))
+}
+
+/// Records of `reader`, decoded as `--lossy-utf8` or `--bytes` ask for and
+/// filtered by `--where`.
+fn read_records<'a, R: Read>(
+    reader: &'a mut Reader<R>,
+    args: &'a ReaderArgs,
+) -> Result<Box<dyn Iterator<Item = Result<StringRecord>> + 'a>> {
+    let headers = match &args.filter {
+        Some(_) if args.with_headers => Some(clean_record(read_headers(reader, args)?, args)),
+        _ => None,
+    };
+    let records: Box<dyn Iterator<Item = Result<StringRecord>>> = if args.lossy_utf8 || args.bytes {
+        Box::new(
+            reader
+                .byte_records()
+                .map(|record| Ok(decode_record(&record?, args))),
+        )
+    } else {
+        Box::new(reader.records().map(|record| Ok(record?)))
+    };
+    let records = without_last(
+        records.map(|record| record.map(|record| unskipped(record, args))),
+        args.skip_last,
+    );
+    let Some(filter) = &args.filter else {
+        return Ok(records);
+    };
+    Ok(Box::new(records.filter_map(move |record| {
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => return Some(Err(err)),
+        };
+        match filter.matches(&clean_record(record.clone(), args), headers.as_ref()) {
+            Ok(true) => Some(Ok(record)),
+            Ok(false) => None,
+            Err(err) => Some(Err(err)),
+        }
+    })))
+}
+
+fn read_headers<R: Read>(reader: &mut Reader<R>, args: &ReaderArgs) -> Result<StringRecord> {
+    if args.lossy_utf8 || args.bytes {
+        Ok(decode_record(reader.byte_headers()?, args))
+    } else {
+        Ok(reader.headers()?.clone())
+    }
+}
+
+fn strip_quotes(field: &str) -> &str {
+    field
+        .strip_prefix('"')
+        .and_then(|field| field.strip_suffix('"'))
+        .unwrap_or(field)
+}
+
+fn normalize_newlines(field: &str) -> Cow<'_, str> {
+    if field.contains('\r') {
+        Cow::Owned(field.replace("\r\n", "\n").replace('\r', "\n"))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+fn clean_field<'a>(field: &'a str, args: &ReaderArgs) -> Cow<'a, str> {
+    let field = if args.strip_quotes {
+        strip_quotes(field)
+    } else {
+        field
+    };
+    if args.normalize_newlines {
+        normalize_newlines(field)
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+fn clean_record(record: StringRecord, args: &ReaderArgs) -> StringRecord {
+    if args.strip_quotes || args.normalize_newlines {
+        record
+            .iter()
+            .map(|field| clean_field(field, args))
+            .collect::<Vec<_>>()
+            .iter()
+            .map(Cow::as_ref)
+            .collect()
+    } else {
+        record
+    }
+}
+
+fn needs_normalization(args: &ParseArgs) -> bool {
+    args.with_prefix.is_some() || args.normalize_bools || args.round.is_some()
+}
+
+/// Rows of a column dropped or altered while parsing, which would otherwise
+/// go unnoticed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParseWarnings {
+    /// Records passing `--where`
+    pub records: usize,
+    /// Records too short to hold the column
+    pub short: usize,
+    /// Records dropped by `--where` or `--with-prefix`
+    pub filtered: usize,
+    /// Empty values kept
+    pub empty: usize,
+    /// Values with invalid UTF-8 replaced by `--lossy-utf8`
+    pub replaced: usize,
+}
+
+impl ParseWarnings {
+    pub fn is_empty(&self) -> bool {
+        self.short + self.filtered + self.empty + self.replaced == 0
+    }
+
+    /// Accounts for the values `normalize_fields` kept of `fields` of them.
+    fn normalized(&mut self, fields: usize, values: &[String]) {
+        self.filtered += fields - values.len();
+        self.empty = values.iter().filter(|value| value.is_empty()).count();
+    }
+
+    /// Counts a cleaned field about to be kept.
+    fn see(&mut self, field: &str, args: &ReaderArgs) {
+        if args.lossy_utf8 && field.contains(char::REPLACEMENT_CHARACTER) {
+            self.replaced += 1;
+        }
+    }
+}
+
+static WARNINGS: Mutex<Vec<(PathBuf, ParseWarnings)>> = Mutex::new(Vec::new());
+
+/// Takes the warnings of the files parsed so far, in parsing order.
+pub fn take_warnings() -> Vec<(PathBuf, ParseWarnings)> {
+    std::mem::take(&mut *WARNINGS.lock().unwrap_or_else(PoisonError::into_inner))
+}
+
+static DISPLAY_NAMES: Mutex<Vec<(PathBuf, String)>> = Mutex::new(Vec::new());
+
+/// Shows the temporary file `path` as `name` in messages, until
+/// `forget_display_name`. Used for inputs like `file.csv@REV`.
+pub fn set_display_name(path: &Path, name: String) {
+    DISPLAY_NAMES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push((path.to_path_buf(), name));
+}
+
+pub fn forget_display_name(path: &Path) {
+    DISPLAY_NAMES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .retain(|(named, _)| named != path);
+}
+
+/// How messages show the input `path`: by its display name if it has one.
+pub fn display_name(path: &Path) -> String {
+    DISPLAY_NAMES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .find(|(named, _)| named == path)
+        .map_or_else(|| path.display().to_string(), |(_, name)| name.clone())
+}
+
+pub fn parse_csv(path: &Path, index: usize, args: &ParseArgs) -> Result<Vec<String>> {
+    if let Some(budget) = args.max_memory
+        && needs_normalization(args)
+    {
+        return parse_csv_spilled(path, index, args, budget);
+    }
+    let start = Instant::now();
+    let mut reader = csv_reader(path, &args.reader)?;
+    let (mut warnings, values) = column_values(&mut reader, index, args)
+        .with_context(|| format!("reading {}", display_name(path)))?;
+    let fields = values.len();
+    let values = if needs_normalization(args) {
+        normalize_fields(&values, args)
+    } else {
+        values
+    };
+    warnings.normalized(fields, &values);
+    log_parsed(path, index, (&warnings, values.len()), start);
+    Ok(values)
+}
+
+/// Records that `--where` dropped, given the number passing it.
+fn dropped_by_filter<R: Read>(reader: &Reader<R>, args: &ReaderArgs, records: usize) -> usize {
+    let header = usize::from(args.with_headers);
+    let read = usize::try_from(reader.position().record()).unwrap_or(usize::MAX);
+    read.saturating_sub(header + records + args.skip_last)
+}
+
+/// Field at the 1-based `index` of `record`, or none for records too short
+/// to hold it, which are an error with `--strict` and empty with
+/// `--missing-as-empty`.
+fn column_field<'a>(
+    record: &'a StringRecord,
+    index: usize,
+    args: &ParseArgs,
+) -> Result<Option<&'a str>> {
+    match record.get(index - 1) {
+        None if args.strict => field(record, index).map(Some),
+        None if args.reader.missing_as_empty => Ok(Some("")),
+        field => Ok(field),
+    }
+}
+
+/// Warnings while reading, and the cleaned fields at the 1-based `index` of
+/// the records that have one.
+fn column_values<R: Read>(
+    reader: &mut Reader<R>,
+    index: usize,
+    args: &ParseArgs,
+) -> Result<(ParseWarnings, Vec<String>)> {
+    let mut fields = Vec::new();
+    let mut warnings = ParseWarnings::default();
+    for record in read_records(reader, &args.reader)? {
+        warnings.records += 1;
+        let record = record?;
+        match column_field(&record, index, args)? {
+            Some(field) => {
+                let field = clean_field(field, &args.reader);
+                warnings.see(&field, &args.reader);
+                fields.push(field.into_owned());
+            }
+            None => warnings.short += 1,
+        }
+    }
+    warnings.filtered = dropped_by_filter(reader, &args.reader, warnings.records);
+    Ok((warnings, fields))
+}
+
+/// Like `parse_csv`, but for CSV data in memory, as for the library API.
+pub fn parse_bytes(data: &[u8], index: usize, args: &ParseArgs) -> Result<Vec<String>> {
+    let mut reader = reader_builder(&args.reader).from_reader(csv_input(data, &args.reader)?);
+    let (_, values) = column_values(&mut reader, index, args)?;
+    if !needs_normalization(args) {
+        return Ok(values);
+    }
+    Ok(normalize_fields(&values, args))
+}
+
+/// Logs the `parsed` event for a column: records read, records too short to
+/// hold the column and values kept after normalization. Also keeps the
+/// warnings for `take_warnings`.
+fn log_parsed(
+    path: &Path,
+    index: usize,
+    (warnings, kept): (&ParseWarnings, usize),
+    start: Instant,
+) {
+    if !warnings.is_empty() {
+        WARNINGS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push((path.to_path_buf(), warnings.clone()));
+    }
+    if !logging::enabled() {
+        return;
+    }
+    let fields = [
+        ("file", display_name(path)),
+        ("index", index.to_string()),
+        ("records", warnings.records.to_string()),
+        ("values", kept.to_string()),
+        ("elapsed_ms", start.elapsed().as_millis().to_string()),
+    ];
+    logging::info("parsed", &fields);
+    if warnings.short > 0 {
+        logging::warn(
+            "short-records",
+            &[
+                ("file", display_name(path)),
+                ("index", index.to_string()),
+                ("skipped", warnings.short.to_string()),
+            ],
+        );
+    }
+    if warnings.filtered > 0 {
+        logging::info(
+            "filtered",
+            &[
+                ("file", display_name(path)),
+                ("skipped", warnings.filtered.to_string()),
+            ],
+        );
+    }
+}
+
+/// Like `parse_csv`, but keeps the raw fields awaiting normalization within
+/// `budget` bytes of memory, spilling the rest to temporary files.
+fn parse_csv_spilled(
+    path: &Path,
+    index: usize,
+    args: &ParseArgs,
+    budget: usize,
+) -> Result<Vec<String>> {
+    let start = Instant::now();
+    let mut reader = csv_reader(path, &args.reader)?;
+    let mut spill = Spill::new(budget);
+    let mut warnings = ParseWarnings::default();
+    for record in read_records(&mut reader, &args.reader)? {
+        warnings.records += 1;
+        let record = record?;
+        match column_field(&record, index, args)
+            .with_context(|| format!("reading {}", display_name(path)))?
+        {
+            Some(field) => {
+                let field = clean_field(field, &args.reader);
+                warnings.see(&field, &args.reader);
+                spill.push(field.into_owned())?;
+            }
+            None => warnings.short += 1,
+        }
+    }
+    warnings.filtered = dropped_by_filter(&reader, &args.reader, warnings.records);
+    let fields = warnings.records - warnings.short;
+    let mut values = Vec::new();
+    for field in spill.into_iter()? {
+        values.extend(normalize_field(&field?, args));
+    }
+    warnings.normalized(fields, &values);
+    log_parsed(path, index, (&warnings, values.len()), start);
+    Ok(values)
+}
+
+/// Passes the normalized values of a column to `f` as they are read, without
+/// collecting them.
+pub fn for_each_value(
+    path: &Path,
+    index: usize,
+    args: &ParseArgs,
+    mut f: impl FnMut(String) -> Result<()>,
+) -> Result<()> {
+    let mut reader = csv_reader(path, &args.reader)?;
+    for record in read_records(&mut reader, &args.reader)? {
+        let record = record?;
+        let context = || format!("reading {}", display_name(path));
+        let Some(field) = column_field(&record, index, args).with_context(context)? else {
+            continue;
+        };
+        if let Some(value) = normalize_field(&clean_field(field, &args.reader), args) {
+            f(value)?;
+        }
+    }
+    Ok(())
+}
+
+/// Source records of the values `parse_csv` extracts from `path`, in the
+/// same order.
+pub fn source_records(path: &Path, index: usize, args: &ParseArgs) -> Result<Vec<StringRecord>> {
+    let mut reader = csv_reader(path, &args.reader)?;
+    let mut records = Vec::new();
+    for record in read_records(&mut reader, &args.reader)? {
+        let record = clean_record(record?, &args.reader);
+        let Some(field) = column_field(&record, index, args)? else {
+            continue;
+        };
+        if args
+            .with_prefix
+            .as_deref()
+            .is_none_or(|with_prefix| field.starts_with(with_prefix))
+        {
+            records.push(record);
+        }
+    }
+    Ok(records)
+}
+
+/// Full records of a CSV file, together with its header row when present.
+pub struct Table {
+    pub headers: Option<StringRecord>,
+    pub records: Vec<StringRecord>,
+}
+
+/// Whether two header names refer to the same column: exactly with
+/// `strict`, else ignoring case, spaces and underscores, so that `Order ID`
+/// matches `order_id`.
+pub fn same_header(a: &str, b: &str, strict: bool) -> bool {
+    if strict {
+        return a == b;
+    }
+    let key = |name: &str| -> String {
+        name.chars()
+            .filter(|c| !c.is_whitespace() && *c != '_')
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+    a == b || key(a) == key(b)
+}
+
+/// Field at the 1-based `index` of `record`.
+pub fn field(record: &StringRecord, index: usize) -> Result<&str> {
+    record.get(index - 1).ok_or_else(|| {
+        Category::BadIndex.error(format!(
+            "Record at line {} has no column {index}",
+            record.position().map_or(0, |pos| pos.line())
+        ))
+    })
+}
+
+pub fn read_table(path: &Path, args: &ReaderArgs) -> Result<Table> {
+    sample_table(path, args, usize::MAX)
+}
+
+/// Like `read_table`, but reads at most `limit` records.
+pub fn sample_table(path: &Path, args: &ReaderArgs, limit: usize) -> Result<Table> {
+    let mut reader = csv_reader(path, args)?;
+    let headers = if args.with_headers {
+        Some(clean_record(read_headers(&mut reader, args)?, args))
+    } else {
+        None
+    };
+    let records = read_records(&mut reader, args)?
+        .take(limit)
+        .map(|record| Ok(clean_record(record?, args)))
+        .collect::<Result<_>>()?;
+    Ok(Table { headers, records })
+}
+
+pub fn load_column(args: &ParseArgs, path: &Path, index: usize) -> Result<Vec<String>> {
+    let values = read_column(args, path, index)?;
+    match args.transform_cmd.as_deref() {
+        Some(cmd) => transform_values(cmd, values),
+        None => Ok(values),
+    }
+}
+
+/// Values of a plain text file with one value per line, normalized like a
+/// column. Blank lines are skipped, as blank CSV records are.
+pub fn load_lines(args: &ParseArgs, path: &Path) -> Result<Vec<String>> {
+    let data = fs::read(path)?;
+    let text = if args.reader.lossy_utf8 {
+        String::from_utf8_lossy(&data)
+    } else {
+        Cow::Borrowed(std::str::from_utf8(&data).map_err(|err| {
+            Category::Parse.error(format!("{} is not valid UTF-8: {err}", display_name(path)))
+        })?)
+    };
+    let values: Vec<String> = text
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+    let values = if needs_normalization(args) {
+        normalize_fields(&values, args)
+    } else {
+        values
+    };
+    match args.transform_cmd.as_deref() {
+        Some(cmd) => transform_values(cmd, values),
+        None => Ok(values),
+    }
+}
+
+fn read_column(args: &ParseArgs, path: &Path, index: usize) -> Result<Vec<String>> {
+    let parse = || parse_csv(path, index, args);
+
+    let Some(cache_dir) = args.cache_dir.as_deref() else {
+        return parse();
+    };
+    let options = [
+        index.to_string(),
+        format!("{:?}", args.with_prefix),
+        args.reader.with_headers.to_string(),
+        args.reader.raw.to_string(),
+        args.reader.strip_quotes.to_string(),
+        format!("{:?}", args.reader.terminator),
+        args.reader.normalize_newlines.to_string(),
+        args.reader.lossy_utf8.to_string(),
+        args.reader.bytes.to_string(),
+        format!("{:?}", args.reader.filter),
+        args.normalize_bools.to_string(),
+        format!("{:?}", args.round),
+        args.strict.to_string(),
+        args.reader.missing_as_empty.to_string(),
+        args.reader.skip_rows.to_string(),
+        args.reader.skip_last.to_string(),
+        format!("{:?}", args.reader.fixed_width),
+        format!("{:?}", args.reader.delimiter),
+        format!("{:?}", fixed_width::widths(&args.reader)?),
+    ];
+    let cache = ColumnCache::new(cache_dir, path, &options)?;
+    if let Some(values) = cache.load()? {
+        logging::info("cache-hit", &[("file", path.display().to_string())]);
+        return Ok(values);
+    }
+    let values = parse()?;
+    cache.store(&values)?;
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::Expr;
+    use assert_fs::prelude::{FileWriteBin, FileWriteStr};
+
+    #[test]
+    fn test_parse_csv() {
+        let file = assert_fs::NamedTempFile::new("input.csv").unwrap();
+        file.write_str("1,2,some some/some/path,4\n1,2,some some/some/other/path,4")
+            .unwrap();
+        let path = file.path();
+        let mut args = ParseArgs::default();
+        let output = parse_csv(path, 3, &args).unwrap();
+        assert_eq!(output.len(), 2);
+        assert_eq!(output[0], "some some/some/path");
+        assert_eq!(output[1], "some some/some/other/path");
+
+        args.reader.with_headers = true;
+        let output = parse_csv(path, 3, &args).unwrap();
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0], "some some/some/other/path");
+
+        let file = assert_fs::NamedTempFile::new("input.csv").unwrap();
+        file.write_str("1,2,some some/other/path,4\n1,2,ayy some/ayy/other/path,4")
+            .unwrap();
+
+        let args = ParseArgs {
+            with_prefix: Some("some".to_string()),
+            ..Default::default()
+        };
+        let output = parse_csv(file.path(), 3, &args).unwrap();
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0], "other/path");
+
+        let file = assert_fs::NamedTempFile::new("input.csv").unwrap();
+        file.write_str("\"a \"\"b\"\"\",x\n\"\"\"c\"\"\",y")
+            .unwrap();
+        let path = file.path();
+        let mut args = ParseArgs::default();
+        assert_eq!(parse_csv(path, 1, &args).unwrap(), ["a \"b\"", "\"c\""]);
+        args.reader.raw = true;
+        assert_eq!(
+            parse_csv(path, 1, &args).unwrap(),
+            ["\"a \"\"b\"\"\"", "\"\"\"c\"\"\""]
+        );
+        args.reader.raw = false;
+        args.reader.strip_quotes = true;
+        assert_eq!(parse_csv(path, 1, &args).unwrap(), ["a \"b\"", "c"]);
+
+        let file = assert_fs::NamedTempFile::new("input.csv").unwrap();
+        file.write_str("a,\"x\r\ny\"\r\nb,z\r\n").unwrap();
+        let path = file.path();
+        let mut args = ParseArgs::default();
+        assert_eq!(parse_csv(path, 2, &args).unwrap(), ["x\r\ny", "z"]);
+        args.reader.normalize_newlines = true;
+        assert_eq!(parse_csv(path, 2, &args).unwrap(), ["x\ny", "z"]);
+        args.reader.terminator = Some(Terminator::Any(b'\n'));
+        args.reader.normalize_newlines = false;
+        assert_eq!(parse_csv(path, 2, &args).unwrap(), ["x\r\ny\r", "z\r"]);
+
+        let args = ParseArgs {
+            normalize_bools: true,
+            max_memory: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(parse_csv(path, 1, &args).unwrap(), ["a", "b"]);
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let file = assert_fs::NamedTempFile::new("input.csv").unwrap();
+        file.write_binary(b"1,caf\xe9\n2,ok\n").unwrap();
+        let mut args = ParseArgs::default();
+        assert!(parse_csv(file.path(), 2, &args).is_err());
+        args.reader.lossy_utf8 = true;
+        assert_eq!(
+            parse_csv(file.path(), 2, &args).unwrap(),
+            ["caf\u{FFFD}", "ok"]
+        );
+        args.reader.lossy_utf8 = false;
+        args.reader.bytes = true;
+        assert_eq!(
+            parse_csv(file.path(), 2, &args).unwrap(),
+            ["caf\\xE9", "ok"]
+        );
+        let lines: Vec<u64> = source_records(file.path(), 2, &args)
+            .unwrap()
+            .iter()
+            .map(|record| record.position().unwrap().line())
+            .collect();
+        assert_eq!(lines, [1, 2]);
+    }
+
+    #[test]
+    fn test_source_records() {
+        let file = assert_fs::NamedTempFile::new("input.csv").unwrap();
+        file.write_str("a,some/1\nb,other/2\nc,\"some/\n3\"\nd,some/4")
+            .unwrap();
+        let args = ParseArgs {
+            with_prefix: Some("some".to_string()),
+            ..Default::default()
+        };
+        let lines: Vec<u64> = source_records(file.path(), 2, &args)
+            .unwrap()
+            .iter()
+            .map(|record| record.position().unwrap().line())
+            .collect();
+        assert_eq!(lines, [1, 3, 5]);
+    }
+
+    #[test]
+    fn test_skip_rows() {
+        let file = assert_fs::NamedTempFile::new("input.csv").unwrap();
+        file.write_str("Export of 2024-01-31, \"all regions\"\n\nid,name\n1,a\n2,b\n")
+            .unwrap();
+        let mut args = ParseArgs::default();
+        args.reader.skip_rows = 2;
+        args.reader.with_headers = true;
+        assert_eq!(parse_csv(file.path(), 2, &args).unwrap(), ["a", "b"]);
+        let lines: Vec<u64> = source_records(file.path(), 2, &args)
+            .unwrap()
+            .iter()
+            .map(|record| record.position().unwrap().line())
+            .collect();
+        assert_eq!(lines, [4, 5]);
+        assert!(parse_bytes(b"banner\n1,a\n", 2, &ParseArgs::default()).is_err());
+        args.reader.with_headers = false;
+        args.reader.skip_rows = 1;
+        assert_eq!(parse_bytes(b"banner\n1,a\n", 2, &args).unwrap(), ["a"]);
+
+        args.reader.skip_rows = 0;
+        args.reader.skip_last = 1;
+        let data = b"1,a\n2,b\nTOTAL,2\n";
+        assert_eq!(parse_bytes(data, 2, &args).unwrap(), ["a", "b"]);
+        let mut reader = reader_builder(&args.reader).from_reader(&data[..]);
+        let (warnings, _) = column_values(&mut reader, 2, &args).unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_column_values_warnings() {
+        let mut args = ParseArgs::default();
+        args.reader.with_headers = true;
+        args.reader.lossy_utf8 = true;
+        args.reader.filter = Some(Expr::parse("$1 != 'skip'").unwrap());
+        let data = b"a,b\nskip,1\nkeep,\nkeep,\xff\n";
+        let mut reader = reader_builder(&args.reader).from_reader(&data[..]);
+        let (mut warnings, fields) = column_values(&mut reader, 2, &args).unwrap();
+        warnings.normalized(fields.len(), &fields);
+        assert_eq!(
+            warnings,
+            ParseWarnings {
+                records: 2,
+                short: 0,
+                filtered: 1,
+                empty: 1,
+                replaced: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_short_records() {
+        let mut args = ParseArgs::default();
+        let mut reader = reader_builder(&args.reader).from_reader(&b"a\nb\n"[..]);
+        let (warnings, _) = column_values(&mut reader, 2, &args).unwrap();
+        assert_eq!(warnings.short, 2);
+
+        args.strict = true;
+        let mut reader = reader_builder(&args.reader).from_reader(&b"a\nb\n"[..]);
+        let err = column_values(&mut reader, 2, &args).unwrap_err();
+        assert_eq!(err.to_string(), "Record at line 1 has no column 2");
+
+        args.strict = false;
+        args.reader.missing_as_empty = true;
+        let mut reader = reader_builder(&args.reader).from_reader(&b"a,1\nb\nc,3\n"[..]);
+        let (warnings, fields) = column_values(&mut reader, 2, &args).unwrap();
+        assert_eq!(
+            (warnings.short, fields),
+            (0, vec!["1".into(), "".into(), "3".into()])
+        );
+    }
+
+    #[test]
+    fn test_load_lines() {
+        let file = assert_fs::NamedTempFile::new("allowlist.txt").unwrap();
+        file.write_str("some/eu\r\n\nsome/us\nother/apac\n")
+            .unwrap();
+        let args = ParseArgs {
+            with_prefix: Some("some".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(load_lines(&args, file.path()).unwrap(), ["eu", "us"]);
+    }
+
+    #[test]
+    fn test_normalize_bool() {
+        assert_eq!(normalize_bool("TRUE".to_string()), "true");
+        assert_eq!(normalize_bool("y".to_string()), "true");
+        assert_eq!(normalize_bool(" 0".to_string()), "false");
+        assert_eq!(normalize_bool("No".to_string()), "false");
+        assert_eq!(normalize_bool("maybe".to_string()), "maybe");
+    }
+
+    #[test]
+    fn test_normalize_fields() {
+        let fields: Vec<String> = (0..PARALLEL_THRESHOLD * 2)
+            .map(|i| format!("{}/{i}", if i % 2 == 0 { "keep" } else { "drop" }))
+            .collect();
+        let args = ParseArgs {
+            with_prefix: Some("keep".to_string()),
+            ..Default::default()
+        };
+        let output = normalize_fields(&fields, &args);
+        assert_eq!(output.len(), PARALLEL_THRESHOLD);
+        assert_eq!(output[0], "0");
+        assert_eq!(output[1], "2");
+        assert_eq!(
+            output.last().unwrap(),
+            &(PARALLEL_THRESHOLD * 2 - 2).to_string()
+        );
+    }
+
+    #[test]
+    fn test_round_number() {
+        assert_eq!(round_number("1.005".to_string(), 1), "1.0");
+        assert_eq!(round_number(" 2.349".to_string(), 2), "2.35");
+        assert_eq!(round_number("7".to_string(), 2), "7.00");
+        assert_eq!(round_number("-0.001".to_string(), 2), "0.00");
+        assert_eq!(round_number("n/a".to_string(), 2), "n/a");
+    }
+
+    #[test]
+    fn test_same_header() {
+        assert!(same_header("Order ID", "order_id", false));
+        assert!(!same_header("Order ID", "order_id", true));
+        assert!(!same_header("order", "order_id", false));
+    }
+
+    #[test]
+    fn test_filter_prefix() {
+        assert_eq!(filter_prefix("a a/1/1/1"), "1/1/1");
+    }
+}
@@ -0,0 +1,156 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+use anyhow::Result;
+
+use crate::cli::CompareArgs;
+use crate::dates::format_timestamp;
+use crate::fingerprint::Fingerprint;
+use crate::json::Value;
+
+/// What a report was produced from and by, so archived reports are
+/// self-describing.
+pub struct Provenance {
+    pub inputs: (Fingerprint, Fingerprint),
+    modified: (Option<SystemTime>, Option<SystemTime>),
+    generated: Option<SystemTime>,
+    hostname: Option<String>,
+    options: Value,
+}
+
+fn hostname() -> Option<String> {
+    ["/proc/sys/kernel/hostname", "/etc/hostname"]
+        .iter()
+        .find_map(|path| fs::read_to_string(path).ok())
+        .or_else(|| env::var("HOSTNAME").ok())
+        .or_else(|| env::var("COMPUTERNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Options given on the command line, as written. The salt is what keeps
+/// hashed values from being guessed, and webhook URLs often hold a token, so
+/// both are masked.
+fn options(args: &CompareArgs) -> Value {
+    Value::Object(
+        args.given
+            .iter()
+            .map(|(name, value)| match name.as_str() {
+                "salt" | "notify-url" => (name.clone(), Value::String("***".to_string())),
+                _ => (name.clone(), value.clone()),
+            })
+            .collect(),
+    )
+}
+
+impl Provenance {
+    /// Provenance of the compared files. `--deterministic` leaves out what
+    /// changes between reruns: the times and the host.
+    pub fn new(args: &CompareArgs) -> Result<Self> {
+        let inputs = (Fingerprint::of(&args.orig)?, Fingerprint::of(&args.diff)?);
+        if args.deterministic {
+            return Ok(Self {
+                inputs,
+                modified: (None, None),
+                generated: None,
+                hostname: None,
+                options: options(args),
+            });
+        }
+        Ok(Self {
+            inputs,
+            modified: (modified(&args.orig), modified(&args.diff)),
+            generated: Some(SystemTime::now()),
+            hostname: hostname(),
+            options: options(args),
+        })
+    }
+
+    /// Provenance of inputs given in memory. There is no clock on
+    /// `wasm32-unknown-unknown`, so the generation time is left out there.
+    pub fn in_memory(args: &CompareArgs, (orig, diff): (&[u8], &[u8])) -> Self {
+        Self {
+            inputs: (Fingerprint::of_bytes(orig), Fingerprint::of_bytes(diff)),
+            modified: (None, None),
+            generated: (!cfg!(all(target_arch = "wasm32", target_os = "unknown")))
+                .then(SystemTime::now),
+            hostname: hostname(),
+            options: options(args),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let text = |value: &str| Value::String(value.to_string());
+        let input = |fingerprint: &Fingerprint, modified: Option<SystemTime>| {
+            Value::Object(vec![
+                ("size".to_string(), Value::Number(fingerprint.size as f64)),
+                ("sha256".to_string(), text(&fingerprint.sha256)),
+                ("modified".to_string(), timestamp(modified)),
+            ])
+        };
+        Value::Object(vec![
+            ("tool".to_string(), text(env!("CARGO_PKG_NAME"))),
+            ("version".to_string(), text(env!("CARGO_PKG_VERSION"))),
+            ("generated".to_string(), timestamp(self.generated)),
+            (
+                "hostname".to_string(),
+                self.hostname.as_deref().map_or(Value::Null, text),
+            ),
+            ("options".to_string(), self.options.clone()),
+            (
+                "inputs".to_string(),
+                Value::Object(vec![
+                    ("orig".to_string(), input(&self.inputs.0, self.modified.0)),
+                    ("diff".to_string(), input(&self.inputs.1, self.modified.1)),
+                ]),
+            ),
+        ])
+    }
+}
+
+fn timestamp(time: Option<SystemTime>) -> Value {
+    time.map_or(Value::Null, |time| Value::String(format_timestamp(time)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Command};
+
+    #[test]
+    fn test_options() {
+        let cli = Cli::parse_given([
+            "csv-compare",
+            "compare",
+            "a.csv",
+            "b.csv@HEAD",
+            "-o",
+            "1",
+            "--show-matching",
+            "a|b",
+            "--select-hunks",
+            "1,2",
+            "--salt",
+            "s3",
+            "--hash-values",
+            "--notify-url",
+            "https://hooks.example/T0KEN",
+        ])
+        .unwrap();
+        let Command::Compare(args) = cli.command else {
+            panic!("expected compare command");
+        };
+        assert_eq!(
+            options(&args).to_string(),
+            r#"{"orig":"a.csv","diff":"b.csv@HEAD","orig-index":"1","show-matching":"a|b","notify-url":"***","select-hunks":["1","2"],"hash-values":true,"salt":"***"}"#
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use std::borrow::Cow;
+
+use crate::cli::CompareArgs;
+use crate::sha256::{self, Sha256};
+
+const REDACTED: &str = "***";
+
+/// Hex digits of the salted hash shown in place of a value.
+const HASH_LEN: usize = 16;
+
+/// How compared values are shown in reports, so they can be shared without
+/// disclosing the values themselves.
+pub enum Masking {
+    Plain,
+    Redact,
+    Hash(String),
+}
+
+impl Masking {
+    pub fn new(args: &CompareArgs) -> Self {
+        if args.redact {
+            Masking::Redact
+        } else if args.hash_values {
+            Masking::Hash(args.salt.clone().unwrap_or_default())
+        } else {
+            Masking::Plain
+        }
+    }
+
+    pub fn is_plain(&self) -> bool {
+        matches!(self, Masking::Plain)
+    }
+
+    /// The value as it may be displayed. Equal values hash equally, so the
+    /// structure of the differences stays visible.
+    pub fn apply<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        match self {
+            Masking::Plain => Cow::Borrowed(value),
+            Masking::Redact => Cow::Borrowed(REDACTED),
+            Masking::Hash(salt) => {
+                let mut hasher = Sha256::new();
+                hasher.update(&(salt.len() as u64).to_le_bytes());
+                hasher.update(salt.as_bytes());
+                hasher.update(value.as_bytes());
+                let mut hash = sha256::to_hex(&hasher.finalize());
+                hash.truncate(HASH_LEN);
+                Cow::Owned(hash)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_masking() {
+        assert_eq!(Masking::Plain.apply("alice"), "alice");
+        assert_eq!(Masking::Redact.apply("alice"), "***");
+
+        let salted = Masking::Hash("pepper".to_string());
+        let hash = salted.apply("alice");
+        assert_eq!(hash.len(), HASH_LEN);
+        assert_eq!(hash, salted.apply("alice"));
+        assert_ne!(hash, salted.apply("bob"));
+        assert_ne!(hash, Masking::Hash(String::new()).apply("alice"));
+    }
+}
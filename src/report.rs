@@ -0,0 +1,126 @@
+use std::fmt::Write;
+use std::fs;
+
+use anyhow::Result;
+use similar::ChangeTag;
+use similar::udiff::UnifiedHunkHeader;
+
+use crate::chunked::grouped_ops;
+use crate::cli::ReportArgs;
+use crate::counts::diff_counts;
+use crate::diff3::slices;
+use crate::errors::Category;
+use crate::json::{self, Value};
+use crate::provenance::Provenance;
+use crate::redact::Masking;
+use crate::template;
+
+fn line(index: Option<usize>) -> Value {
+    index.map_or(Value::Null, |index| Value::Number((index + 1) as f64))
+}
+
+/// Structured result of comparing two columns: labels, counts and the hunks
+/// of the diff, with their changes and 1-based line numbers.
+pub fn comparison(
+    labels: (&str, &str),
+    (orig_lines, diff_lines): (&[String], &[String]),
+    masking: &Masking,
+    chunk_rows: usize,
+    provenance: &Provenance,
+) -> Value {
+    let (orig, diff) = (slices(orig_lines), slices(diff_lines));
+    let counts = diff_counts(orig_lines, diff_lines, chunk_rows);
+    let hunks = grouped_ops(&orig, &diff, chunk_rows, 3)
+        .iter()
+        .map(|ops| {
+            let changes = ops
+                .iter()
+                .flat_map(|op| op.iter_changes(&orig[..], &diff[..]))
+                .map(|change| {
+                    let kind = match change.tag() {
+                        ChangeTag::Delete => "removed",
+                        ChangeTag::Insert => "added",
+                        ChangeTag::Equal => "equal",
+                    };
+                    Value::Object(vec![
+                        ("kind".to_string(), Value::String(kind.to_string())),
+                        ("tag".to_string(), Value::String(change.tag().to_string())),
+                        (
+                            "value".to_string(),
+                            Value::String(masking.apply(change.value()).into_owned()),
+                        ),
+                        ("orig_line".to_string(), line(change.old_index())),
+                        ("diff_line".to_string(), line(change.new_index())),
+                    ])
+                })
+                .collect();
+            Value::Object(vec![
+                (
+                    "header".to_string(),
+                    Value::String(UnifiedHunkHeader::new(ops).to_string()),
+                ),
+                ("changes".to_string(), Value::Array(changes)),
+            ])
+        })
+        .collect();
+    let number = |count: usize| Value::Number(count as f64);
+    Value::Object(vec![
+        ("orig".to_string(), Value::String(labels.0.to_string())),
+        ("diff".to_string(), Value::String(labels.1.to_string())),
+        (
+            "inputs".to_string(),
+            Value::Object(vec![
+                ("orig".to_string(), provenance.inputs.0.to_json()),
+                ("diff".to_string(), provenance.inputs.1.to_json()),
+            ]),
+        ),
+        ("provenance".to_string(), provenance.to_json()),
+        (
+            "identical".to_string(),
+            Value::Bool(orig_lines == diff_lines),
+        ),
+        (
+            "counts".to_string(),
+            Value::Object(vec![
+                ("removed".to_string(), number(counts.removed)),
+                ("added".to_string(), number(counts.added)),
+                ("changed".to_string(), number(counts.changed)),
+            ]),
+        ),
+        ("hunks".to_string(), Value::Array(hunks)),
+    ])
+}
+
+/// Unified diff of a saved `comparison`, in the format of `--patch-output`.
+fn unified_diff(result: &Value) -> Option<String> {
+    let mut diff = String::new();
+    let label = |key| result.get(key).and_then(Value::as_str);
+    writeln!(diff, "--- {}", label("orig")?).ok()?;
+    writeln!(diff, "+++ {}", label("diff")?).ok()?;
+    for hunk in result.get("hunks")?.as_array()? {
+        writeln!(diff, "{}", hunk.get("header")?.as_str()?).ok()?;
+        for change in hunk.get("changes")?.as_array()? {
+            let tag = change.get("tag")?.as_str()?;
+            writeln!(diff, "{tag}{}", change.get("value")?.as_str()?).ok()?;
+        }
+    }
+    Some(diff)
+}
+
+pub fn run(args: &ReportArgs) -> Result<()> {
+    let path = &args.result;
+    let result = json::parse(&fs::read_to_string(path)?)
+        .map_err(|err| Category::Parse.error(format!("{}: {err}", path.display())))?;
+    let rendered = match &args.template {
+        Some(template) => template::render(&fs::read_to_string(template)?, &result)
+            .map_err(|err| Category::Parse.error(format!("{}: {err}", template.display())))?,
+        None => unified_diff(&result).ok_or_else(|| {
+            Category::Parse.error(format!(
+                "{}: not a comparison result (missing `orig`, `diff` or `hunks`)",
+                path.display()
+            ))
+        })?,
+    };
+    print!("{rendered}");
+    Ok(())
+}
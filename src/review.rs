@@ -0,0 +1,212 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::Result;
+use colored::Colorize;
+use inquire::Select;
+use similar::udiff::UnifiedHunkHeader;
+use similar::{ChangeTag, DiffOp};
+
+use crate::chunked::grouped_ops;
+use crate::i18n::tr;
+
+/// How a reviewed hunk ends up in the resolved column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Resolution {
+    KeepOrig,
+    KeepDiff,
+    KeepBoth,
+    /// Leave the hunk unresolved, wrapped in conflict markers.
+    Skip,
+}
+
+impl Resolution {
+    const ALL: [Resolution; 4] = [
+        Resolution::KeepOrig,
+        Resolution::KeepDiff,
+        Resolution::KeepBoth,
+        Resolution::Skip,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Resolution::KeepOrig => "keep orig",
+            Resolution::KeepDiff => "keep diff",
+            Resolution::KeepBoth => "keep both",
+            Resolution::Skip => "skip (leave conflict markers)",
+        }
+    }
+}
+
+fn print_hunk((orig, diff): (&[&str], &[&str]), ops: &[DiffOp], number: usize, total: usize) {
+    println!(
+        "{} {}",
+        format!("[{number}/{total}]").bold(),
+        UnifiedHunkHeader::new(ops).to_string().cyan()
+    );
+    for op in ops {
+        for change in op.iter_changes(orig, diff) {
+            match change.tag() {
+                ChangeTag::Delete => println!("{}{}", "-".red(), change.value().red()),
+                ChangeTag::Insert => println!("{}{}", "+".green(), change.value().green()),
+                ChangeTag::Equal => println!(" {}", change.value()),
+            }
+        }
+    }
+}
+
+/// Rebuilds the column from orig, replacing the changes of each hunk
+/// according to its resolution.
+fn resolve(
+    orig: &[&str],
+    diff: &[&str],
+    hunks: &[Vec<DiffOp>],
+    resolutions: &[Resolution],
+) -> Vec<String> {
+    let mut res = Vec::with_capacity(orig.len());
+    let mut pos = 0;
+    for (ops, resolution) in hunks.iter().zip(resolutions) {
+        for op in ops {
+            let (old, new) = (op.old_range(), op.new_range());
+            res.extend(orig[pos..old.start].iter().map(|value| value.to_string()));
+            pos = old.end;
+            let (old, new) = (&orig[old], &diff[new]);
+            let lines = |values: &[&str]| values.iter().map(|value| value.to_string()).collect();
+            let lines: Vec<String> = match (op, resolution) {
+                (DiffOp::Equal { .. }, _) | (_, Resolution::KeepOrig) => lines(old),
+                (_, Resolution::KeepDiff) => lines(new),
+                (_, Resolution::KeepBoth) => [lines(old), lines(new)].concat(),
+                (_, Resolution::Skip) => [
+                    vec!["<<<<<<< orig".to_string()],
+                    lines(old),
+                    vec!["=======".to_string()],
+                    lines(new),
+                    vec![">>>>>>> diff".to_string()],
+                ]
+                .concat(),
+            };
+            res.extend(lines);
+        }
+    }
+    res.extend(orig[pos..].iter().map(|value| value.to_string()));
+    res
+}
+
+/// Writes the hunks for which `selected(index)` holds (0-based) as a unified
+/// diff that can be shared or applied separately.
+pub fn write_patch(
+    output: &Path,
+    headers: (&str, &str),
+    (orig_lines, diff_lines): (&[String], &[String]),
+    chunk_rows: usize,
+    selected: impl Fn(usize) -> bool,
+) -> Result<()> {
+    let orig: Vec<&str> = orig_lines.iter().map(String::as_str).collect();
+    let diff: Vec<&str> = diff_lines.iter().map(String::as_str).collect();
+
+    let mut writer = BufWriter::new(File::create(output)?);
+    writeln!(writer, "--- {}", headers.0)?;
+    writeln!(writer, "+++ {}", headers.1)?;
+    for (idx, ops) in grouped_ops(&orig, &diff, chunk_rows, 3).iter().enumerate() {
+        if !selected(idx) {
+            continue;
+        }
+        writeln!(writer, "{}", UnifiedHunkHeader::new(ops))?;
+        for change in ops
+            .iter()
+            .flat_map(|op| op.iter_changes(&orig[..], &diff[..]))
+        {
+            writeln!(writer, "{}{}", change.tag(), change.value())?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Walks through every hunk of the diff, asking how to resolve it, and writes
+/// the resolved column to `output`. With `patch`, the hunks kept from diff are
+/// also exported as a unified diff.
+pub fn run(
+    (orig_lines, diff_lines): (&[String], &[String]),
+    chunk_rows: usize,
+    output: &Path,
+    patch: Option<(&Path, (&str, &str))>,
+) -> Result<()> {
+    let orig: Vec<&str> = orig_lines.iter().map(String::as_str).collect();
+    let diff: Vec<&str> = diff_lines.iter().map(String::as_str).collect();
+    let hunks = grouped_ops(&orig, &diff, chunk_rows, 3);
+
+    let mut resolutions = Vec::with_capacity(hunks.len());
+    for (idx, ops) in hunks.iter().enumerate() {
+        print_hunk((&orig, &diff), ops, idx + 1, hunks.len());
+        let labels: Vec<&str> = Resolution::ALL.iter().map(|res| res.label()).collect();
+        let choice = Select::new(&tr("resolve-hunk", &[]), labels).raw_prompt()?;
+        resolutions.push(Resolution::ALL[choice.index]);
+    }
+
+    let mut writer = BufWriter::new(File::create(output)?);
+    for value in resolve(&orig, &diff, &hunks, &resolutions) {
+        writeln!(writer, "{value}")?;
+    }
+    writer.flush()?;
+    let (count, path) = (hunks.len().to_string(), output.display().to_string());
+    println!("{}", tr("resolved", &[("count", &count), ("path", &path)]));
+
+    if let Some((path, headers)) = patch {
+        write_patch(path, headers, (orig_lines, diff_lines), chunk_rows, |idx| {
+            resolutions[idx] == Resolution::KeepDiff
+        })?;
+        println!("{}", tr("wrote", &[("path", &path.display().to_string())]));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar::TextDiff;
+
+    #[test]
+    fn test_resolve() {
+        let orig: Vec<&str> = "a b c d e f g h i j".split(' ').collect();
+        let diff: Vec<&str> = "a B c d e f g h i J".split(' ').collect();
+        let hunks = TextDiff::from_slices(&orig, &diff).grouped_ops(1);
+        assert_eq!(hunks.len(), 2);
+
+        let resolved = resolve(
+            &orig,
+            &diff,
+            &hunks,
+            &[Resolution::KeepDiff, Resolution::KeepOrig],
+        );
+        assert_eq!(resolved.join(" "), "a B c d e f g h i j");
+
+        let resolved = resolve(
+            &orig,
+            &diff,
+            &hunks,
+            &[Resolution::KeepBoth, Resolution::Skip],
+        );
+        assert_eq!(
+            resolved.join(" "),
+            "a b B c d e f g h i <<<<<<< orig j ======= J >>>>>>> diff"
+        );
+    }
+
+    #[test]
+    fn test_write_patch() {
+        let patch = assert_fs::NamedTempFile::new("out.patch").unwrap();
+        let orig: Vec<String> = "a b c d e f g h i j".split(' ').map(String::from).collect();
+        let diff: Vec<String> = "a B c d e f g h i J".split(' ').map(String::from).collect();
+        let headers = ("a/orig.csv", "b/diff.csv");
+        write_patch(patch.path(), headers, (&orig, &diff), usize::MAX, |idx| {
+            idx == 1
+        })
+        .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(patch.path()).unwrap(),
+            "--- a/orig.csv\n+++ b/diff.csv\n@@ -7,4 +7,4 @@\n g\n h\n i\n-j\n+J\n"
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+
+use crate::dates::parse_datetime;
+use crate::errors::Category;
+
+/// How two values of a column are compared.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rule {
+    Exact,
+    Ignore,
+    CaseInsensitive,
+    Numeric {
+        tolerance: f64,
+    },
+    /// Dates parsed with `format`, equal when at most `tolerance` seconds
+    /// apart (none if unset).
+    Date {
+        format: String,
+        tolerance: Option<i64>,
+    },
+}
+
+impl Rule {
+    pub fn equal(&self, old: &str, new: &str) -> bool {
+        match self {
+            Rule::Exact => old == new,
+            Rule::Ignore => true,
+            Rule::CaseInsensitive => old.to_lowercase() == new.to_lowercase(),
+            Rule::Numeric { tolerance } => {
+                match (old.trim().parse::<f64>(), new.trim().parse::<f64>()) {
+                    (Ok(old), Ok(new)) => (old - new).abs() <= *tolerance,
+                    _ => old == new,
+                }
+            }
+            Rule::Date { format, tolerance } => {
+                match (parse_datetime(old, format), parse_datetime(new, format)) {
+                    (Some(old), Some(new)) => (old - new).abs() <= tolerance.unwrap_or(0),
+                    _ => old == new,
+                }
+            }
+        }
+    }
+}
+
+/// Per-column comparison rules, read from a TOML file such as:
+///
+/// ```toml
+/// [columns.price]
+/// rule = "numeric"
+/// tolerance = 0.01
+///
+/// [columns.3]
+/// rule = "ignore"
+/// ```
+///
+/// Columns are identified by header name or by their 1-based orig index.
+#[derive(Debug, Default)]
+pub struct Rules {
+    columns: Vec<(String, Rule)>,
+}
+
+#[derive(Default)]
+struct Section {
+    column: String,
+    rule: Option<String>,
+    tolerance: Option<f64>,
+    format: Option<String>,
+}
+
+impl Section {
+    fn into_rule(self) -> Result<(String, Rule)> {
+        let rule = match self.rule.as_deref() {
+            None | Some("exact") => Rule::Exact,
+            Some("ignore") => Rule::Ignore,
+            Some("case-insensitive") => Rule::CaseInsensitive,
+            Some("numeric") => Rule::Numeric {
+                tolerance: self.tolerance.unwrap_or(0.0),
+            },
+            Some("date") => Rule::Date {
+                format: self
+                    .format
+                    .ok_or_else(|| anyhow!("Date rule of `{}` needs a `format`", self.column))?,
+                tolerance: self.tolerance.map(|tolerance| tolerance as i64),
+            },
+            Some(other) => return Err(anyhow!("Unknown rule `{other}` for `{}`", self.column)),
+        };
+        Ok((self.column, rule))
+    }
+}
+
+/// `line` without its comment, a `#` outside of quoted strings.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (pos, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..pos],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn unquote(value: &str) -> Option<&str> {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+}
+
+impl Rules {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+            .map_err(|err| Category::Parse.error(format!("{}: {err}", path.display())))
+    }
+
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut columns = Vec::new();
+        let mut section: Option<Section> = None;
+        for (idx, line) in input.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |msg: &str| anyhow!("line {}: {msg}", idx + 1);
+
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                let column = name
+                    .trim()
+                    .strip_prefix("columns.")
+                    .ok_or_else(|| error("expected a `[columns.<name>]` section"))?;
+                let column = unquote(column).unwrap_or(column);
+                if let Some(section) = section.take() {
+                    columns.push(section.into_rule()?);
+                }
+                section = Some(Section {
+                    column: column.to_string(),
+                    ..Default::default()
+                });
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected `key = value`"))?;
+            let (key, value) = (key.trim(), value.trim());
+            let section = section
+                .as_mut()
+                .ok_or_else(|| error("key outside of a `[columns.<name>]` section"))?;
+            match key {
+                "rule" => {
+                    section.rule = Some(
+                        unquote(value)
+                            .ok_or_else(|| error("expected a string"))?
+                            .to_string(),
+                    )
+                }
+                "format" => {
+                    section.format = Some(
+                        unquote(value)
+                            .ok_or_else(|| error("expected a string"))?
+                            .to_string(),
+                    )
+                }
+                "tolerance" => {
+                    section.tolerance = Some(value.parse().map_err(|_| error("expected a number"))?)
+                }
+                _ => return Err(error(&format!("unknown key `{key}`"))),
+            }
+        }
+        if let Some(section) = section {
+            columns.push(section.into_rule()?);
+        }
+        Ok(Self { columns })
+    }
+
+    /// Sets the tolerance of date rules that do not specify their own.
+    pub fn with_time_tolerance(mut self, seconds: i64) -> Self {
+        for (_, rule) in &mut self.columns {
+            if let Rule::Date { tolerance, .. } = rule
+                && tolerance.is_none()
+            {
+                *tolerance = Some(seconds);
+            }
+        }
+        self
+    }
+
+    /// Rule of a column, looked up by its header `name` or 1-based `index`.
+    pub fn get(&self, name: Option<&str>, index: usize) -> &Rule {
+        let index = index.to_string();
+        self.columns
+            .iter()
+            .find(|(column, _)| Some(column.as_str()) == name || *column == index)
+            .map_or(&Rule::Exact, |(_, rule)| rule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules() {
+        let rules = Rules::parse(
+            r#"
+            # comparison rules
+            [columns.price]
+            rule = "numeric"
+            tolerance = 0.01
+
+            [columns."Name"]
+            rule = "case-insensitive"
+
+            [columns.4]
+            rule = "ignore"
+
+            [columns.updated]
+            rule = "date" # parsed before comparing
+            format = "%d/%m/%Y"
+            "#,
+        )
+        .unwrap();
+
+        assert!(rules.get(Some("price"), 1).equal("1.000", "1.005"));
+        assert!(!rules.get(Some("price"), 1).equal("1.00", "1.02"));
+        assert!(rules.get(Some("Name"), 2).equal("ACME", "acme"));
+        assert!(rules.get(None, 4).equal("a", "b"));
+        assert!(
+            rules
+                .get(Some("updated"), 5)
+                .equal("01/02/2024", "1/2/2024")
+        );
+        assert_eq!(rules.get(Some("other"), 6), &Rule::Exact);
+
+        let rules = rules.with_time_tolerance(2 * 86_400);
+        assert!(
+            rules
+                .get(Some("updated"), 5)
+                .equal("01/02/2024", "03/02/2024")
+        );
+        assert!(
+            !rules
+                .get(Some("updated"), 5)
+                .equal("01/02/2024", "04/02/2024")
+        );
+
+        let rules = Rules::parse(
+            "[columns.\"id #\"] # key\nrule = \"date\"\nformat = \"%Y #%m\"\ntolerance = 0\n",
+        )
+        .unwrap()
+        .with_time_tolerance(86_400);
+        assert_eq!(
+            rules.get(Some("id #"), 1),
+            &Rule::Date {
+                format: "%Y #%m".to_string(),
+                tolerance: Some(0)
+            }
+        );
+
+        assert!(Rules::parse("[columns.a]\nrule = \"fuzzy\"").is_err());
+        assert!(Rules::parse("rule = \"exact\"").is_err());
+    }
+}
@@ -0,0 +1,236 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Mutex, PoisonError, mpsc};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+
+use crate::api;
+use crate::cli::ServeArgs;
+use crate::errors::{self, Category};
+use crate::json::{self, Value};
+use crate::logging;
+
+/// Largest accepted request body.
+const MAX_BODY: usize = 256 << 20;
+
+/// How long a connection may stall while its request is read or its
+/// response written.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+/// `:8080` listens on all interfaces, like Go's `net/http` does.
+fn address(listen: &str) -> String {
+    match listen.strip_prefix(':') {
+        Some(port) => format!("0.0.0.0:{port}"),
+        None => listen.to_string(),
+    }
+}
+
+fn read_request(reader: &mut impl BufRead) -> Result<Request> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(Category::Parse.error("malformed request line"));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut length = 0;
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            continue;
+        };
+        if name.eq_ignore_ascii_case("content-length") {
+            length = value
+                .trim()
+                .parse()
+                .map_err(|_| Category::Parse.error("invalid Content-Length"))?;
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            return Err(Category::Parse.error("chunked requests need a Content-Length instead"));
+        }
+    }
+    if length > MAX_BODY {
+        return Err(Category::Parse.error(format!("request body exceeds {MAX_BODY} bytes")));
+    }
+    // Read as the body arrives, not into a buffer of the announced length.
+    let mut body = Vec::new();
+    reader.take(length as u64).read_to_end(&mut body)?;
+    if body.len() < length {
+        return Err(Category::Parse.error(format!(
+            "request body ended after {} of {length} bytes",
+            body.len()
+        )));
+    }
+    Ok(Request { method, path, body })
+}
+
+/// Compares the `orig` and `diff` CSV texts of a request body.
+fn compare(body: &[u8]) -> Result<Value> {
+    let request = json::parse(std::str::from_utf8(body)?)?;
+    let text = |name: &str| {
+        request
+            .get(name)
+            .and_then(Value::as_str)
+            .ok_or_else(|| Category::Parse.error(format!("`{name}` must be the CSV text")))
+    };
+    api::compare(
+        text("orig")?,
+        text("diff")?,
+        request.get("options").unwrap_or(&Value::Null),
+    )
+}
+
+fn respond(request: &Request) -> (u16, Value) {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/health") => (
+            200,
+            Value::Object(vec![(
+                "status".to_string(),
+                Value::String("ok".to_string()),
+            )]),
+        ),
+        ("POST", "/compare") => match compare(&request.body) {
+            Ok(result) => (200, result),
+            Err(err) => {
+                let status = match errors::category(&err) {
+                    Category::Io | Category::Other => 500,
+                    _ => 400,
+                };
+                (status, errors::to_json(&err))
+            }
+        },
+        (_, "/health" | "/compare") => (405, errors::to_json(&anyhow!("method not allowed"))),
+        _ => (404, errors::to_json(&anyhow!("not found"))),
+    }
+}
+
+fn write_response(writer: &mut impl Write, status: u16, body: &Value) -> Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    };
+    let body = body.to_string();
+    write!(
+        writer,
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn handle(stream: TcpStream) -> Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let (status, body) = match read_request(&mut reader) {
+        Ok(request) => {
+            let (status, body) = respond(&request);
+            logging::info(
+                "request",
+                &[
+                    ("method", request.method),
+                    ("path", request.path),
+                    ("status", status.to_string()),
+                ],
+            );
+            (status, body)
+        }
+        Err(err) => (400, errors::to_json(&err)),
+    };
+    write_response(&mut &stream, status, &body)
+}
+
+/// Serves comparisons over HTTP: `POST /compare` with a JSON body of the
+/// `orig` and `diff` CSV texts and optional `options`, and `GET /health`.
+/// Connections are handled by `--max-connections` threads; while all are
+/// busy, new connections wait in the listen backlog.
+pub fn run(args: &ServeArgs) -> Result<()> {
+    let listener = TcpListener::bind(address(&args.listen))?;
+    eprintln!("Listening on http://{}", listener.local_addr()?);
+    let (sender, receiver) = mpsc::sync_channel::<TcpStream>(0);
+    let receiver = Mutex::new(receiver);
+    thread::scope(|scope| {
+        for _ in 0..args.max_connections {
+            scope.spawn(|| {
+                loop {
+                    let next = receiver
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .recv();
+                    let Ok(stream) = next else {
+                        break;
+                    };
+                    if let Err(err) = handle(stream) {
+                        logging::warn("connection", &[("error", err.to_string())]);
+                    }
+                }
+            });
+        }
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => sender.send(stream)?,
+                Err(err) => logging::warn("accept", &[("error", err.to_string())]),
+            }
+        }
+        drop(sender);
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(body: &str) -> (u16, Value) {
+        let raw = format!(
+            "POST /compare HTTP/1.1\r\nHost: x\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        let request = read_request(&mut raw.as_bytes()).unwrap();
+        respond(&request)
+    }
+
+    #[test]
+    fn test_truncated_body() {
+        let raw = "POST /compare HTTP/1.1\r\nContent-Length: 1000000\r\n\r\n{}";
+        let err = read_request(&mut raw.as_bytes()).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "request body ended after 2 of 1000000 bytes"
+        );
+    }
+
+    #[test]
+    fn test_compare_request() {
+        let (status, result) = post(
+            r#"{"orig": "id,v\n1,a\n2,b\n", "diff": "id,v\n1,a\n2,c\n", "options": {"orig_index": 2, "with_headers": true}}"#,
+        );
+        assert_eq!(status, 200);
+        assert_eq!(result.get("identical"), Some(&Value::Bool(false)));
+        let counts = result.get("counts").unwrap();
+        assert_eq!(counts.get("changed"), Some(&Value::Number(1.0)));
+
+        let (status, result) =
+            post(r#"{"orig": "a", "diff": "a", "options": {"transform_cmd": "rm -rf /"}}"#);
+        assert_eq!(status, 400);
+        assert_eq!(result.get("code").unwrap().as_str(), Some("parse"));
+        assert_eq!(address(":8080"), "0.0.0.0:8080");
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+
+use crate::cli::SetOperation;
+use crate::spill::Partitions;
+
+/// Applies `op` to the distinct values of both columns. Values keep the order
+/// of their first occurrence, orig values first.
+pub fn set_operation<'a>(op: SetOperation, orig: &'a [String], diff: &'a [String]) -> Vec<&'a str> {
+    let orig_set: HashSet<&str> = orig.iter().map(String::as_str).collect();
+    let diff_set: HashSet<&str> = diff.iter().map(String::as_str).collect();
+
+    let mut seen = HashSet::new();
+    let mut res = Vec::new();
+    let mut push = |value: &'a str| {
+        if seen.insert(value) {
+            res.push(value);
+        }
+    };
+    match op {
+        SetOperation::Intersection => orig
+            .iter()
+            .filter(|value| diff_set.contains(value.as_str()))
+            .for_each(|value| push(value)),
+        SetOperation::OnlyOrig => orig
+            .iter()
+            .filter(|value| !diff_set.contains(value.as_str()))
+            .for_each(|value| push(value)),
+        SetOperation::OnlyDiff => diff
+            .iter()
+            .filter(|value| !orig_set.contains(value.as_str()))
+            .for_each(|value| push(value)),
+        SetOperation::Union => orig.iter().chain(diff).for_each(|value| push(value)),
+    }
+    res
+}
+
+/// Overlap of the distinct values of both columns.
+#[derive(Debug, PartialEq)]
+pub struct SetMetrics {
+    pub only_orig: usize,
+    pub only_diff: usize,
+    pub both: usize,
+    pub jaccard: f64,
+    pub overlap: f64,
+}
+
+fn ratio(num: usize, den: usize) -> f64 {
+    if den == 0 {
+        1.0
+    } else {
+        num as f64 / den as f64
+    }
+}
+
+impl SetMetrics {
+    fn new(only_orig: usize, only_diff: usize, both: usize) -> Self {
+        SetMetrics {
+            only_orig,
+            only_diff,
+            both,
+            jaccard: ratio(both, both + only_orig + only_diff),
+            overlap: ratio(both, (only_orig + both).min(only_diff + both)),
+        }
+    }
+}
+
+pub fn set_metrics(orig: &[String], diff: &[String]) -> SetMetrics {
+    let orig_set: HashSet<&str> = orig.iter().map(String::as_str).collect();
+    let diff_set: HashSet<&str> = diff.iter().map(String::as_str).collect();
+    let both = orig_set.intersection(&diff_set).count();
+    SetMetrics::new(orig_set.len() - both, diff_set.len() - both, both)
+}
+
+/// `set_operation` (if `op` is given) and `set_metrics` of columns spilled
+/// into hash partitions, holding one pair of partitions in memory at a time.
+/// Values come grouped by partition, in first occurrence order within each.
+pub fn partitioned_sets(
+    op: Option<SetOperation>,
+    orig: Partitions,
+    diff: Partitions,
+    mut emit: impl FnMut(&str) -> Result<()>,
+) -> Result<SetMetrics> {
+    let (mut only_orig, mut only_diff, mut both) = (0, 0, 0);
+    for (orig, diff) in orig.into_iter()?.zip(diff.into_iter()?) {
+        let (orig, diff) = (orig?, diff?);
+        if let Some(op) = op {
+            for value in set_operation(op, &orig, &diff) {
+                emit(value)?;
+            }
+        }
+        let metrics = set_metrics(&orig, &diff);
+        only_orig += metrics.only_orig;
+        only_diff += metrics.only_diff;
+        both += metrics.both;
+    }
+    Ok(SetMetrics::new(only_orig, only_diff, both))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_operation() {
+        let orig: Vec<String> = ["a", "b", "b", "c"].map(String::from).to_vec();
+        let diff: Vec<String> = ["c", "d", "a"].map(String::from).to_vec();
+
+        assert_eq!(
+            set_operation(SetOperation::Intersection, &orig, &diff),
+            ["a", "c"]
+        );
+        assert_eq!(set_operation(SetOperation::OnlyOrig, &orig, &diff), ["b"]);
+        assert_eq!(set_operation(SetOperation::OnlyDiff, &orig, &diff), ["d"]);
+        assert_eq!(
+            set_operation(SetOperation::Union, &orig, &diff),
+            ["a", "b", "c", "d"]
+        );
+
+        assert_eq!(
+            set_metrics(&orig, &diff),
+            SetMetrics {
+                only_orig: 1,
+                only_diff: 1,
+                both: 2,
+                jaccard: 0.5,
+                overlap: 2.0 / 3.0,
+            }
+        );
+
+        let (mut orig_parts, mut diff_parts) =
+            (Partitions::new(2).unwrap(), Partitions::new(2).unwrap());
+        orig.iter()
+            .for_each(|value| orig_parts.push(value).unwrap());
+        diff.iter()
+            .for_each(|value| diff_parts.push(value).unwrap());
+        let mut emitted = Vec::new();
+        let metrics =
+            partitioned_sets(Some(SetOperation::Union), orig_parts, diff_parts, |value| {
+                emitted.push(value.to_string());
+                Ok(())
+            })
+            .unwrap();
+        emitted.sort();
+        assert_eq!(emitted, ["a", "b", "c", "d"]);
+        assert_eq!(metrics, set_metrics(&orig, &diff));
+    }
+}
@@ -0,0 +1,124 @@
+use std::borrow::Cow;
+
+use colored::Colorize;
+use similar::DiffOp;
+use similar::udiff::UnifiedHunkHeader;
+use unicode_width::UnicodeWidthStr;
+
+use crate::chunked::grouped_ops;
+use crate::cli::CompareArgs;
+use crate::compare::hunk_selected;
+use crate::display::{pad, truncate};
+use crate::redact::Masking;
+
+/// A displayed line: the orig value on the left, the diff value on the right.
+/// Values only present on one side leave the other side empty.
+struct Row<'a> {
+    orig: Option<&'a str>,
+    diff: Option<&'a str>,
+    changed: bool,
+}
+
+fn rows<'a>(orig: &[&'a str], diff: &[&'a str], ops: &[DiffOp]) -> Vec<Row<'a>> {
+    let mut rows = Vec::new();
+    for op in ops {
+        let (old, new) = (&orig[op.old_range()], &diff[op.new_range()]);
+        let changed = !matches!(op, DiffOp::Equal { .. });
+        for idx in 0..old.len().max(new.len()) {
+            rows.push(Row {
+                orig: old.get(idx).copied(),
+                diff: new.get(idx).copied(),
+                changed,
+            });
+        }
+    }
+    rows
+}
+
+/// Prints the hunks of the diff in two columns, orig left and diff right,
+/// padded by terminal width so wide characters keep the columns aligned.
+/// Returns the removed and added values.
+pub fn print<'a>(
+    args: &CompareArgs,
+    orig_lines: &'a [String],
+    diff_lines: &'a [String],
+) -> (Vec<&'a str>, Vec<&'a str>) {
+    let orig: Vec<&str> = orig_lines.iter().map(String::as_str).collect();
+    let diff: Vec<&str> = diff_lines.iter().map(String::as_str).collect();
+
+    let masking = Masking::new(args);
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    for (idx, ops) in grouped_ops(&orig, &diff, args.chunk_rows, 3)
+        .iter()
+        .enumerate()
+    {
+        if !hunk_selected(args, idx) {
+            continue;
+        }
+        let rows = rows(&orig, &diff, ops);
+        let fitted = |value: Option<&'a str>| -> Cow<'a, str> {
+            let value = masking.apply(value.unwrap_or(""));
+            match args.max_width {
+                Some(width) => truncate(&value, width).into_owned().into(),
+                None => value,
+            }
+        };
+        let width = rows
+            .iter()
+            .map(|row| fitted(row.orig).width())
+            .max()
+            .unwrap_or(0);
+
+        println!("{}", UnifiedHunkHeader::new(ops).to_string().cyan());
+        for row in rows {
+            let left = pad(&fitted(row.orig), width);
+            let right = fitted(row.diff);
+            if !row.changed {
+                if !args.changes_only {
+                    println!("  {left} │   {right}");
+                }
+                continue;
+            }
+            removed.extend(row.orig);
+            added.extend(row.diff);
+            let (left_tag, right_tag) = (
+                if row.orig.is_some() { "-" } else { " " },
+                if row.diff.is_some() { "+" } else { " " },
+            );
+            println!(
+                "{} {} │ {} {}",
+                left_tag.red(),
+                left.red(),
+                right_tag.green(),
+                right.green()
+            );
+        }
+    }
+    (removed, added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar::TextDiff;
+
+    #[test]
+    fn test_rows() {
+        let orig = ["a", "b", "c"];
+        let diff = ["a", "B", "x", "c"];
+        let ops = TextDiff::from_slices(&orig, &diff).grouped_ops(1);
+        let rows: Vec<_> = rows(&orig, &diff, &ops[0])
+            .into_iter()
+            .map(|row| (row.orig, row.diff, row.changed))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (Some("a"), Some("a"), false),
+                (Some("b"), Some("B"), true),
+                (None, Some("x"), true),
+                (Some("c"), Some("c"), false),
+            ]
+        );
+    }
+}
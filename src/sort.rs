@@ -0,0 +1,141 @@
+use std::cmp::Ordering;
+
+use csv::StringRecord;
+
+use crate::cli::{SortKey, SortOrder};
+
+/// Compares digit runs by numeric value and everything else byte-wise.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (Some(ca), Some(cb)) = (a.chars().next(), b.chars().next()) else {
+            return a.len().cmp(&b.len());
+        };
+        if ca.is_ascii_digit() && cb.is_ascii_digit() {
+            let a_len = a.bytes().take_while(u8::is_ascii_digit).count();
+            let b_len = b.bytes().take_while(u8::is_ascii_digit).count();
+            let (a_num, a_rest) = a.split_at(a_len);
+            let (b_num, b_rest) = b.split_at(b_len);
+            let (a_trim, b_trim) = (a_num.trim_start_matches('0'), b_num.trim_start_matches('0'));
+            let ord = a_trim
+                .len()
+                .cmp(&b_trim.len())
+                .then_with(|| a_trim.cmp(b_trim))
+                .then_with(|| a_num.len().cmp(&b_num.len()));
+            if ord != Ordering::Equal {
+                return ord;
+            }
+            (a, b) = (a_rest, b_rest);
+        } else {
+            if ca != cb {
+                return ca.cmp(&cb);
+            }
+            (a, b) = (&a[ca.len_utf8()..], &b[cb.len_utf8()..]);
+        }
+    }
+}
+
+fn numeric_cmp(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.total_cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+pub fn compare_values(a: &str, b: &str, order: SortOrder) -> Ordering {
+    match order {
+        SortOrder::Lexical => a.cmp(b),
+        SortOrder::Natural => natural_cmp(a, b),
+        SortOrder::Numeric => numeric_cmp(a, b),
+    }
+}
+
+/// `compare_values`, with ties broken byte-wise so that only identical values
+/// compare equal.
+pub fn total_cmp(a: &str, b: &str, order: SortOrder) -> Ordering {
+    compare_values(a, b, order).then_with(|| a.cmp(b))
+}
+
+pub fn sort_values(values: &mut [String], order: SortOrder) {
+    match order {
+        SortOrder::Lexical => values.sort_unstable(),
+        _ => values.sort_by(|a, b| compare_values(a, b, order)),
+    }
+}
+
+/// Sorts `values` and their source `records` alike, by `keys` in turn. The
+/// sort is stable, so rows with equal keys keep their file order.
+pub fn sort_by_keys(
+    values: &mut Vec<String>,
+    records: &mut Vec<StringRecord>,
+    keys: &[SortKey],
+    order: SortOrder,
+) {
+    let mut rows: Vec<usize> = (0..values.len().min(records.len())).collect();
+    rows.sort_by(|&a, &b| {
+        keys.iter()
+            .map(|key| {
+                let field = |row: usize| records[row].get(key.column - 1).unwrap_or("");
+                let ord = compare_values(field(a), field(b), order);
+                if key.descending { ord.reverse() } else { ord }
+            })
+            .find(|ord| ord.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+    let mut taken: Vec<Option<String>> = values.drain(..).map(Some).collect();
+    values.extend(rows.iter().filter_map(|&row| taken[row].take()));
+    let mut taken: Vec<Option<StringRecord>> = records.drain(..).map(Some).collect();
+    records.extend(rows.iter().filter_map(|&row| taken[row].take()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(values: &[&str], order: SortOrder) -> Vec<String> {
+        let mut values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+        sort_values(&mut values, order);
+        values
+    }
+
+    #[test]
+    fn test_sort_by_keys() {
+        let mut records: Vec<StringRecord> = [["eu", "2"], ["us", "1"], ["eu", "10"]]
+            .into_iter()
+            .map(|row| StringRecord::from(row.to_vec()))
+            .collect();
+        let mut values = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let keys = [
+            SortKey {
+                column: 1,
+                descending: false,
+            },
+            SortKey {
+                column: 2,
+                descending: true,
+            },
+        ];
+        sort_by_keys(&mut values, &mut records, &keys, SortOrder::Numeric);
+        assert_eq!(values, ["c", "a", "b"]);
+        assert_eq!(&records[0], &StringRecord::from(vec!["eu", "10"]));
+    }
+
+    #[test]
+    fn test_sort_values() {
+        let values = ["file10", "file9", "file1", "file01", "a"];
+        assert_eq!(
+            sorted(&values, SortOrder::Lexical),
+            ["a", "file01", "file1", "file10", "file9"]
+        );
+        assert_eq!(
+            sorted(&values, SortOrder::Natural),
+            ["a", "file1", "file01", "file9", "file10"]
+        );
+        assert_eq!(
+            sorted(&["10", "x", "9.5", "-1"], SortOrder::Numeric),
+            ["-1", "9.5", "10", "x"]
+        );
+    }
+}
@@ -0,0 +1,272 @@
+use std::fs::File;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::mem;
+
+use anyhow::Result;
+use tempfile::NamedTempFile;
+
+use crate::cli::SortOrder;
+use crate::sort::total_cmp;
+
+/// Parses a byte size such as `512M`, `2G` or `4096`.
+pub fn parse_size(value: &str) -> Result<usize, String> {
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(pos) => value.split_at(pos),
+        None => (value, ""),
+    };
+    let scale: usize = match unit.to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        _ => return Err(format!("unknown size unit `{unit}`, expected K, M or G")),
+    };
+    number
+        .parse::<usize>()
+        .ok()
+        .and_then(|number| number.checked_mul(scale))
+        .ok_or_else(|| format!("invalid size `{value}`"))
+}
+
+fn write_value(writer: &mut impl Write, value: &str) -> Result<()> {
+    writer.write_all(&(value.len() as u64).to_le_bytes())?;
+    writer.write_all(value.as_bytes())?;
+    Ok(())
+}
+
+/// Memory taken by a buffered value.
+fn value_size(value: &str) -> usize {
+    value.len() + mem::size_of::<String>()
+}
+
+/// Values kept in memory up to a byte budget. Beyond it, the buffered values
+/// are written to temporary files and read back in order. A sorted spill
+/// sorts each run before writing it, for an external merge sort.
+pub struct Spill {
+    budget: usize,
+    used: usize,
+    values: Vec<String>,
+    runs: Vec<NamedTempFile>,
+    order: Option<SortOrder>,
+}
+
+impl Spill {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            used: 0,
+            values: Vec::new(),
+            runs: Vec::new(),
+            order: None,
+        }
+    }
+
+    pub fn sorted(budget: usize, order: SortOrder) -> Self {
+        Self {
+            order: Some(order),
+            ..Self::new(budget)
+        }
+    }
+
+    pub fn push(&mut self, value: String) -> Result<()> {
+        self.used += value_size(&value);
+        self.values.push(value);
+        if self.used > self.budget {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if let Some(order) = self.order {
+            self.values.sort_by(|a, b| total_cmp(a, b, order));
+        }
+        let mut file = NamedTempFile::new()?;
+        let mut writer = BufWriter::new(file.as_file_mut());
+        for value in self.values.drain(..) {
+            write_value(&mut writer, &value)?;
+        }
+        writer.flush()?;
+        drop(writer);
+        self.runs.push(file);
+        self.used = 0;
+        Ok(())
+    }
+
+    fn readers(&self) -> Result<Vec<RunReader>> {
+        self.runs
+            .iter()
+            .map(|run| Ok(RunReader::new(run.reopen()?)))
+            .collect()
+    }
+
+    /// All pushed values, in order.
+    pub fn into_iter(self) -> Result<impl Iterator<Item = Result<String>>> {
+        let readers = self.readers()?;
+        Ok(readers
+            .into_iter()
+            .flatten()
+            .chain(self.values.into_iter().map(Ok)))
+    }
+
+    /// All pushed values in sort order, merging the sorted runs as they are
+    /// read.
+    pub fn into_sorted(mut self) -> Result<Merge> {
+        let order = self.order.unwrap_or(SortOrder::Lexical);
+        self.values.sort_by(|a, b| total_cmp(a, b, order));
+        let mut sources: Vec<Box<dyn Iterator<Item = Result<String>>>> = Vec::new();
+        for reader in self.readers()? {
+            sources.push(Box::new(reader));
+        }
+        sources.push(Box::new(mem::take(&mut self.values).into_iter().map(Ok)));
+        let heads = sources
+            .iter_mut()
+            .map(|source| source.next().transpose())
+            .collect::<Result<_>>()?;
+        Ok(Merge {
+            sources,
+            heads,
+            order,
+            _runs: self.runs,
+        })
+    }
+}
+
+/// K-way merge of sorted runs, yielding the smallest head each time.
+/// Identical values only tie with each other.
+pub struct Merge {
+    sources: Vec<Box<dyn Iterator<Item = Result<String>>>>,
+    heads: Vec<Option<String>>,
+    order: SortOrder,
+    /// Keeps the run files alive while they are read.
+    _runs: Vec<NamedTempFile>,
+}
+
+impl Iterator for Merge {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (idx, _) = self
+            .heads
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, head)| head.as_deref().map(|head| (idx, head)))
+            .min_by(|a, b| total_cmp(a.1, b.1, self.order))?;
+        let value = self.heads[idx].take();
+        match self.sources[idx].next().transpose() {
+            Ok(next) => self.heads[idx] = next,
+            Err(err) => return Some(Err(err)),
+        }
+        value.map(Ok)
+    }
+}
+
+/// Values distributed over temporary files by hash, so that equal values of
+/// both columns land in partitions with the same index.
+pub struct Partitions {
+    files: Vec<BufWriter<NamedTempFile>>,
+}
+
+impl Partitions {
+    pub fn new(count: usize) -> Result<Self> {
+        let files = (0..count.max(1))
+            .map(|_| Ok(BufWriter::new(NamedTempFile::new()?)))
+            .collect::<Result<_>>()?;
+        Ok(Self { files })
+    }
+
+    pub fn push(&mut self, value: &str) -> Result<()> {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let idx = (hasher.finish() % self.files.len() as u64) as usize;
+        write_value(&mut self.files[idx], value)
+    }
+
+    /// The values of each partition, in the order they were pushed, read
+    /// one partition at a time.
+    pub fn into_iter(self) -> Result<impl Iterator<Item = Result<Vec<String>>>> {
+        let files = self
+            .files
+            .into_iter()
+            .map(|file| Ok(file.into_inner().map_err(|err| err.into_error())?))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(files
+            .into_iter()
+            .map(|file| RunReader::new(file.reopen()?).collect()))
+    }
+}
+
+/// Reads the values of a spilled run back.
+struct RunReader {
+    reader: BufReader<File>,
+}
+
+impl RunReader {
+    fn new(file: File) -> Self {
+        Self {
+            reader: BufReader::new(file),
+        }
+    }
+
+    fn read(&mut self) -> Result<Option<String>> {
+        let mut len = [0; 8];
+        match self.reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+        let mut value = vec![0; u64::from_le_bytes(len) as usize];
+        self.reader.read_exact(&mut value)?;
+        Ok(Some(String::from_utf8(value)?))
+    }
+}
+
+impl Iterator for RunReader {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spill() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("2k"), Ok(2048));
+        assert_eq!(parse_size("1G"), Ok(1 << 30));
+        assert!(parse_size("1T").is_err());
+
+        let mut spill = Spill::new(100);
+        let values: Vec<String> = (0..50).map(|i| format!("value {i}")).collect();
+        for value in &values {
+            spill.push(value.clone()).unwrap();
+        }
+        assert!(!spill.runs.is_empty());
+        let read: Vec<String> = spill.into_iter().unwrap().collect::<Result<_>>().unwrap();
+        assert_eq!(read, values);
+
+        let mut spill = Spill::sorted(100, SortOrder::Natural);
+        for value in values.iter().rev() {
+            spill.push(value.clone()).unwrap();
+        }
+        let sorted: Vec<String> = spill.into_sorted().unwrap().collect::<Result<_>>().unwrap();
+        assert_eq!(sorted, values);
+
+        let mut partitions = Partitions::new(3).unwrap();
+        for value in &values {
+            partitions.push(value).unwrap();
+        }
+        let mut read: Vec<String> = partitions
+            .into_iter()
+            .unwrap()
+            .flat_map(Result::unwrap)
+            .collect();
+        read.sort_by(|a, b| total_cmp(a, b, SortOrder::Natural));
+        assert_eq!(read, values);
+    }
+}
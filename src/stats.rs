@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::cli::StatsArgs;
+use crate::parse::load_column;
+
+#[derive(Debug, PartialEq)]
+struct Stats {
+    values: usize,
+    unique: usize,
+    empty: usize,
+    duplicated: usize,
+}
+
+/// Number of most duplicated values listed in summaries.
+const TOP_DUPLICATES: usize = 5;
+
+fn value_counts<'a>(values: impl IntoIterator<Item = &'a str>) -> HashMap<&'a str, usize> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for value in values {
+        *counts.entry(value).or_default() += 1;
+    }
+    counts
+}
+
+/// Distinct values with their number of occurrences, most frequent first.
+pub fn ranked_values<'a>(values: impl IntoIterator<Item = &'a str>) -> Vec<(&'a str, usize)> {
+    let mut ranked: Vec<_> = value_counts(values).into_iter().collect();
+    ranked.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    ranked
+}
+
+fn column_stats(values: &[String]) -> Stats {
+    let counts = value_counts(values.iter().map(String::as_str));
+    Stats {
+        values: values.len(),
+        unique: counts.len(),
+        empty: counts.get("").copied().unwrap_or(0),
+        duplicated: counts.values().filter(|&&count| count > 1).count(),
+    }
+}
+
+/// The `n` values occurring most often (more than once), most frequent first.
+fn top_duplicates(values: &[String], n: usize) -> Vec<(&str, usize)> {
+    ranked_values(values.iter().map(String::as_str))
+        .into_iter()
+        .take_while(|&(_, count)| count > 1)
+        .take(n)
+        .collect()
+}
+
+/// Prints the `n` most frequent of the `kind` (added, removed) values.
+pub fn print_top_values(kind: &str, values: &[&str], n: usize) {
+    println!("top {kind} values:");
+    for (value, count) in ranked_values(values.iter().copied()).into_iter().take(n) {
+        println!("  {value:?}: {count} times");
+    }
+}
+
+fn print_top_duplicates(values: &[String]) {
+    for (value, count) in top_duplicates(values, TOP_DUPLICATES) {
+        println!("  {value:?}: {count} times");
+    }
+}
+
+/// Prints how many values of a compared column are duplicated, with the top
+/// offenders.
+pub fn print_duplicates(name: &str, values: &[String]) {
+    let stats = column_stats(values);
+    println!(
+        "{name}: {} values, {} unique, {} duplicated",
+        stats.values, stats.unique, stats.duplicated
+    );
+    print_top_duplicates(values);
+}
+
+pub fn run(args: &StatsArgs) -> Result<()> {
+    let values = load_column(&args.parse, &args.file, args.index)?;
+    let stats = column_stats(&values);
+
+    println!("file: {}", args.file.display());
+    println!("values: {}", stats.values);
+    println!("unique values: {}", stats.unique);
+    println!("empty values: {}", stats.empty);
+    println!("duplicated values: {}", stats.duplicated);
+    print_top_duplicates(&values);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_column_stats() {
+        let values: Vec<String> = ["a", "b", "a", "", "c", "c", "c"]
+            .iter()
+            .map(|value| value.to_string())
+            .collect();
+        assert_eq!(
+            column_stats(&values),
+            Stats {
+                values: 7,
+                unique: 4,
+                empty: 1,
+                duplicated: 2,
+            }
+        );
+        assert_eq!(top_duplicates(&values, 5), [("c", 3), ("a", 2)]);
+        assert_eq!(top_duplicates(&values, 1), [("c", 3)]);
+    }
+}
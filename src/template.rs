@@ -0,0 +1,255 @@
+use anyhow::{Result, anyhow};
+
+use crate::json::Value;
+
+/// A parsed template: text interleaved with `{{ value | filter }}`,
+/// `{% for item in list %}…{% endfor %}` and
+/// `{% if [not] value %}…{% else %}…{% endif %}` blocks.
+#[derive(Debug, PartialEq)]
+enum Node {
+    Text(String),
+    Value(Vec<String>, Vec<String>),
+    For(String, Vec<String>, Vec<Node>),
+    If(bool, Vec<String>, Vec<Node>, Vec<Node>),
+}
+
+#[derive(Debug, PartialEq)]
+enum Tag<'a> {
+    Text(&'a str),
+    Value(&'a str),
+    Block(&'a str),
+}
+
+fn tags(template: &str) -> Result<Vec<Tag<'_>>> {
+    let mut res = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let close = match rest[start..].get(..2) {
+            Some("{{") => "}}",
+            Some("{%") => "%}",
+            Some("{#") => "#}",
+            _ => {
+                res.push(Tag::Text(&rest[..start + 1]));
+                rest = &rest[start + 1..];
+                continue;
+            }
+        };
+        res.push(Tag::Text(&rest[..start]));
+        let inner = &rest[start + 2..];
+        let end = inner
+            .find(close)
+            .ok_or_else(|| anyhow!("unclosed `{}` in template", &rest[start..start + 2]))?;
+        match close {
+            "}}" => res.push(Tag::Value(inner[..end].trim())),
+            "%}" => res.push(Tag::Block(inner[..end].trim())),
+            _ => {}
+        }
+        rest = &inner[end + 2..];
+    }
+    res.push(Tag::Text(rest));
+    Ok(res)
+}
+
+fn path(expr: &str) -> Vec<String> {
+    expr.split('.')
+        .map(|part| part.trim().to_string())
+        .collect()
+}
+
+/// Parses nodes up to one of the `until` block tags, which is returned.
+fn parse_nodes<'a>(
+    tags: &mut impl Iterator<Item = Tag<'a>>,
+    until: &[&str],
+) -> Result<(Vec<Node>, Option<&'a str>)> {
+    let mut nodes = Vec::new();
+    while let Some(tag) = tags.next() {
+        match tag {
+            Tag::Text(text) if !text.is_empty() => nodes.push(Node::Text(text.to_string())),
+            Tag::Text(_) => {}
+            Tag::Value(expr) => {
+                let mut parts = expr.split('|');
+                let value = path(parts.next().unwrap_or(""));
+                let filters = parts.map(|filter| filter.trim().to_string()).collect();
+                nodes.push(Node::Value(value, filters));
+            }
+            Tag::Block(block) if until.contains(&block) => return Ok((nodes, Some(block))),
+            Tag::Block(block) => {
+                let words: Vec<&str> = block.split_whitespace().collect();
+                match words.as_slice() {
+                    ["for", name, "in", list] => {
+                        let (body, end) = parse_nodes(tags, &["endfor"])?;
+                        end.ok_or_else(|| anyhow!("missing `{{% endfor %}}`"))?;
+                        nodes.push(Node::For(name.to_string(), path(list), body));
+                    }
+                    ["if", rest @ ..] => {
+                        let (negated, cond) = match rest {
+                            ["not", cond] => (true, cond),
+                            [cond] => (false, cond),
+                            _ => return Err(anyhow!("invalid condition `{block}`")),
+                        };
+                        let (then, end) = parse_nodes(tags, &["else", "endif"])?;
+                        let otherwise = match end {
+                            Some("else") => {
+                                let (otherwise, end) = parse_nodes(tags, &["endif"])?;
+                                end.ok_or_else(|| anyhow!("missing `{{% endif %}}`"))?;
+                                otherwise
+                            }
+                            Some(_) => Vec::new(),
+                            None => return Err(anyhow!("missing `{{% endif %}}`")),
+                        };
+                        nodes.push(Node::If(negated, path(cond), then, otherwise));
+                    }
+                    _ => return Err(anyhow!("unknown template tag `{block}`")),
+                }
+            }
+        }
+    }
+    Ok((nodes, None))
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(value) => *value,
+        Value::Number(number) => *number != 0.0,
+        Value::String(text) => !text.is_empty(),
+        Value::Array(values) => !values.is_empty(),
+        Value::Object(_) => true,
+    }
+}
+
+fn text(value: &Value) -> Result<String> {
+    Ok(match value {
+        Value::Null => String::new(),
+        Value::Bool(value) => value.to_string(),
+        Value::Number(number) if number.fract() == 0.0 => format!("{number:.0}"),
+        Value::Number(number) => number.to_string(),
+        Value::String(text) => text.clone(),
+        Value::Array(_) | Value::Object(_) => {
+            return Err(anyhow!("cannot print a list or object, iterate over it"));
+        }
+    })
+}
+
+fn apply_filter(value: Value, filter: &str) -> Result<Value> {
+    Ok(match filter {
+        "length" => Value::Number(match &value {
+            Value::Array(values) => values.len(),
+            Value::Object(entries) => entries.len(),
+            value => text(value)?.chars().count(),
+        } as f64),
+        "upper" => Value::String(text(&value)?.to_uppercase()),
+        "lower" => Value::String(text(&value)?.to_lowercase()),
+        _ => return Err(anyhow!("unknown template filter `{filter}`")),
+    })
+}
+
+struct Renderer<'a> {
+    /// Loop variables, innermost last, looked up before the context.
+    scopes: Vec<(String, Value)>,
+    context: &'a Value,
+}
+
+impl Renderer<'_> {
+    fn lookup(&self, path: &[String]) -> Result<Value> {
+        let (first, rest) = path.split_first().ok_or_else(|| anyhow!("empty name"))?;
+        let mut value = match self.scopes.iter().rev().find(|(name, _)| name == first) {
+            Some((_, value)) => value,
+            None => self
+                .context
+                .get(first)
+                .ok_or_else(|| anyhow!("unknown template value `{first}`"))?,
+        };
+        for part in rest {
+            value = value
+                .get(part)
+                .ok_or_else(|| anyhow!("unknown template value `{}`", path.join(".")))?;
+        }
+        Ok(value.clone())
+    }
+
+    fn render(&mut self, nodes: &[Node], output: &mut String) -> Result<()> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => output.push_str(text),
+                Node::Value(path, filters) => {
+                    let mut value = self.lookup(path)?;
+                    for filter in filters {
+                        value = apply_filter(value, filter)?;
+                    }
+                    output.push_str(&text(&value)?);
+                }
+                Node::For(name, path, body) => {
+                    let Value::Array(items) = self.lookup(path)? else {
+                        return Err(anyhow!("`{}` is not a list", path.join(".")));
+                    };
+                    for (idx, item) in items.into_iter().enumerate() {
+                        let info = Value::Object(vec![
+                            ("index".to_string(), Value::Number((idx + 1) as f64)),
+                            ("first".to_string(), Value::Bool(idx == 0)),
+                        ]);
+                        self.scopes.push(("loop".to_string(), info));
+                        self.scopes.push((name.clone(), item));
+                        let res = self.render(body, output);
+                        self.scopes.truncate(self.scopes.len() - 2);
+                        res?;
+                    }
+                }
+                Node::If(negated, path, then, otherwise) => {
+                    let cond = self.lookup(path).map(|value| truthy(&value))?;
+                    let body = if cond != *negated { then } else { otherwise };
+                    self.render(body, output)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Renders `template` with the values of the `context` object.
+pub fn render(template: &str, context: &Value) -> Result<String> {
+    let (nodes, end) = parse_nodes(&mut tags(template)?.into_iter(), &[])?;
+    if let Some(end) = end {
+        return Err(anyhow!("unexpected `{{% {end} %}}`"));
+    }
+    let mut output = String::new();
+    Renderer {
+        scopes: Vec::new(),
+        context,
+    }
+    .render(&nodes, &mut output)?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let context = crate::json::parse(
+            r#"{"name": "orders", "identical": false, "count": 2,
+                "values": [{"v": "a"}, {"v": "b"}]}"#,
+        )
+        .unwrap();
+        let render = |template: &str| render(template, &context).unwrap();
+        assert_eq!(render("{{ name | upper }}: {{ count }}"), "ORDERS: 2");
+        assert_eq!(
+            render("{% for item in values %}{{ loop.index }}={{ item.v }} {% endfor %}"),
+            "1=a 2=b "
+        );
+        assert_eq!(
+            render("{% if identical %}same{% else %}{{ values | length }} changes{% endif %}"),
+            "2 changes"
+        );
+        assert_eq!(
+            render("{% if not identical %}x{% endif %}{# note #}{ y }"),
+            "x{ y }"
+        );
+
+        assert!(super::render("{{ missing }}", &context).is_err());
+        assert!(super::render("{% for v in values %}", &context).is_err());
+        assert!(super::render("{% endif %}", &context).is_err());
+        assert!(super::render("{{ values }}", &context).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use std::io::{self, BufRead, Read};
+
+/// How a line of text splits into fields.
+pub enum Split {
+    /// Fields of these byte widths, trimmed of their padding; bytes past the
+    /// last width are ignored.
+    Widths(Vec<usize>),
+    /// Fields separated by this byte sequence, taken verbatim.
+    Separator(Vec<u8>),
+}
+
+impl Split {
+    fn fields<'a>(&self, line: &'a [u8]) -> Vec<&'a [u8]> {
+        match self {
+            Self::Widths(widths) => {
+                let mut start = 0;
+                widths
+                    .iter()
+                    .map(|width| {
+                        let end = (start + width).min(line.len());
+                        let field = line[start.min(end)..end].trim_ascii();
+                        start = end;
+                        field
+                    })
+                    .collect()
+            }
+            Self::Separator(separator) => {
+                let mut fields = Vec::new();
+                let mut rest = line;
+                while let Some(pos) = rest
+                    .windows(separator.len())
+                    .position(|window| window == separator)
+                {
+                    fields.push(&rest[..pos]);
+                    rest = &rest[pos + separator.len()..];
+                }
+                fields.push(rest);
+                fields
+            }
+        }
+    }
+}
+
+/// Reads the lines of `inner` as CSV records delimited by `delimiter`, one
+/// per line, for input formats the CSV reader cannot parse itself.
+pub struct Tokenized<R> {
+    inner: R,
+    split: Split,
+    delimiter: u8,
+    line: Vec<u8>,
+    record: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: BufRead> Tokenized<R> {
+    pub fn new(inner: R, split: Split, delimiter: u8) -> Self {
+        Self {
+            inner,
+            split,
+            delimiter,
+            line: Vec::new(),
+            record: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// Encodes the next line as a CSV record, returning whether there was one.
+    fn next_record(&mut self) -> io::Result<bool> {
+        self.line.clear();
+        self.record.clear();
+        self.pos = 0;
+        if self.inner.read_until(b'\n', &mut self.line)? == 0 {
+            return Ok(false);
+        }
+        let line = self.line.strip_suffix(b"\n").unwrap_or(&self.line);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if !line.is_empty() {
+            for (idx, field) in self.split.fields(line).into_iter().enumerate() {
+                if idx > 0 {
+                    self.record.push(self.delimiter);
+                }
+                let special = |byte: &u8| [self.delimiter, b'"', b'\r', b'\n'].contains(byte);
+                if field.iter().any(special) {
+                    self.record.push(b'"');
+                    for &byte in field {
+                        if byte == b'"' {
+                            self.record.push(b'"');
+                        }
+                        self.record.push(byte);
+                    }
+                    self.record.push(b'"');
+                } else {
+                    self.record.extend_from_slice(field);
+                }
+            }
+        }
+        self.record.push(b'\n');
+        Ok(true)
+    }
+}
+
+impl<R: BufRead> Read for Tokenized<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.record.len() && !self.next_record()? {
+            return Ok(0);
+        }
+        let len = buf.len().min(self.record.len() - self.pos);
+        buf[..len].copy_from_slice(&self.record[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokenized(data: &[u8], split: Split) -> String {
+        let mut csv = String::new();
+        Tokenized::new(data, split, b',')
+            .read_to_string(&mut csv)
+            .unwrap();
+        csv
+    }
+
+    #[test]
+    fn test_tokenized() {
+        let data = b"0001Smith, J  42.50\r\n\n0002O\"Neil    7\n0003X";
+        assert_eq!(
+            tokenized(data, Split::Widths(vec![4, 10, 5])),
+            "0001,\"Smith, J\",42.50\n\n0002,\"O\"\"Neil\",7\n0003,X,\n"
+        );
+        let data = b"1~|~a, b~|~\n2~|~\"c\"~|~~|\n";
+        assert_eq!(
+            tokenized(data, Split::Separator(b"~|~".to_vec())),
+            "1,\"a, b\",\n2,\"\"\"c\"\"\",~|\n"
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{Result, anyhow};
+use tempfile::{Builder, NamedTempFile};
+
+use crate::transform::shell_command;
+
+/// Writes `values` one per line to a temporary file named after `path`, with
+/// characters other than letters, digits, `.`, `-` and `_` replaced.
+fn column_file(path: &Path, side: &str, values: &[String]) -> Result<NamedTempFile> {
+    let name: String = path
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let file = Builder::new()
+        .prefix(&format!("{name}.{side}."))
+        .suffix(".txt")
+        .tempfile()?;
+    let mut writer = BufWriter::new(file.as_file());
+    for value in values {
+        writeln!(writer, "{value}")?;
+    }
+    writer.flush()?;
+    drop(writer);
+    Ok(file)
+}
+
+/// The shell command line `tool`, run with the `files` as its last two
+/// arguments. `sh` gets them as positional parameters, so they are never
+/// parsed as shell code.
+fn tool_command(tool: &str, files: [&Path; 2]) -> Command {
+    if cfg!(windows) {
+        // `cmd` has no positional parameters; Windows file names cannot
+        // contain `"`, and column files contain no `%`.
+        let [orig, diff] = files.map(Path::display);
+        shell_command(&format!("{tool} \"{orig}\" \"{diff}\""))
+    } else {
+        let mut command = shell_command(&format!("{tool} \"$1\" \"$2\""));
+        command.arg("csv-compare").args(files);
+        command
+    }
+}
+
+/// Opens the extracted and normalized columns in an external diff tool such
+/// as `meld` or `vimdiff`, invoked as `<tool> <orig file> <diff file>`.
+pub fn launch(
+    tool: &str,
+    paths: (&Path, &Path),
+    orig_lines: &[String],
+    diff_lines: &[String],
+) -> Result<()> {
+    let orig = column_file(paths.0, "orig", orig_lines)?;
+    let diff = column_file(paths.1, "diff", diff_lines)?;
+    // Tools like `diff` exit with 1 when the inputs differ, so only a failure
+    // to start is an error.
+    tool_command(tool, [orig.path(), diff.path()])
+        .status()
+        .map_err(|err| anyhow!("Failed to run diff tool `{tool}`: {err}"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_launch() {
+        let out = assert_fs::NamedTempFile::new("out.txt").unwrap();
+        let orig = vec!["a".to_string(), "b".to_string()];
+        let diff = vec!["c".to_string()];
+        let tool = format!("cat > \"{}\"", out.path().display());
+        launch(
+            &tool,
+            (Path::new("orig.csv"), Path::new("diff.csv")),
+            &orig,
+            &diff,
+        )
+        .unwrap();
+        assert_eq!(std::fs::read_to_string(out.path()).unwrap(), "a\nb\nc\n");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_launch_hostile_name() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let out = dir.path().join("out.txt");
+        let marker = dir.path().join("pwned");
+        let name = format!(
+            "a$(touch {0})`touch {0}`\";touch {0};\".csv",
+            marker.display()
+        );
+        let tool = format!("cat > \"{}\"", out.display());
+        let values = vec!["a".to_string()];
+        launch(
+            &tool,
+            (Path::new(&name), Path::new(&name)),
+            &values,
+            &values,
+        )
+        .unwrap();
+        assert!(!marker.exists());
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "a\na\n");
+        assert_eq!(
+            tool_command("x", [Path::new("$(a)"), Path::new("`b`")])
+                .get_args()
+                .collect::<Vec<_>>(),
+            ["-c", "x \"$1\" \"$2\"", "csv-compare", "$(a)", "`b`"]
+        );
+    }
+}
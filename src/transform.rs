@@ -0,0 +1,82 @@
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+
+use anyhow::{Result, anyhow};
+
+pub fn shell_command(cmd: &str) -> Command {
+    if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", cmd]);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.args(["-c", cmd]);
+        command
+    }
+}
+
+/// Pipes `values` through `cmd`, one per line, and reads back as many lines.
+/// Values spanning lines cannot be told apart in the output and are rejected.
+pub fn transform_values(cmd: &str, values: Vec<String>) -> Result<Vec<String>> {
+    if let Some(value) = values.iter().find(|value| value.contains(['\n', '\r'])) {
+        return Err(anyhow!(
+            "Transform command `{cmd}` cannot take the multi-line value {value:?}"
+        ));
+    }
+    let count = values.len();
+    let mut child = shell_command(cmd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|err| anyhow!("Failed to run transform command `{cmd}`: {err}"))?;
+
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = thread::spawn(move || -> std::io::Result<()> {
+        for value in values {
+            writeln!(stdin, "{value}")?;
+        }
+        Ok(())
+    });
+
+    let mut output = String::new();
+    child.stdout.take().unwrap().read_to_string(&mut output)?;
+    let status = child.wait()?;
+    let written = writer.join().unwrap();
+
+    if !status.success() {
+        return Err(anyhow!("Transform command `{cmd}` exited with {status}"));
+    }
+    // A command exiting before reading all values breaks the pipe; the line
+    // count tells more than the write error.
+    let output: Vec<String> = output.lines().map(str::to_string).collect();
+    if output.len() != count {
+        return Err(anyhow!(
+            "Transform command `{cmd}` printed {} lines for {count} values",
+            output.len()
+        ));
+    }
+    written?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_transform_values() {
+        let values = vec!["a".to_string(), "B".to_string()];
+        let output = transform_values("tr a-z A-Z", values).unwrap();
+        assert_eq!(output, vec!["A", "B"]);
+
+        assert!(transform_values("exit 1", vec![]).is_err());
+        assert!(transform_values("cat", vec!["a\nb".to_string()]).is_err());
+        let err = transform_values("head -n 1", vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "Transform command `head -n 1` printed 1 lines for 2 values"
+        );
+    }
+}
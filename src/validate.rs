@@ -0,0 +1,230 @@
+use std::fs;
+
+use anyhow::{Result, anyhow};
+use regex_automata::meta::Regex;
+
+use crate::cli::ValidateArgs;
+use crate::errors::Category;
+use crate::json::{self, Value};
+use crate::parse::{Table, normalize_bool, read_table};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColumnType {
+    String,
+    Integer,
+    Number,
+    Boolean,
+}
+
+impl ColumnType {
+    fn name(self) -> &'static str {
+        match self {
+            ColumnType::String => "string",
+            ColumnType::Integer => "integer",
+            ColumnType::Number => "number",
+            ColumnType::Boolean => "boolean",
+        }
+    }
+
+    fn matches(self, value: &str) -> bool {
+        match self {
+            ColumnType::String => true,
+            ColumnType::Integer => value.trim().parse::<i64>().is_ok(),
+            ColumnType::Number => value.trim().parse::<f64>().is_ok(),
+            ColumnType::Boolean => {
+                matches!(normalize_bool(value.to_string()).as_str(), "true" | "false")
+            }
+        }
+    }
+}
+
+struct ColumnRule {
+    name: String,
+    column_type: ColumnType,
+    required: bool,
+    pattern: Option<(String, Regex)>,
+}
+
+/// Expected layout of a CSV file:
+///
+/// ```json
+/// {"columns": [{"name": "id", "type": "integer", "required": true, "pattern": "^[0-9]+$"}]}
+/// ```
+///
+/// Columns are looked up by header name when the file has headers, and by
+/// their position in the schema otherwise.
+struct Schema {
+    columns: Vec<ColumnRule>,
+}
+
+impl Schema {
+    fn parse(input: &str) -> Result<Self> {
+        let doc = json::parse(input)?;
+        let columns = doc
+            .get("columns")
+            .and_then(Value::as_array)
+            .ok_or_else(|| anyhow!("Schema must contain a `columns` array"))?;
+        let columns = columns
+            .iter()
+            .map(|column| {
+                let name = column
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| anyhow!("Schema column is missing a `name`"))?;
+                let column_type = match column.get("type").and_then(Value::as_str) {
+                    None | Some("string") => ColumnType::String,
+                    Some("integer") => ColumnType::Integer,
+                    Some("number") => ColumnType::Number,
+                    Some("boolean") => ColumnType::Boolean,
+                    Some(other) => return Err(anyhow!("Unknown column type `{other}`")),
+                };
+                let pattern = match column.get("pattern").and_then(Value::as_str) {
+                    Some(pattern) => Some((pattern.to_string(), Regex::new(pattern)?)),
+                    None => None,
+                };
+                Ok(ColumnRule {
+                    name: name.to_string(),
+                    column_type,
+                    required: column
+                        .get("required")
+                        .and_then(Value::as_bool)
+                        .unwrap_or(false),
+                    pattern,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { columns })
+    }
+}
+
+#[derive(Debug)]
+struct Violation {
+    line: u64,
+    column: String,
+    message: String,
+}
+
+fn validate(schema: &Schema, table: &Table) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let mut positions = Vec::with_capacity(schema.columns.len());
+    for (idx, rule) in schema.columns.iter().enumerate() {
+        let position = match &table.headers {
+            Some(headers) => headers.iter().position(|name| name == rule.name),
+            None => Some(idx),
+        };
+        if position.is_none() {
+            violations.push(Violation {
+                line: 1,
+                column: rule.name.clone(),
+                message: "missing column".to_string(),
+            });
+        }
+        positions.push(position);
+    }
+
+    for record in &table.records {
+        let line = record.position().map_or(0, |pos| pos.line());
+        for (rule, position) in schema.columns.iter().zip(&positions) {
+            let Some(position) = position else {
+                continue;
+            };
+            let mut violation = |message: String| {
+                violations.push(Violation {
+                    line,
+                    column: rule.name.clone(),
+                    message,
+                })
+            };
+            let value = record.get(*position).unwrap_or("");
+            if value.is_empty() {
+                if rule.required {
+                    violation("required value is empty".to_string());
+                }
+                continue;
+            }
+            if !rule.column_type.matches(value) {
+                violation(format!(
+                    "expected {}, got {value:?}",
+                    rule.column_type.name()
+                ));
+            }
+            if let Some((pattern, regex)) = &rule.pattern
+                && !regex.is_match(value)
+            {
+                violation(format!("{value:?} does not match pattern {pattern:?}"));
+            }
+        }
+    }
+    violations
+}
+
+pub fn run(args: &ValidateArgs) -> Result<()> {
+    let schema = Schema::parse(&fs::read_to_string(&args.schema)?)?;
+    let table = read_table(&args.file, &args.reader)?;
+    let violations = validate(&schema, &table);
+
+    for Violation {
+        line,
+        column,
+        message,
+    } in &violations
+    {
+        println!(
+            "{}:{line}: column `{column}`: {message}",
+            args.file.display()
+        );
+    }
+    if !violations.is_empty() {
+        return Err(
+            Category::ThresholdExceeded.error(format!("{} violations found", violations.len()))
+        );
+    }
+    println!("{}: OK", args.file.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::FileWriteStr;
+
+    use crate::cli::ReaderArgs;
+
+    #[test]
+    fn test_validate() {
+        let schema = Schema::parse(
+            r#"{"columns": [
+                {"name": "id", "type": "integer", "required": true},
+                {"name": "code", "pattern": "^[A-Z]{2}$"},
+                {"name": "active", "type": "boolean"},
+                {"name": "missing"}
+            ]}"#,
+        )
+        .unwrap();
+        let file = assert_fs::NamedTempFile::new("input.csv").unwrap();
+        file.write_str("id,active,code\n1,yes,EU\nx,no,eu\n,maybe,")
+            .unwrap();
+        let reader = ReaderArgs {
+            with_headers: true,
+            ..Default::default()
+        };
+        let table = read_table(file.path(), &reader).unwrap();
+
+        let violations: Vec<_> = validate(&schema, &table)
+            .into_iter()
+            .map(|violation| (violation.line, violation.column))
+            .collect();
+        assert_eq!(
+            violations,
+            [
+                (1, "missing".to_string()),
+                (3, "id".to_string()),
+                (3, "code".to_string()),
+                (4, "id".to_string()),
+                (4, "active".to_string()),
+            ]
+        );
+
+        assert!(Schema::parse(r#"{"columns": [{"name": "a", "type": "date"}]}"#).is_err());
+    }
+}
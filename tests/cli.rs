@@ -0,0 +1,348 @@
+use std::process::Command;
+
+use assert_fs::TempDir;
+use assert_fs::prelude::{FileWriteStr, PathChild};
+
+/// Runs the binary with `args` after the `orig` and `diff` CSV texts, written
+/// to a temporary directory, and returns whether it succeeded and its output.
+fn compare(orig: &str, diff: &str, args: &[&str]) -> (bool, String) {
+    let dir = TempDir::new().unwrap();
+    dir.child("orig.csv").write_str(orig).unwrap();
+    dir.child("diff.csv").write_str(diff).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_csv-compare"))
+        .current_dir(dir.path())
+        .args(["orig.csv", "diff.csv", "-y"])
+        .args(args)
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn test_format_gitlab_codequality() {
+    let args = ["-o", "1", "--format", "gitlab-codequality"];
+    let (ok, output) = compare("a\nb\n", "a\nb\n", &args);
+    assert!(ok);
+    assert_eq!(output.trim(), "[]");
+
+    let (ok, output) = compare("a\nb\n", "a\nc\n", &args);
+    assert!(!ok);
+    assert!(output.starts_with('['), "{output}");
+    assert!(
+        output.contains("\"check_name\":\"csv-compare/removed\""),
+        "{output}"
+    );
+    assert!(
+        output.contains("\"check_name\":\"csv-compare/added\""),
+        "{output}"
+    );
+}
+
+#[test]
+fn test_format_prometheus() {
+    let args = ["-o", "1", "--format", "prometheus"];
+    let (ok, output) = compare("a\nb\n", "a\nb\n", &args);
+    assert!(ok);
+    assert!(
+        output.contains("csv_compare_identical{orig=\"a/orig.csv\",diff=\"b/diff.csv\"} 1"),
+        "{output}"
+    );
+    assert!(
+        output.contains("csv_compare_rows_changed{orig=\"a/orig.csv\",diff=\"b/diff.csv\"} 0"),
+        "{output}"
+    );
+
+    let (_, output) = compare("a\nb\n", "a\nc\n", &args);
+    assert!(
+        output.contains("csv_compare_identical{orig=\"a/orig.csv\",diff=\"b/diff.csv\"} 0"),
+        "{output}"
+    );
+}
+
+#[test]
+fn test_counts_only() {
+    let (ok, output) = compare("a\nb\n", "a\nb\n", &["-o", "1", "--counts-only"]);
+    assert!(ok);
+    assert_eq!(output, "removed: 0\nadded: 0\nchanged: 0\n");
+
+    let (_, output) = compare("a\nb\n", "c\nb\nd\n", &["-o", "1", "--counts-only"]);
+    assert_eq!(output, "removed: 0\nadded: 1\nchanged: 1\n");
+}
+
+#[test]
+fn test_summary() {
+    let (ok, output) = compare("a\na\nb\n", "a\na\nb\n", &["-o", "1", "--summary"]);
+    assert!(ok);
+    assert!(
+        output.contains("orig.csv: 3 values, 2 unique, 1 duplicated"),
+        "{output}"
+    );
+    assert!(
+        output.contains("diff.csv: 3 values, 2 unique, 1 duplicated"),
+        "{output}"
+    );
+}
+
+#[test]
+fn test_reports_on_identical_inputs() {
+    let dir = TempDir::new().unwrap();
+    let template = dir.child("report.txt");
+    template.write_str("identical: {{ identical }}\n").unwrap();
+    let template = template.path().to_str().unwrap();
+    let (ok, output) = compare("a\nb\n", "a\nb\n", &["-o", "1", "--template", template]);
+    assert!(ok);
+    assert_eq!(output, "identical: true\n");
+
+    let (_, output) = compare("a\nb\n", "a\nb\n", &["-o", "1", "--checksums"]);
+    assert!(output.contains("orig.csv: "), "{output}");
+    assert!(output.contains("diff.csv: "), "{output}");
+
+    let (_, output) = compare("a\nb\n", "a\nb\n", &["-o", "1", "--top", "3"]);
+    assert!(output.contains("top removed values:"), "{output}");
+
+    let html = dir.child("report.html");
+    let html_path = html.path().to_str().unwrap();
+    compare("a\nb\n", "a\nb\n", &["-o", "1", "--html", html_path]);
+    assert!(html.path().exists());
+}
+
+#[test]
+fn test_sort_by_line_numbers() {
+    let args = ["-o", "2", "--sort-by", "1", "--line-numbers"];
+    let (ok, output) = compare("3,z\n1,a\n2,b\n", "1,a\n", &args);
+    assert!(!ok);
+    assert!(output.contains("- [orig:3] b\n"), "{output}");
+    assert!(output.contains("- [orig:1] z\n"), "{output}");
+}
+
+#[test]
+fn test_apply_and_report() {
+    let dir = TempDir::new().unwrap();
+    dir.child("orig.csv").write_str("1,a\n2,b\n3,c\n").unwrap();
+    dir.child("diff.csv")
+        .write_str("1,a\n2,B\n3,c\n4,d\n")
+        .unwrap();
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_csv-compare"))
+            .current_dir(dir.path())
+            .args(args)
+            .env("NO_COLOR", "1")
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8(output.stdout).unwrap()
+    };
+    // Exits with failure, as the columns differ.
+    let status = Command::new(env!("CARGO_BIN_EXE_csv-compare"))
+        .current_dir(dir.path())
+        .args([
+            "orig.csv",
+            "diff.csv",
+            "-o",
+            "2",
+            "-y",
+            "--patch-output",
+            "p.diff",
+        ])
+        .output()
+        .unwrap()
+        .status;
+    assert_eq!(status.code(), Some(1));
+    assert_eq!(
+        run(&["apply", "p.diff", "orig.csv", "-i", "2"]),
+        "a\nB\nc\nd\n"
+    );
+
+    // Identical inputs still write a patch, which changes nothing.
+    dir.child("same.csv").write_str("1,a\n2,b\n3,c\n").unwrap();
+    run(&[
+        "orig.csv",
+        "same.csv",
+        "-o",
+        "2",
+        "-y",
+        "--patch-output",
+        "same.diff",
+    ]);
+    assert_eq!(
+        run(&["apply", "same.diff", "orig.csv", "-i", "2"]),
+        "a\nb\nc\n"
+    );
+
+    let result = csv_compare::api::compare(
+        "a\nb\nc\n",
+        "a\nB\nc\n",
+        &csv_compare::json::parse(r#"{"orig_index": 1}"#).unwrap(),
+    )
+    .unwrap();
+    dir.child("result.json")
+        .write_str(&result.to_string())
+        .unwrap();
+    let report = run(&["report", "result.json"]);
+    assert!(
+        report.contains("@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n"),
+        "{report}"
+    );
+    dir.child("t.txt")
+        .write_str("changed: {{ counts.changed }}\n")
+        .unwrap();
+    assert_eq!(
+        run(&["report", "result.json", "--template", "t.txt"]),
+        "changed: 1\n"
+    );
+}
+
+#[test]
+fn test_emit_rows_with_keys() {
+    let orig = "id,name\n1,a\n2,b\n3,c\n";
+    let diff = "id,name\n1,a\n3,C\n4,d\n";
+    let args = ["--with-headers", "--join", "1", "-o", "2", "--emit"];
+    let emit = |op| {
+        let (ok, output) = compare(orig, diff, &[&args[..], &[op]].concat());
+        assert!(ok);
+        output
+    };
+    assert_eq!(emit("only-orig"), "id,name\n2,b\n");
+    assert_eq!(emit("only-diff"), "id,name\n4,d\n");
+    assert_eq!(emit("intersection"), "id,name\n1,a\n3,c\n");
+    assert_eq!(emit("union"), "id,name\n1,a\n2,b\n3,c\n4,d\n");
+}
+
+#[test]
+fn test_join_keys_by_header() {
+    let orig = "id,price\n1,2\n2,3\n";
+    let diff = "price,id\n2,1\n4,2\n";
+    let (ok, output) = compare(orig, diff, &["--with-headers", "--join", "1"]);
+    assert!(ok);
+    assert!(output.contains("2: price: 3 -> 4"), "{output}");
+    assert!(output.contains("2 matched keys, 1 changed"), "{output}");
+}
+
+#[test]
+fn test_brief() {
+    let (ok, output) = compare("a\nb\n", "a\nc\n", &["-o", "1", "--brief"]);
+    assert!(!ok);
+    assert_eq!(output, "Files orig.csv and diff.csv differ\n");
+
+    let (ok, output) = compare("a,1\nb,2\n", "a,3\nb,4\n", &["-o", "1", "-q"]);
+    assert!(ok);
+    assert_eq!(output, "");
+}
+
+#[test]
+fn test_changes_only() {
+    let (ok, output) = compare(
+        "a\nb\nc\nd\n",
+        "a\nB\nc\nd\ne\n",
+        &["-o", "1", "--changes-only"],
+    );
+    assert!(!ok);
+    let hunk = output.split_once("@@ -1,4 +1,5 @@\n").unwrap().1;
+    assert_eq!(hunk, "-b\n+B\n+e\n");
+}
+
+#[test]
+fn test_row_diff() {
+    let orig = "id,name,city\n1,\"Smith, J\",Oslo\n2,b,Rome\n";
+    let diff = "id,name,city\n1,\"Smith, J\",Bergen\n2,b,Rome\n";
+    let (ok, output) = compare(orig, diff, &["--with-headers", "--join", "1", "--row-diff"]);
+    assert!(ok);
+    assert!(
+        output.starts_with("1\n-\"Smith, J\",Oslo\n+\"Smith, J\",Bergen\n"),
+        "{output}"
+    );
+    assert!(output.contains("2 matched keys, 1 changed"), "{output}");
+}
+
+#[test]
+fn test_report_identical() {
+    let (ok, output) = compare("a,1\nb,2\n", "a,3\nb,4\n", &["-o", "1", "-s"]);
+    assert!(ok);
+    assert_eq!(output, "Columns of orig.csv and diff.csv are identical\n");
+
+    let (_, output) = compare("a,1\n", "a,3\n", &["-o", "2", "--report-identical"]);
+    assert!(!output.contains("identical"), "{output}");
+    assert!(output.contains("-1\n+3\n"), "{output}");
+}
+
+#[test]
+fn test_print0() {
+    let (ok, output) = compare(
+        "1,a b\n2,\"c\nd\"\n",
+        "1,a b\n2,e\n",
+        &["-o", "2", "--print0"],
+    );
+    assert!(!ok);
+    assert_eq!(output, "c\nd\0e\0");
+}
+
+#[test]
+fn test_deterministic() {
+    let args = ["-o", "1", "--emit", "only-diff", "--deterministic"];
+    let (ok, output) = compare("a\n", "z\ny\na\nm\n", &args);
+    assert!(!ok);
+    assert!(output.starts_with("m\ny\nz\n"), "{output}");
+
+    let args = ["--join", "1", "-o", "2", "--deterministic"];
+    let (_, output) = compare("b,1\na,1\n", "a,2\nb,2\n", &args);
+    assert!(
+        output.starts_with("a: 1 -> 2 (+1)\nb: 1 -> 2 (+1)\n"),
+        "{output}"
+    );
+}
+
+#[test]
+fn test_show_matching() {
+    let orig = "id-1\nid-2\nx-1\nid-3\n";
+    let diff = "id-1\nid-9\nx-2\nid-3\n";
+    let (ok, output) = compare(orig, diff, &["-o", "1", "--show-matching", "^x-"]);
+    assert!(!ok);
+    assert!(output.contains("-x-1\n+x-2\n"), "{output}");
+    assert!(!output.contains("id-2"), "{output}");
+    assert!(!output.contains("id-9"), "{output}");
+
+    let args = ["-o", "1", "--show-matching", "^x-", "--counts-only"];
+    let (_, output) = compare(orig, diff, &args);
+    assert_eq!(output, "removed: 0\nadded: 0\nchanged: 2\n");
+}
+
+#[test]
+fn test_list() {
+    let orig = "a\nb\nc\nd\ne\nf\ng\n";
+    let diff = "a\nb\nc\nX\ne\nf\ng\nh\n";
+    let (ok, output) = compare(orig, diff, &["-o", "1", "--list"]);
+    assert!(!ok);
+    assert_eq!(output, "-d\n+X\n+h\n");
+
+    let (_, output) = compare(orig, orig, &["-o", "1", "--list"]);
+    assert_eq!(output, "");
+}
+
+#[test]
+fn test_label() {
+    let (ok, output) = compare(
+        "a\n",
+        "b\n",
+        &["-o", "1", "--label", "old", "--label", "new"],
+    );
+    assert!(!ok);
+    assert!(
+        output.starts_with("diff old new\n--- old\n+++ new\n"),
+        "{output}"
+    );
+
+    let (ok, output) = compare("a\n", "b\n", &["-o", "1", "--label", "old"]);
+    assert!(!ok);
+    assert!(
+        output.starts_with("diff old b/diff.csv\n--- old\n+++ b/diff.csv\t"),
+        "{output}"
+    );
+
+    let args = ["-o", "1", "--label", "a", "--label", "b", "--label", "c"];
+    let (ok, _) = compare("a\n", "b\n", &args);
+    assert!(!ok);
+}